@@ -0,0 +1,123 @@
+extern crate getopts;
+extern crate rustfmt_combiner;
+
+use std::collections::BTreeSet;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use getopts::Options;
+use rustfmt_combiner::{CargoPackage, Config, PRESET_NAMES, cargo_metadata, combine_file,
+                       containing_package, find_rust_files};
+
+fn make_opts() -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "show this message");
+    opts.optopt("", "manifest-path", "path to the package's Cargo.toml", "PATH");
+    opts.optflag("",
+                 "workspace",
+                 "process every member of the workspace rather than just the current package");
+    opts.optopt("",
+                "preset",
+                &format!("start from a preset configuration: {}", PRESET_NAMES.join(", ")),
+                "NAME");
+    opts
+}
+
+fn print_usage(opts: &Options) {
+    let brief = "Usage: cargo combine-imports [options]\n\nCombines the use statements in every \
+                 source file of the current package, or with --workspace, of every package in the \
+                 workspace. Source files are those of every target: libraries, binaries, tests, \
+                 examples, benchmarks and build scripts.";
+    print!("{}", opts.usage(brief));
+}
+
+// The source files of a package: everything under each target's directory, except for targets
+// (like build scripts) that live at the root of the package.
+fn package_files(package: &CargoPackage) -> io::Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    for target in &package.targets {
+        match target.src_path.parent() {
+            Some(dir) if dir != package.directory() => files.extend(find_rust_files(dir)?),
+            _ => {
+                files.insert(target.src_path.clone());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let opts = make_opts();
+    let matches = opts.parse(args).map_err(|e| e.to_string())?;
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return Ok(());
+    }
+
+    let mut config = match matches.opt_str("preset") {
+        Some(name) => Config::preset(&name).ok_or(format!("unknown preset `{}`", name))?,
+        None => Config::default(),
+    };
+    config.apply_env_overrides().map_err(|e| e.to_string())?;
+    config.validate().map_err(|e| e.to_string())?;
+
+    let manifest_path = matches.opt_str("manifest-path").map(PathBuf::from);
+    let members = cargo_metadata(manifest_path.as_ref().map(|p| p.as_path()))?;
+    let workspace = matches.opt_present("workspace");
+    let packages: Vec<&CargoPackage> = if workspace {
+        members.iter().collect()
+    } else {
+        let dir = match manifest_path {
+            Some(ref path) => path.parent().unwrap_or(Path::new(".")).canonicalize(),
+            None => env::current_dir(),
+        };
+        let dir = dir.map_err(|e| e.to_string())?;
+        vec![containing_package(&members, &dir)
+                 .ok_or(format!("no package found for {}", dir.display()))?]
+    };
+
+    // Files that can't be processed are left as they are, and reported at the end.
+    let mut failures = vec![];
+    for package in packages {
+        let files = match package_files(package) {
+            Ok(files) => files,
+            Err(e) => {
+                failures.push(format!("{}: {}", package.name, e));
+                continue;
+            }
+        };
+        // Imports may use the package names of the package's dependencies, and are grouped by
+        // where the crates they start with come from.
+        let mut config = config.clone();
+        config.crate_names = package.crate_names();
+        config.crate_kinds = package.crate_kinds(&members);
+        let mut changed = 0;
+        for path in &files {
+            match combine_file(path, &config) {
+                Ok(true) => changed += 1,
+                Ok(false) => {}
+                Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        if workspace {
+            println!("{}: {} of {} files changed", package.name, changed, files.len());
+        }
+    }
+    if !failures.is_empty() {
+        return Err(format!("couldn't process {} files:\n    {}",
+                           failures.len(),
+                           failures.join("\n    ")));
+    }
+    Ok(())
+}
+
+fn main() {
+    // Cargo passes the subcommand name through as the first argument.
+    let args: Vec<String> = env::args().skip(1).skip_while(|a| a == "combine-imports").collect();
+    if let Err(message) = run(&args) {
+        let _ = writeln!(io::stderr(), "error: {}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,62 @@
+extern crate getopts;
+extern crate lsp_server;
+extern crate rustfmt_combiner;
+extern crate serde_json;
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use getopts::Options;
+use lsp_server::Connection;
+use rustfmt_combiner::{Config, LanguageServer, PRESET_NAMES};
+
+fn make_opts() -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "show this message");
+    opts.optopt("",
+                "preset",
+                &format!("start from a preset configuration: {}", PRESET_NAMES.join(", ")),
+                "NAME");
+    opts
+}
+
+fn print_usage(opts: &Options) {
+    let brief = "Usage: combiner-lsp [options]\n\nA language server, talking over standard input \
+                 and output, with a code action that combines the use statements of a source \
+                 file. Files use the config of the nearest combiner.toml, if there is one.";
+    print!("{}", opts.usage(brief));
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let opts = make_opts();
+    let matches = opts.parse(args).map_err(|e| e.to_string())?;
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return Ok(());
+    }
+
+    let mut config = match matches.opt_str("preset") {
+        Some(name) => Config::preset(&name).ok_or(format!("unknown preset `{}`", name))?,
+        None => Config::default(),
+    };
+    config.apply_env_overrides().map_err(|e| e.to_string())?;
+    config.validate().map_err(|e| e.to_string())?;
+
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(LanguageServer::capabilities())
+        .map_err(|e| e.to_string())?;
+    connection.initialize(capabilities).map_err(|e| e.to_string())?;
+    LanguageServer::new(config).run(&connection)?;
+    // The writer thread finishes once every sender of the connection's messages is dropped.
+    drop(connection);
+    io_threads.join().map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        let _ = writeln!(io::stderr(), "error: {}", message);
+        process::exit(1);
+    }
+}
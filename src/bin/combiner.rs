@@ -0,0 +1,1057 @@
+extern crate getopts;
+extern crate notify;
+extern crate rustfmt_combiner;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate tracing;
+extern crate tracing_subscriber;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use getopts::Options;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use rustfmt_combiner::{CACHE_FILE_NAME, CONFIG_FILE_NAMES, CargoPackage, Config, FileFilter,
+                       GitChanges, Granularity, HashCache, ImportStats, JOURNAL_FILE_NAME,
+                       Journal, PRESET_NAMES, Severity, SourceEdit, apply_edits, cargo_metadata,
+                       combine_source, containing_package, content_hash, duplicate_imports,
+                       git_changed_files, find_config_file, line_column, read_file,
+                       render_diagnostic, rustc_diagnostic, source_diagnostics, source_edits,
+                       unified_diff, write_file};
+use serde_json::Value;
+use tracing::Level;
+
+// Exit statuses, besides success.
+const EXIT_CHANGES_PENDING: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+// The file argument that stands for standard input, and the name it's given in the output.
+const STDIN_ARG: &'static str = "-";
+const STDIN_NAME: &'static str = "<stdin>";
+
+// The subcommands taking files, besides the default of combining them.
+const SUBCOMMANDS: &'static [&'static str] = &["split", "normalize", "stats"];
+
+// How long to wait for changes to a file to settle before recombining it in watch mode.
+const WATCH_DEBOUNCE_MS: u64 = 300;
+
+// Whether a progress line is drawn on standard error, to be cleared before anything else is
+// written there.
+static PROGRESS_DRAWN: AtomicBool = AtomicBool::new(false);
+
+// Draw the progress line on a terminal, overwriting the last one.
+fn draw_progress(line: &str) {
+    let _ = write!(io::stderr(), "\r\x1b[K{}", line);
+    PROGRESS_DRAWN.store(true, Ordering::SeqCst);
+}
+
+// Clear the progress line, if one is drawn, so that what's written next isn't appended to it.
+// It's drawn again for the next file.
+fn clear_progress() {
+    if PROGRESS_DRAWN.swap(false, Ordering::SeqCst) {
+        let _ = write!(io::stderr(), "\r\x1b[K");
+    }
+}
+
+// Where an interactive review is up to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Review {
+    // Ask about each file.
+    Ask,
+    // Rewrite the rest of the files without asking.
+    AcceptAll,
+    // Leave the rest of the files as they are.
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    /// Rewrite files in place.
+    Overwrite,
+    /// Only list the files that would change.
+    Check,
+    /// Print a unified diff of the changes for each file, without changing it.
+    Diff,
+    /// Print the new contents of each file, without changing it.
+    Stdout,
+    /// Only report the changes, without changing any files.
+    Report,
+}
+
+fn make_opts() -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "show this message");
+    opts.optflagmulti("v",
+                      "verbose",
+                      "log why statements are (or aren't) combined to standard error; repeat for \
+                       more detail");
+    opts.optflag("",
+                 "item",
+                 "with `combiner split`, split imports into one statement per item rather than \
+                  per module");
+    opts.optflag("",
+                 "skip-tests",
+                 "leave the imports in #[cfg(test)] modules as they are (like \
+                  process_test_modules = false in a config file)");
+    opts.optflag("",
+                 "no-config",
+                 &format!("don't look for {} files", CONFIG_FILE_NAMES.join(" or ")));
+    opts.optflag("",
+                 "cargo-metadata",
+                 "run cargo metadata to find the workspace members, path dependencies and \
+                  (renamed) external crates that imports can start with, for grouping with \
+                  group_imports = \"StdExternalWorkspaceCrate\"");
+    opts.optflag("",
+                 "check",
+                 "don't modify any files, but list those that would change and exit with status \
+                  1 if there are any (also enabled by COMBINER_CHECK=1)");
+    opts.optflag("", "diff", "don't modify any files, but print a diff of the changes");
+    opts.optopt("",
+                "emit",
+                "what to emit, as in rustfmt: files (the default) to rewrite them, stdout to print \
+                 them, or checkstyle or json for a report of the changes in rustfmt's format",
+                "files|stdout|checkstyle|json");
+    opts.optflag("i",
+                 "interactive",
+                 "show the diff for each file that would change, and ask whether to rewrite it, \
+                  skip it or edit the result first");
+    opts.optflag("",
+                 "backup",
+                 &format!("save a copy of each file as <file>.orig before rewriting it, so that \
+                           `combiner undo` can restore it (recorded in {})",
+                          JOURNAL_FILE_NAME));
+    opts.optflag("",
+                 "cached",
+                 &format!("skip files that haven't changed since they were last combined, \
+                           recording the files' hashes in {}",
+                          CACHE_FILE_NAME));
+    opts.optopt("",
+                "report",
+                "print a report instead of the usual output: json for the edits made to each \
+                 file, sarif for the statements that can be combined and duplicate imports, or \
+                 rustc-json for rustc's JSON diagnostics, with suggestions that rustfix can \
+                 apply to files left as they are with --check",
+                "FORMAT");
+    opts.optopt("",
+                "since",
+                "only process files that git reports as changed relative to REF",
+                "REF");
+    opts.optflag("", "staged", "only process files with changes staged in git");
+    opts.optflag("",
+                 "watch",
+                 "after processing the files, keep watching them and recombine any that change");
+    opts.optopt("",
+                "preset",
+                &format!("start from a preset configuration for files without a config file: {}",
+                         PRESET_NAMES.join(", ")),
+                "NAME");
+    opts.optmulti("",
+                  "include",
+                  "when searching directories, only process files matching the glob (repeatable)",
+                  "GLOB");
+    opts.optmulti("",
+                  "exclude",
+                  "when searching directories, skip files matching the glob (repeatable)",
+                  "GLOB");
+    opts
+}
+
+fn print_usage(opts: &Options) {
+    let brief = "Usage: combiner [options] <file or directory>...\n       combiner split \
+                 [--item] [options] <file or directory>...\n       combiner normalize [options] \
+                 <file or directory>...\n       combiner stats [options] <file or \
+                 directory>...\n       combiner undo\n\n\
+                 Combines the use statements in each file, rewriting it in place. Directories \
+                 are searched recursively for .rs files, skipping hidden and target directories, \
+                 and files ignored by .gitignore, .ignore or .combinerignore files. With --diff, \
+                 the changes are printed as unified diffs instead, with --interactive, each \
+                 file's diff is shown and you're asked whether to rewrite it, and with --watch, \
+                 files are recombined whenever they change. With --since or --staged, the files \
+                 default to the current directory.\n\nEach file is combined using the nearest \
+                 combiner.toml or .combiner.toml in its directory or the directories above \
+                 it.\n\nA file of `-` reads the source (a whole file or just some use \
+                 statements) from standard input and writes the result to standard \
+                 output.\n\n`combiner split` does the \
+                 opposite, splitting imports into one statement per module, or with --item, per \
+                 item. `combiner normalize` rewrites them in a canonical form, with one \
+                 statement for each name imported, sorted and without duplicates.\n\n`combiner \
+                 stats` doesn't change anything, but prints statistics about the imports and how \
+                 much combining them would reduce them.\n\n\
+                 `combiner undo` restores \
+                 the files rewritten by the last run with --backup in the current \
+                 directory.\n\nFiles that can't be processed are left as they are and listed at \
+                 the end. Exits with status 1 if --check finds files that would change, and 2 on \
+                 errors.";
+    print!("{}", opts.usage(brief));
+}
+
+// The files named on the command line, with directories expanded into the (filtered) files
+// within them.
+fn find_files(args: &[String], filter: &FileFilter) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+    for arg in args {
+        let path = Path::new(arg);
+        if arg != STDIN_ARG && path.is_dir() {
+            files.extend(filter.find_rust_files(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+fn check_mode_from_env() -> bool {
+    env::var("COMBINER_CHECK").map(|v| v != "" && v != "0" && v != "false").unwrap_or(false)
+}
+
+// The settings and state of a run over some files.
+struct Processor {
+    // The config for files without a config file of their own.
+    config: Config,
+    // The granularity to split imports to, when splitting rather than combining them.
+    split: Option<Granularity>,
+    // The statistics being gathered, instead of changing anything, for `combiner stats`.
+    stats: Option<ImportStats>,
+    // Whether test modules are left alone, whatever the config says.
+    skip_tests: bool,
+    // Whether to look for config files, and the configs loaded from them.
+    discover_configs: bool,
+    configs: BTreeMap<PathBuf, Config>,
+    // The workspace's members from `cargo metadata`, if the crates imports start with are being
+    // classified with it.
+    packages: Vec<CargoPackage>,
+    mode: Mode,
+    // Where files are recorded as they're backed up, if they are.
+    journal: Option<Journal>,
+    // The state of the review, if each file's changes are being reviewed before they're made.
+    review: Option<Review>,
+    report: Option<Report>,
+    // The hashes of files already combined, if they're being cached.
+    cache: Option<HashCache>,
+    summary: Summary,
+}
+
+// Totals for the files processed in a run.
+#[derive(Clone, Copy, Debug, Default)]
+struct Summary {
+    files: usize,
+    changed: usize,
+    // The statements replaced in the files that changed, and the statements replacing them.
+    statements_before: usize,
+    statements_after: usize,
+}
+
+impl Summary {
+    fn print(&self, mode: Mode) {
+        let changed = if mode == Mode::Overwrite { "changed" } else { "would change" };
+        let mut line = format!("{} of {} files {}", self.changed, self.files, changed);
+        if self.statements_before > 0 && self.statements_after > self.statements_before {
+            line.push_str(&format!(", {} use statements {} into {}",
+                                   self.statements_before,
+                                   if mode == Mode::Overwrite { "split" } else { "would be split" },
+                                   self.statements_after));
+        } else if self.statements_before > 0 {
+            let reduction = self.statements_before - self.statements_after;
+            line.push_str(&format!(", {} use statements {} into {} ({}% fewer)",
+                                   self.statements_before,
+                                   if mode == Mode::Overwrite {
+                                       "combined"
+                                   } else {
+                                       "would be combined"
+                                   },
+                                   self.statements_after,
+                                   reduction * 100 / self.statements_before));
+        }
+        let _ = writeln!(io::stderr(), "{}", line);
+    }
+}
+
+impl Processor {
+    // The config for files in `dir`, adjusted for splitting if that's what's being done.
+    fn config_for(&mut self, dir: &Path) -> Result<Config, String> {
+        let mut config = self.load_config_for(dir)?;
+        if !self.packages.is_empty() {
+            let dir = dir.canonicalize().map_err(|e| format!("{}: {}", dir.display(), e))?;
+            if let Some(package) = containing_package(&self.packages, &dir) {
+                // Anything the config file gives takes precedence.
+                let mut names = package.crate_names();
+                names.extend(config.crate_names);
+                config.crate_names = names;
+                let mut kinds = package.crate_kinds(&self.packages);
+                kinds.extend(config.crate_kinds);
+                config.crate_kinds = kinds;
+            }
+        }
+        if self.skip_tests {
+            config.process_test_modules = false;
+        }
+        Ok(match self.split {
+            Some(granularity) => config.splitting(granularity),
+            None => config,
+        })
+    }
+
+    // The config for files in `dir`: from the nearest config file, or the default. Like the
+    // default, configs from files are subject to the environment's overrides.
+    fn load_config_for(&mut self, dir: &Path) -> Result<Config, String> {
+        if !self.discover_configs {
+            return Ok(self.config.clone());
+        }
+        let dir = dir.canonicalize().map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let path = match find_config_file(&dir) {
+            Some(path) => path,
+            None => return Ok(self.config.clone()),
+        };
+        if let Some(config) = self.configs.get(&path) {
+            return Ok(config.clone());
+        }
+        let mut config = Config::from_file(&path).map_err(|e| e.to_string())?;
+        config.apply_env_overrides()
+            .and_then(|_| config.validate())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        debug!(path = %path.display(), "loaded config file");
+        self.configs.insert(path, config.clone());
+        Ok(config)
+    }
+
+    // Process a file, returning whether it was (or in check mode would be) changed. Files are
+    // backed up first if there's a journal.
+    fn process_file(&mut self, path: &Path) -> Result<bool, String> {
+        let _span = info_span!("file", path = %path.display()).entered();
+        let source = read_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let config = self.config_for(path.parent().unwrap_or(Path::new(".")))?;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_source(&source, &config);
+            return Ok(false);
+        }
+        // Cached files are keyed by their absolute paths.
+        let cache_key = if self.cache.is_some() {
+            path.canonicalize().ok()
+        } else {
+            None
+        };
+        if let (Some(cache), Some(key)) = (self.cache.as_ref(), cache_key.as_ref()) {
+            if cache.is_combined(key, content_hash(&source, &config)) {
+                info!("unchanged since it was last combined");
+                self.summary.files += 1;
+                return Ok(false);
+            }
+        }
+        print_diagnostics(&path.display().to_string(), &source, &config);
+        let edits = source_edits(&source, &config);
+        if let Some(ref mut report) = self.report {
+            report.add_file(path, &source, &edits);
+        }
+        self.summary.files += 1;
+        if edits.is_empty() {
+            if let (Some(cache), Some(key)) = (self.cache.as_mut(), cache_key.as_ref()) {
+                cache.insert(key, content_hash(&source, &config));
+            }
+            info!("unchanged");
+            if self.mode == Mode::Stdout {
+                print_contents(path, &source);
+            }
+            return Ok(false);
+        }
+        let mut combined = apply_edits(&source, &edits);
+        if self.review.is_some() {
+            match self.review_file(path, &source, &combined)? {
+                Some(reviewed) => combined = reviewed,
+                None => {
+                    info!("skipped");
+                    return Ok(false);
+                }
+            }
+        }
+        self.summary.changed += 1;
+        for edit in &edits {
+            self.summary.statements_before += edit.removed.len();
+            self.summary.statements_after += edit.added.len();
+        }
+        info!(edits = edits.len(), "changed");
+        // Files that are only checked are only known to be combined if they're unchanged.
+        if let (Some(cache), Some(key)) = (self.cache.as_mut(), cache_key.as_ref()) {
+            if self.mode == Mode::Overwrite {
+                cache.insert(key, content_hash(&combined, &config));
+            }
+        }
+        match self.mode {
+            Mode::Overwrite => {
+                if let Some(ref mut journal) = self.journal {
+                    journal.backup(path)
+                        .and_then(|_| journal.save())
+                        .map_err(|e| format!("{}: couldn't back up: {}", path.display(), e))?;
+                }
+                write_file(path, &combined).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            Mode::Check if self.report.is_some() => {}
+            Mode::Check => {
+                clear_progress();
+                println!("{}", path.display())
+            }
+            Mode::Stdout => print_contents(path, &combined),
+            Mode::Report => {}
+            Mode::Diff => {
+                clear_progress();
+                let name = path.display().to_string();
+                print!("{}", unified_diff(&source, &combined, &name, &name))
+            }
+        }
+        Ok(true)
+    }
+
+    // Show the diff of the changes to a file and ask what to do with them, returning the
+    // contents to write (which may have been edited), or `None` to leave the file as it is.
+    fn review_file(&mut self,
+                   path: &Path,
+                   source: &str,
+                   combined: &str)
+                   -> Result<Option<String>, String> {
+        match self.review {
+            Some(Review::AcceptAll) | None => return Ok(Some(combined.to_string())),
+            Some(Review::Quit) => return Ok(None),
+            Some(Review::Ask) => {}
+        }
+        let name = path.display().to_string();
+        print!("{}", unified_diff(source, combined, &name, &name));
+        loop {
+            print!("Rewrite {} [y,n,e,a,q,?]? ", name);
+            let _ = io::stdout().flush();
+            let mut answer = String::new();
+            // The end of the input leaves this file and the rest as they are.
+            if io::stdin().read_line(&mut answer).map_err(|e| e.to_string())? == 0 {
+                println!();
+                self.review = Some(Review::Quit);
+                return Ok(None);
+            }
+            match answer.trim() {
+                "y" => return Ok(Some(combined.to_string())),
+                "n" => return Ok(None),
+                "e" => return edit_contents(path, combined).map(Some),
+                "a" => {
+                    self.review = Some(Review::AcceptAll);
+                    return Ok(Some(combined.to_string()));
+                }
+                "q" => {
+                    self.review = Some(Review::Quit);
+                    return Ok(None);
+                }
+                _ => {
+                    println!("y - rewrite this file\n\
+                              n - leave this file as it is\n\
+                              e - edit the rewritten file before it's written\n\
+                              a - rewrite this file and all the rest\n\
+                              q - leave this file and all the rest as they are\n\
+                              ? - print help")
+                }
+            }
+        }
+    }
+
+    // Combine standard input, writing the result (or in check or diff mode, the changes) to
+    // standard output, and returning whether it was changed.
+    fn process_stdin(&mut self) -> Result<bool, String> {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map_err(|e| format!("{}: {}", STDIN_NAME, e))?;
+        let config = self.config_for(Path::new("."))?;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_source(&source, &config);
+            return Ok(false);
+        }
+        print_diagnostics(STDIN_NAME, &source, &config);
+        let combined = combine_source(&source, &config);
+        let changed = combined != source;
+        match self.mode {
+            Mode::Overwrite | Mode::Stdout => print!("{}", combined),
+            Mode::Check if changed => println!("{}", STDIN_NAME),
+            Mode::Diff => print!("{}", unified_diff(&source, &combined, STDIN_NAME, STDIN_NAME)),
+            Mode::Check | Mode::Report => {}
+        }
+        Ok(changed)
+    }
+}
+
+// Print the warnings and errors about the imports of a file to standard error, as rustc does,
+// so that they can be read (or jumped to by an editor) alongside the compiler's.
+fn print_diagnostics(name: &str, source: &str, config: &Config) {
+    for diagnostic in source_diagnostics(source, config) {
+        if diagnostic.diagnostic.severity > Severity::Note {
+            clear_progress();
+            let _ = writeln!(io::stderr(), "{}", render_diagnostic(&diagnostic, name, source));
+        }
+    }
+}
+
+// Print the contents of a file for `--emit stdout`, preceded by its name like rustfmt does.
+fn print_contents(path: &Path, contents: &str) {
+    clear_progress();
+    print!("{}:\n\n{}", path.display(), contents);
+}
+
+// Let the user edit `contents` (the new contents of `path`) in their editor, returning the
+// result.
+fn edit_contents(path: &Path, contents: &str) -> Result<String, String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Keep the file name, so the editor knows it's Rust.
+    let name = path.file_name().map_or("source.rs".into(), |n| n.to_string_lossy());
+    let temp = env::temp_dir().join(format!("combiner-{}-{}", process::id(), name));
+    write_file(&temp, contents).map_err(|e| format!("{}: {}", temp.display(), e))?;
+    // The editor may have arguments of its own, like `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program).args(words).arg(&temp).status();
+    let result = match status {
+        Ok(ref status) if status.success() => {
+            read_file(&temp).map_err(|e| format!("{}: {}", temp.display(), e))
+        }
+        Ok(status) => Err(format!("{} exited with {}", editor, status)),
+        Err(e) => Err(format!("couldn't run {}: {}", editor, e)),
+    };
+    let _ = fs::remove_file(&temp);
+    result.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ReportFormat {
+    /// The edits made to each file.
+    Json,
+    /// Static analysis results for code scanning tools.
+    Sarif,
+    /// The lines that would change in each file, in the Checkstyle XML format rustfmt emits.
+    Checkstyle,
+    /// The blocks of lines that would change in each file, in the JSON format rustfmt emits.
+    Rustfmt,
+    /// A diagnostic for each edit, in the JSON format rustc emits, with the edit as a
+    /// machine-applicable suggestion.
+    Rustc,
+}
+
+// A machine-readable report of a run, in place of the usual output.
+struct Report {
+    format: ReportFormat,
+    // A report of each file in JSON format, or the results in SARIF format.
+    entries: Vec<Value>,
+}
+
+// The SARIF rules for the results in reports.
+const SARIF_RULES: &'static [(&'static str, &'static str)] =
+    &[("combinable-imports", "use statements can be combined"),
+      ("duplicate-import", "a name is imported more than once")];
+
+impl Report {
+    fn new(format: ReportFormat) -> Report {
+        Report {
+            format: format,
+            entries: vec![],
+        }
+    }
+
+    fn add_file(&mut self, path: &Path, source: &str, edits: &[SourceEdit]) {
+        match self.format {
+            ReportFormat::Json => self.entries.push(file_report(path, edits)),
+            // Only files that would change are included in rustfmt's JSON.
+            ReportFormat::Rustfmt if edits.is_empty() => {}
+            ReportFormat::Checkstyle |
+            ReportFormat::Rustfmt => self.entries.push(rustfmt_report(path, source, edits)),
+            ReportFormat::Rustc => {
+                let name = path.display().to_string();
+                self.entries
+                    .extend(edits.iter().map(|edit| rustc_diagnostic(&name, source, edit)));
+            }
+            ReportFormat::Sarif => {
+                for edit in edits {
+                    let message = format!("{} use statements can be combined into {}",
+                                          edit.removed.len(),
+                                          edit.added.len());
+                    self.entries.push(sarif_result("combinable-imports",
+                                                   &message,
+                                                   path,
+                                                   source,
+                                                   (edit.start, edit.end)));
+                }
+                for duplicate in duplicate_imports(source) {
+                    let message = format!("`{}` is already imported", duplicate.path);
+                    self.entries.push(sarif_result("duplicate-import",
+                                                   &message,
+                                                   path,
+                                                   source,
+                                                   (duplicate.start, duplicate.end)));
+                }
+            }
+        }
+    }
+
+    // The report as printed at the end of the run.
+    fn render(&self, failures: &[String]) -> String {
+        match self.format {
+            ReportFormat::Checkstyle => checkstyle(&self.entries),
+            // rustfmt emits its JSON on a single line.
+            ReportFormat::Rustfmt => Value::Array(self.entries.clone()).to_string(),
+            // As with rustc, one diagnostic per line.
+            ReportFormat::Rustc => {
+                let lines: Vec<String> = self.entries.iter().map(|e| e.to_string()).collect();
+                lines.join("\n")
+            }
+            _ => serde_json::to_string_pretty(&self.to_json(failures)).unwrap(),
+        }
+    }
+
+    fn to_json(&self, failures: &[String]) -> Value {
+        match self.format {
+            ReportFormat::Json => json!({ "files": self.entries, "failures": failures }),
+            ReportFormat::Checkstyle |
+            ReportFormat::Rustfmt |
+            ReportFormat::Rustc => Value::Array(self.entries.clone()),
+            ReportFormat::Sarif => {
+                let rules: Vec<Value> = SARIF_RULES.iter()
+                    .map(|&(id, description)| {
+                        json!({ "id": id, "shortDescription": { "text": description } })
+                    })
+                    .collect();
+                let notifications: Vec<Value> = failures.iter()
+                    .map(|message| json!({ "level": "error", "message": { "text": message } }))
+                    .collect();
+                json!({
+                    "version": "2.1.0",
+                    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                    "runs": [{
+                        "tool": { "driver": { "name": "combiner", "rules": rules } },
+                        "results": self.entries,
+                        "invocations": [{
+                            "executionSuccessful": failures.is_empty(),
+                            "toolExecutionNotifications": notifications,
+                        }],
+                    }],
+                })
+            }
+        }
+    }
+}
+
+// The mismatches in a file in rustfmt's JSON format: the lines each edit replaces in the
+// original file, and the lines replacing them in the new one.
+fn rustfmt_report(path: &Path, source: &str, edits: &[SourceEdit]) -> Value {
+    // How many more lines the new file has than the original, before the current edit.
+    let mut offset = 0isize;
+    let mismatches: Vec<Value> = edits.iter()
+        .map(|edit| {
+            let original = &source[edit.start..edit.end];
+            let begin = line_column(source, edit.start).0;
+            let original_lines = original.lines().count();
+            let expected_lines = edit.replacement.lines().count();
+            let expected_begin = (begin as isize + offset) as usize;
+            offset += expected_lines as isize - original_lines as isize;
+            json!({
+                "original_begin_line": begin,
+                "original_end_line": begin + original_lines - 1,
+                "expected_begin_line": expected_begin,
+                "expected_end_line": expected_begin + expected_lines - 1,
+                "original": original.trim_end_matches('\n'),
+                "expected": edit.replacement.trim_end_matches('\n'),
+            })
+        })
+        .collect();
+    json!({ "name": path.display().to_string(), "mismatches": mismatches })
+}
+
+// Render reports of the files in rustfmt's JSON format as Checkstyle XML, with an error for each
+// line that should be different.
+fn checkstyle(files: &[Value]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+    let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<checkstyle version=\"4.3\">"
+        .to_string();
+    for file in files {
+        xml.push_str(&format!("<file name=\"{}\">", escape(file["name"].as_str().unwrap_or(""))));
+        for mismatch in file["mismatches"].as_array().into_iter().flat_map(|m| m) {
+            let begin = mismatch["original_begin_line"].as_u64().unwrap_or(0);
+            let expected = mismatch["expected"].as_str().unwrap_or("");
+            for (i, line) in expected.lines().enumerate() {
+                xml.push_str(&format!("<error line=\"{}\" severity=\"warning\" message=\"Should \
+                                       be `{}`\" />",
+                                      begin + i as u64,
+                                      escape(line)));
+            }
+        }
+        xml.push_str("</file>");
+    }
+    xml.push_str("</checkstyle>");
+    xml
+}
+
+// The JSON report of the edits to a file.
+fn file_report(path: &Path, edits: &[SourceEdit]) -> Value {
+    let edits: Vec<Value> = edits.iter()
+        .map(|edit| {
+            let diagnostics: Vec<Value> = edit.diagnostics
+                .iter()
+                .map(|d| json!({ "severity": d.severity.to_string(), "message": d.message }))
+                .collect();
+            json!({
+                "start": edit.start,
+                "end": edit.end,
+                "removed": edit.removed,
+                "added": edit.added,
+                "replacement": edit.replacement,
+                "diagnostics": diagnostics,
+            })
+        })
+        .collect();
+    json!({
+        "path": path.display().to_string(),
+        "changed": !edits.is_empty(),
+        "edits": edits,
+    })
+}
+
+// A SARIF result for the byte range `span` of a file.
+fn sarif_result(rule: &str,
+                message: &str,
+                path: &Path,
+                source: &str,
+                span: (usize, usize))
+                -> Value {
+    let (start_line, start_column) = line_column(source, span.0);
+    let (end_line, end_column) = line_column(source, span.1);
+    json!({
+        "ruleId": rule,
+        "level": "warning",
+        "message": { "text": message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": path.to_string_lossy().replace('\\', "/") },
+                "region": {
+                    "startLine": start_line,
+                    "startColumn": start_column,
+                    "endLine": end_line,
+                    "endColumn": end_column,
+                },
+            },
+        }],
+    })
+}
+
+// Watch the files and directories named on the command line, recombining the files that would
+// be processed as they're created or changed, and printing a line for each. Only returns on
+// errors setting up the watch.
+fn watch(args: &[String], filter: &FileFilter, processor: &mut Processor) -> Result<(), String> {
+    let (tx, rx) = channel();
+    let mut watcher = notify::watcher(tx, Duration::from_millis(WATCH_DEBOUNCE_MS))
+        .map_err(|e| e.to_string())?;
+    for arg in args {
+        watcher.watch(arg, RecursiveMode::Recursive).map_err(|e| format!("{}: {}", arg, e))?;
+    }
+    // The files that would be processed, as absolute paths to match the events. Whether a new
+    // file is wanted depends on the filter and ignore files, so the directories are searched
+    // again whenever files are created.
+    let find_watched_files = || -> Result<BTreeSet<PathBuf>, String> {
+        Ok(find_files(args, filter)?.iter().filter_map(|f| f.canonicalize().ok()).collect())
+    };
+    let mut watched = find_watched_files()?;
+    // The contents the combiner last wrote to each file, so that the events its own writes
+    // cause are ignored (unless the file has been changed again since).
+    let mut written: BTreeMap<PathBuf, String> = BTreeMap::new();
+    println!("watching for changes...");
+    while let Ok(first) = rx.recv() {
+        // Handle the events that have arrived together as a batch.
+        let events: Vec<DebouncedEvent> = Some(first).into_iter().chain(rx.try_iter()).collect();
+        for event in events {
+            let path = match event {
+                DebouncedEvent::Write(path) => path,
+                DebouncedEvent::Create(path) |
+                DebouncedEvent::Rename(_, path) => {
+                    watched = find_watched_files().unwrap_or(watched);
+                    path
+                }
+                DebouncedEvent::Error(e, path) => {
+                    let name = path.map_or(String::new(), |p| format!("{}: ", p.display()));
+                    let _ = writeln!(io::stderr(), "error: {}{}", name, e);
+                    continue;
+                }
+                _ => continue,
+            };
+            let path = path.canonicalize().unwrap_or(path);
+            if !watched.contains(&path) || !path.is_file() {
+                continue;
+            }
+            if let Some(contents) = written.remove(&path) {
+                if read_file(&path).ok().as_ref() == Some(&contents) {
+                    continue;
+                }
+            }
+            match processor.process_file(&path) {
+                Ok(true) if processor.mode == Mode::Overwrite => {
+                    if let Ok(contents) = read_file(&path) {
+                        written.insert(path.clone(), contents);
+                    }
+                    println!("{}: combined", path.display())
+                }
+                Ok(true) => println!("{}: would change", path.display()),
+                Ok(false) => println!("{}: unchanged", path.display()),
+                Err(message) => {
+                    let _ = writeln!(io::stderr(), "error: {}", message);
+                }
+            }
+        }
+        if let Some(ref cache) = processor.cache {
+            if let Err(e) = cache.save() {
+                let _ = writeln!(io::stderr(), "error: {}: {}", CACHE_FILE_NAME, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Restore the files backed up by the last run.
+fn undo() -> Result<(), String> {
+    let path = Path::new(JOURNAL_FILE_NAME);
+    if !path.exists() {
+        return Err(format!("no {} to undo", JOURNAL_FILE_NAME));
+    }
+    let journal = Journal::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for file in journal.undo().map_err(|e| format!("couldn't undo: {}", e))? {
+        println!("restored {}", file.display());
+    }
+    Ok(())
+}
+
+// Run the combiner, returning the exit status.
+fn run(args: &[String]) -> Result<i32, String> {
+    let opts = make_opts();
+    let matches = opts.parse(args).map_err(|e| e.to_string())?;
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return Ok(0);
+    }
+    let level = match matches.opt_count("v") {
+        0 => Level::WARN,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(|| {
+            clear_progress();
+            io::stderr()
+        })
+        .init();
+    if matches.free.len() == 1 && matches.free[0] == "undo" && !Path::new("undo").exists() {
+        undo()?;
+        return Ok(0);
+    }
+    let git_changes = match (matches.opt_str("since"), matches.opt_present("staged")) {
+        (Some(_), true) => return Err("--since can't be used with --staged".to_string()),
+        (Some(since), false) => Some(GitChanges::Since(since)),
+        (None, true) => Some(GitChanges::Staged),
+        (None, false) => None,
+    };
+    let mut args = matches.free.clone();
+    // `split`, `normalize` and `stats` are subcommands, unless there's a file of that name.
+    let subcommand = match args.first() {
+        Some(arg) if SUBCOMMANDS.contains(&&arg[..]) && !Path::new(arg).exists() => {
+            Some(arg.clone())
+        }
+        _ => None,
+    };
+    let split = match subcommand.as_ref().map(|s| &s[..]) {
+        Some("split") if !matches.opt_present("item") => Some(Granularity::Module),
+        Some("split") | Some("normalize") => Some(Granularity::Item),
+        _ => None,
+    };
+    let stats = subcommand.as_ref().map_or(false, |s| s == "stats");
+    if subcommand.is_some() {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        if git_changes.is_none() {
+            print_usage(&opts);
+            return Err("no input files".to_string());
+        }
+        args.push(".".to_string());
+    }
+
+    let mut config = match matches.opt_str("preset") {
+        Some(name) => Config::preset(&name).ok_or(format!("unknown preset `{}`", name))?,
+        None => Config::default(),
+    };
+    config.apply_env_overrides().map_err(|e| e.to_string())?;
+    config.validate().map_err(|e| e.to_string())?;
+    let emit = matches.opt_str("emit");
+    if emit.is_some() && (matches.opt_present("check") || matches.opt_present("diff")) {
+        return Err("--emit can't be used with --check or --diff".to_string());
+    }
+    let mode = match emit.as_ref().map(|e| &e[..]) {
+        Some("files") => Mode::Overwrite,
+        Some("stdout") => Mode::Stdout,
+        Some("checkstyle") | Some("json") => Mode::Report,
+        Some(emit) => return Err(format!("unknown --emit value `{}`", emit)),
+        None if matches.opt_present("check") || check_mode_from_env() => Mode::Check,
+        None if matches.opt_present("diff") => Mode::Diff,
+        None => Mode::Overwrite,
+    };
+
+    let watching = matches.opt_present("watch");
+    let interactive = matches.opt_present("interactive");
+    if stats && (watching || interactive || matches.opt_present("report")) {
+        return Err("`combiner stats` can't be used with --watch, --interactive or --report"
+            .to_string());
+    }
+    if interactive &&
+       (mode != Mode::Overwrite || watching || matches.opt_present("report") ||
+        args.iter().any(|arg| arg == STDIN_ARG)) {
+        return Err("--interactive can't be used with --check, --diff, --report, --watch or \
+                    standard input"
+            .to_string());
+    }
+    if watching && mode == Mode::Check {
+        return Err("--watch can't be used with --check".to_string());
+    }
+    if watching && git_changes.is_some() {
+        return Err("--watch can't be used with --since or --staged".to_string());
+    }
+    if watching && args.iter().any(|arg| arg == STDIN_ARG) {
+        return Err("--watch can't be used with standard input".to_string());
+    }
+    if (mode == Mode::Stdout || mode == Mode::Report) &&
+       (watching || matches.opt_present("report")) {
+        return Err("--emit can only be used with --watch or --report to emit files".to_string());
+    }
+    if mode == Mode::Report && args.iter().any(|arg| arg == STDIN_ARG) {
+        return Err("--emit checkstyle and --emit json can't be used with standard input"
+            .to_string());
+    }
+    let report = match (matches.opt_str("report"), emit.as_ref().map(|e| &e[..])) {
+        (_, Some("checkstyle")) => Some(Report::new(ReportFormat::Checkstyle)),
+        (_, Some("json")) => Some(Report::new(ReportFormat::Rustfmt)),
+        (Some(ref format), _) if format == "json" => Some(Report::new(ReportFormat::Json)),
+        (Some(ref format), _) if format == "sarif" => Some(Report::new(ReportFormat::Sarif)),
+        (Some(ref format), _) if format == "rustc-json" => {
+            Some(Report::new(ReportFormat::Rustc))
+        }
+        (Some(format), _) => return Err(format!("unknown report format `{}`", format)),
+        (None, _) => None,
+    };
+    if report.is_some() &&
+       (watching || mode == Mode::Diff || args.iter().any(|arg| arg == STDIN_ARG)) {
+        return Err("--report can't be used with --watch, --diff or standard input".to_string());
+    }
+
+    let filter = FileFilter {
+        include: matches.opt_strs("include"),
+        exclude: matches.opt_strs("exclude"),
+    };
+    // The journal is only saved once a file has been backed up, so a run that changes nothing
+    // leaves the journal of the last one in place.
+    let journal = if matches.opt_present("backup") && mode == Mode::Overwrite {
+        Some(Journal::new(Path::new(JOURNAL_FILE_NAME)))
+    } else {
+        None
+    };
+    let packages = if matches.opt_present("cargo-metadata") {
+        cargo_metadata(None)?
+    } else {
+        vec![]
+    };
+    let mut processor = Processor {
+        config: config,
+        split: split,
+        stats: if stats { Some(ImportStats::new()) } else { None },
+        skip_tests: matches.opt_present("skip-tests"),
+        discover_configs: !matches.opt_present("no-config"),
+        configs: BTreeMap::new(),
+        packages: packages,
+        mode: mode,
+        journal: journal,
+        review: if interactive { Some(Review::Ask) } else { None },
+        report: report,
+        cache: if matches.opt_present("cached") {
+            Some(HashCache::load(Path::new(CACHE_FILE_NAME)))
+        } else {
+            None
+        },
+        summary: Summary::default(),
+    };
+    let mut files = find_files(&args, &filter)?;
+    if let Some(ref changes) = git_changes {
+        let changed_files: BTreeSet<PathBuf> = git_changed_files(Path::new("."), changes)?
+            .into_iter()
+            .collect();
+        files.retain(|f| f.canonicalize().map_or(false, |f| changed_files.contains(&f)));
+    }
+    // Show which file is being processed on a terminal, overwriting the same line.
+    let show_progress = files.len() > 1 && !interactive && io::stderr().is_terminal();
+    let mut changed = false;
+    let mut failures = vec![];
+    for (i, path) in files.iter().enumerate() {
+        if show_progress {
+            draw_progress(&format!("[{}/{}] {}", i + 1, files.len(), path.display()));
+        }
+        let result = if path == Path::new(STDIN_ARG) {
+            processor.process_stdin()
+        } else {
+            processor.process_file(path)
+        };
+        // Carry on past files that can't be processed (leaving them as they are), and report
+        // them all at the end.
+        match result {
+            Ok(c) => changed |= c,
+            Err(message) => failures.push(message),
+        }
+    }
+    clear_progress();
+    if let Some(ref cache) = processor.cache {
+        cache.save().map_err(|e| format!("{}: {}", CACHE_FILE_NAME, e))?;
+    }
+    if let Some(ref stats) = processor.stats {
+        print!("{}", stats);
+    } else if processor.summary.files > 0 {
+        processor.summary.print(mode);
+    }
+    if let Some(ref report) = processor.report {
+        println!("{}", report.render(&failures));
+    }
+    if !failures.is_empty() {
+        let mut stderr = io::stderr();
+        let _ = writeln!(stderr, "error: couldn't process {} files:", failures.len());
+        for message in &failures {
+            let _ = writeln!(stderr, "    {}", message);
+        }
+    }
+    if watching {
+        watch(&args, &filter, &mut processor)?;
+    }
+    Ok(if !failures.is_empty() {
+        EXIT_ERROR
+    } else if mode == Mode::Check && changed {
+        EXIT_CHANGES_PENDING
+    } else {
+        0
+    })
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(status) => process::exit(status),
+        Err(message) => {
+            let _ = writeln!(io::stderr(), "error: {}", message);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use {Item, Path, ViewPath, path_segments};
+use config::DEFAULT_MAX_PATH_DEPTH;
+
+// Lists nested deeper than this aren't parsed any further (their text is split into the rest of
+// a simple path), so that adversarial inputs can't exhaust the stack, or take quadratic time
+// rescanning it. Real imports nested this deep would be too deep for the default
+// `max_path_depth` anyway.
+const MAX_NESTING_DEPTH: usize = DEFAULT_MAX_PATH_DEPTH;
+
+/// A view path borrowing its segments from the text it was parsed from, so that parsing doesn't
+/// allocate a `String` per segment. It's converted to an owned `ViewPath` when it's added to a
+/// combiner.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViewPathRef<'a> {
+    /// `foo::bar::baz as quux`, or just `foo::bar::baz`
+    Simple(Vec<&'a str>, Option<&'a str>),
+    /// `foo::bar::*`
+    Glob(Vec<&'a str>),
+    /// `foo::bar::{a, b as c}`, with each item's name and rename
+    List(Vec<&'a str>, Vec<(&'a str, Option<&'a str>)>),
+    /// `foo::{bar::{a, b}, baz::*, self}`, where each nested view path is relative to the prefix
+    Nested(Vec<&'a str>, Vec<ViewPathRef<'a>>),
+}
+
+// A list item's name and rename.
+pub fn parse_item(s: &str) -> (&str, Option<&str>) {
+    let trimmed = s.trim();
+    let elements: Vec<&str> = trimmed.split_whitespace().collect();
+    if elements.len() == 3 && elements[1] == "as" {
+        (elements[0], Some(elements[2]))
+    } else {
+        (trimmed, None)
+    }
+}
+
+// Split the contents of a brace list on the commas that aren't inside a nested list.
+fn split_list_items(s: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items
+}
+
+impl<'a> ViewPathRef<'a> {
+    /// Parse the body of a `use` statement (without `use` and the `;`).
+    pub fn parse(s: &'a str) -> ViewPathRef<'a> {
+        ViewPathRef::parse_nested(s, 0)
+    }
+
+    // Parse a view path nested in `depth` lists.
+    fn parse_nested(s: &'a str, depth: usize) -> ViewPathRef<'a> {
+        let s = s.trim();
+        let nestable = depth < MAX_NESTING_DEPTH && s.ends_with('}');
+        if let (Some(open), true) = (s.find('{'), nestable) {
+            let items = split_list_items(&s[open + 1..s.len() - 1]);
+            // Lists of more than names (including globs, as in `{self, *}`) are nested.
+            if items.iter().any(|i| i.contains("::") || i.contains('{') || i.trim() == "*") {
+                // A prefix that doesn't end in `::` (as in a half-typed `a{b::c}`) isn't one.
+                let prefix = s[..open].trim_end();
+                let path = if prefix.is_empty() {
+                    Some(vec![])
+                } else {
+                    prefix.strip_suffix("::").map(|p| path_segments(p).map(str::trim).collect())
+                };
+                if let Some(path) = path {
+                    let nested = items.into_iter()
+                        .filter(|i| !i.trim().is_empty())
+                        .map(|i| ViewPathRef::parse_nested(i, depth + 1))
+                        .collect();
+                    return ViewPathRef::Nested(path, nested);
+                }
+            }
+        }
+        let mut path: Vec<&str> = path_segments(s).collect();
+        let last = path.pop().unwrap_or("");
+        if last == "*" {
+            ViewPathRef::Glob(path)
+        } else if last.starts_with("{") && last.ends_with("}") {
+            let items: Vec<_> = last[1..last.len() - 1]
+                .split(",")
+                .filter(|s| !s.trim().is_empty())
+                .map(parse_item)
+                .collect();
+            if items.len() == 1 && items[0].0 == "self" {
+                ViewPathRef::Simple(path, items[0].1)
+            } else {
+                ViewPathRef::List(path, items)
+            }
+        } else {
+            let (name, rename) = parse_item(last);
+            path.push(name);
+            ViewPathRef::Simple(path, rename)
+        }
+    }
+
+    /// The owned view path, copying the segments.
+    pub fn to_view_path(&self) -> ViewPath {
+        fn owned(path: &[&str]) -> Path {
+            path.iter().map(|s| s.to_string()).collect()
+        }
+        match self {
+            &ViewPathRef::Simple(ref p, rename) => {
+                ViewPath::ViewPathSimple(owned(p), rename.map(String::from))
+            }
+            &ViewPathRef::Glob(ref p) => ViewPath::ViewPathGlob(owned(p)),
+            &ViewPathRef::List(ref p, ref items) => {
+                let items = items.iter()
+                    .map(|&(name, rename)| Item(name.to_string(), rename.map(String::from)))
+                    .collect();
+                ViewPath::ViewPathList(owned(p), items)
+            }
+            &ViewPathRef::Nested(ref p, ref nested) => {
+                let nested = nested.iter().map(|vp| vp.to_view_path()).collect();
+                ViewPath::ViewPathNested(owned(p), nested)
+            }
+        }
+    }
+}
+
+impl<'a, 'b> From<&'b ViewPathRef<'a>> for ViewPath {
+    fn from(vp: &ViewPathRef<'a>) -> ViewPath {
+        vp.to_view_path()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn borrowed() {
+        let source = String::from("use a::{self, b::{c, d as e}, f::*};");
+        let vp = ViewPathRef::parse(&source[4..source.len() - 1]);
+        assert_eq!(vp,
+                   ViewPathRef::Nested(vec!["a"],
+                                       vec![ViewPathRef::Simple(vec!["self"], None),
+                                            ViewPathRef::List(vec!["b"],
+                                                              vec![("c", None), ("d", Some("e"))]),
+                                            ViewPathRef::Glob(vec!["f"])]));
+        assert_eq!(vp.to_view_path(), ViewPath::from("a::{self, b::{c, d as e}, f::*}"));
+        // Malformed prefixes are parsed as something, rather than panicking.
+        assert_eq!(ViewPathRef::parse("a :: {b::c}"),
+                   ViewPathRef::Nested(vec!["a"], vec![ViewPathRef::Simple(vec!["b", "c"], None)]));
+        for malformed in &["a{b::c}", "éa{b::c}", "a:{b::c}", "{b::c}x"] {
+            ViewPathRef::parse(malformed);
+        }
+        assert_eq!(ViewPathRef::parse("a{b::c}"), ViewPathRef::Simple(vec!["a{b", "c}"], None));
+    }
+}
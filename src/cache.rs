@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use Config;
+use files::{read_file, write_file};
+
+/// The name of the cache file written (in the current directory) by a run with caching.
+pub const CACHE_FILE_NAME: &'static str = ".combiner-cache";
+
+/// A hash of a file's contents together with the config it's combined with, so that changing
+/// the config invalidates the cache. It's only stable for a given build of the combiner.
+pub fn content_hash(contents: &str, config: &Config) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", config).hash(&mut hasher);
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The hashes of files known to be already combined, so that they can be skipped when they
+/// haven't changed. It's saved as lines of a hex hash and a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HashCache {
+    path: PathBuf,
+    hashes: BTreeMap<PathBuf, u64>,
+}
+
+impl HashCache {
+    /// Load the cache at `path`, or start an empty one if there isn't one (or it can't be read).
+    pub fn load(path: &Path) -> HashCache {
+        let mut hashes = BTreeMap::new();
+        for line in read_file(path).unwrap_or(String::new()).lines() {
+            let mut parts = line.splitn(2, ' ');
+            let hash = parts.next().and_then(|h| u64::from_str_radix(h, 16).ok());
+            if let (Some(hash), Some(file)) = (hash, parts.next()) {
+                hashes.insert(PathBuf::from(file), hash);
+            }
+        }
+        HashCache {
+            path: path.to_path_buf(),
+            hashes: hashes,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut contents = String::new();
+        for (file, hash) in &self.hashes {
+            contents.push_str(&format!("{:016x} {}\n", hash, file.to_string_lossy()));
+        }
+        write_file(&self.path, &contents)
+    }
+
+    /// Whether `file` is known to be combined when its contents have the hash `hash`.
+    pub fn is_combined(&self, file: &Path, hash: u64) -> bool {
+        self.hashes.get(file) == Some(&hash)
+    }
+
+    /// Record that `file` is combined when its contents have the hash `hash`.
+    pub fn insert(&mut self, file: &Path, hash: u64) {
+        self.hashes.insert(file.to_path_buf(), hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use Config;
+    #[test]
+    fn hashes() {
+        let config = Config::default();
+        assert_eq!(content_hash("use a;", &config), content_hash("use a;", &config));
+        assert!(content_hash("use a;", &config) != content_hash("use b;", &config));
+        assert!(content_hash("use a;", &config) != content_hash("use a;", &Config::compact()));
+
+        let path = env::temp_dir().join(format!("combiner-cache-{}", ::std::process::id()));
+        let mut cache = HashCache::load(&path);
+        assert!(!cache.is_combined(Path::new("/src/lib.rs"), 42));
+        cache.insert(Path::new("/src/lib.rs"), 42);
+        cache.save().unwrap();
+        let cache = HashCache::load(&path);
+        assert!(cache.is_combined(Path::new("/src/lib.rs"), 42));
+        assert!(!cache.is_combined(Path::new("/src/lib.rs"), 43));
+        fs::remove_file(&path).unwrap();
+    }
+}
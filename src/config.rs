@@ -0,0 +1,910 @@
+use std::collections::BTreeMap;
+use std::default::Default;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use metadata::CrateKind;
+use ordering::{ItemOrdering, SelfPosition};
+use pattern::PathPattern;
+use render::ImportGroup;
+
+/// The names of config files, in the order they're looked for in each directory.
+pub const CONFIG_FILE_NAMES: &'static [&'static str] = &["combiner.toml", ".combiner.toml"];
+
+/// The default minimum number of items needed before a brace list is emitted.
+pub const DEFAULT_MIN_LIST_LENGTH: usize = 3;
+
+/// The default limits on the number of segments in an import's path, and the number of modules
+/// and items the combiner keeps track of.
+pub const DEFAULT_MAX_PATH_DEPTH: usize = 128;
+pub const DEFAULT_MAX_NODES: usize = 100_000;
+
+/// The Rust edition that combined imports must compile under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Edition {
+    #[cfg_attr(feature = "serde", serde(rename = "2015"))]
+    Edition2015,
+    #[cfg_attr(feature = "serde", serde(rename = "2018"))]
+    Edition2018,
+    #[cfg_attr(feature = "serde", serde(rename = "2021"))]
+    Edition2021,
+}
+
+impl Edition {
+    /// Whether nested groups (`use a::{b::{c, d}, e};`) may be emitted.
+    pub fn supports_nested_groups(self) -> bool {
+        self >= Edition::Edition2018
+    }
+
+    /// Whether paths may be written relative to `crate::`. In 2015 use paths are already
+    /// crate-relative, so the prefix is written as a leading `::` instead.
+    pub fn supports_crate_prefix(self) -> bool {
+        self >= Edition::Edition2018
+    }
+
+    /// Whether `extern crate` items may be converted into `use` statements. In 2015 they're
+    /// what brings crates into scope, so they have to stay.
+    pub fn allows_extern_crate_conversion(self) -> bool {
+        self >= Edition::Edition2018
+    }
+}
+
+impl Default for Edition {
+    fn default() -> Edition {
+        Edition::Edition2015
+    }
+}
+
+impl FromStr for Edition {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Edition, String> {
+        match s {
+            "2015" => Ok(Edition::Edition2015),
+            "2018" => Ok(Edition::Edition2018),
+            "2021" => Ok(Edition::Edition2021),
+            _ => Err(format!("unknown edition `{}`", s)),
+        }
+    }
+}
+
+/// What happens to imports using reserved keywords (like `fn`) as names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeywordPolicy {
+    /// They're reported as errors.
+    Reject,
+    /// The keywords are written as raw identifiers (`r#fn`).
+    Raw,
+}
+
+impl Default for KeywordPolicy {
+    fn default() -> KeywordPolicy {
+        KeywordPolicy::Reject
+    }
+}
+
+impl FromStr for KeywordPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<KeywordPolicy, String> {
+        match &s.to_lowercase()[..] {
+            "reject" => Ok(KeywordPolicy::Reject),
+            "raw" => Ok(KeywordPolicy::Raw),
+            _ => Err(format!("unknown keyword policy `{}`", s)),
+        }
+    }
+}
+
+/// How glob imports are treated when combining.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GlobPolicy {
+    /// Globs are emitted, and make explicit imports of the glob's children redundant.
+    Absorb,
+    /// Globs are emitted alongside explicit imports of the glob's children, which are kept.
+    Keep,
+    /// Globs are dropped from the output, leaving only the explicitly named imports (unless the
+    /// glob is listed in `Config::protected_globs`).
+    Deny,
+}
+
+impl Default for GlobPolicy {
+    fn default() -> GlobPolicy {
+        GlobPolicy::Absorb
+    }
+}
+
+impl FromStr for GlobPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<GlobPolicy, String> {
+        match &s.to_lowercase()[..] {
+            "absorb" => Ok(GlobPolicy::Absorb),
+            "keep" => Ok(GlobPolicy::Keep),
+            "deny" => Ok(GlobPolicy::Deny),
+            _ => Err(format!("unknown glob policy `{}`", s)),
+        }
+    }
+}
+
+/// How finely imports are merged into statements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Granularity {
+    /// One statement per imported item: `use a::b::c; use a::b::d;`
+    Item,
+    /// One statement per module, e.g. `use a::b::{c, d};` (as long as the list is long enough).
+    Module,
+    /// One statement per top-level crate, using nested groups: `use a::{b::{c, d}, e};`
+    Crate,
+    /// A single statement for everything: `use {a::{b::c, d}, x::y};`
+    One,
+}
+
+impl Default for Granularity {
+    fn default() -> Granularity {
+        Granularity::Module
+    }
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Granularity, String> {
+        match &s.to_lowercase()[..] {
+            "item" => Ok(Granularity::Item),
+            "module" => Ok(Granularity::Module),
+            "crate" => Ok(Granularity::Crate),
+            "one" => Ok(Granularity::One),
+            _ => Err(format!("unknown granularity `{}`", s)),
+        }
+    }
+}
+
+/// How the combiner treats imports that can't be combined cleanly, such as two imports binding
+/// the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Strictness {
+    /// Combine them anyway, reporting the problems as warnings.
+    Lenient,
+    /// Report the problems as errors, and leave the imports as they were.
+    Strict,
+    /// As `Strict`, and also only make changes that can't affect what any name resolves to, so
+    /// that the output can be applied without review: explicit imports are kept alongside globs
+    /// of their modules rather than absorbed by them, and globs are never denied.
+    Lossless,
+}
+
+impl Default for Strictness {
+    fn default() -> Strictness {
+        Strictness::Lenient
+    }
+}
+
+impl FromStr for Strictness {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Strictness, String> {
+        match &s.to_lowercase()[..] {
+            "lenient" => Ok(Strictness::Lenient),
+            "strict" => Ok(Strictness::Strict),
+            "lossless" => Ok(Strictness::Lossless),
+            _ => Err(format!("unknown strictness `{}`", s)),
+        }
+    }
+}
+
+/// How rendered imports are sorted into blank-line separated groups. The names are those of
+/// rustfmt's `group_imports` option, and `StdExternalCrate` groups imports exactly as it does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GroupImports {
+    /// Into the configured `groups` (if there are any), followed by the ungrouped imports.
+    Preserve,
+    /// Imports from `std`, `core` and `alloc`, then from other crates, then from the current
+    /// crate (paths starting with `crate`, `self` or `super`).
+    StdExternalCrate,
+    /// As `StdExternalCrate`, but with imports from the workspace's other members and from path
+    /// dependencies (as given by `crate_kinds`) in a group of their own, after those from other
+    /// crates.
+    StdExternalWorkspaceCrate,
+    /// All into one group.
+    One,
+}
+
+impl Default for GroupImports {
+    fn default() -> GroupImports {
+        GroupImports::Preserve
+    }
+}
+
+impl FromStr for GroupImports {
+    type Err = String;
+    fn from_str(s: &str) -> Result<GroupImports, String> {
+        match &s.to_lowercase().replace("_", "")[..] {
+            "preserve" => Ok(GroupImports::Preserve),
+            "stdexternalcrate" => Ok(GroupImports::StdExternalCrate),
+            "stdexternalworkspacecrate" => Ok(GroupImports::StdExternalWorkspaceCrate),
+            "one" => Ok(GroupImports::One),
+            _ => Err(format!("unknown import grouping `{}`", s)),
+        }
+    }
+}
+
+/// A problem with a configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// An option (or environment variable) has a value it can't take.
+    InvalidValue { option: String, message: String },
+    /// Two options contradict each other, so one of them would have to be ignored.
+    Conflict {
+        options: (String, String),
+        message: String,
+    },
+    /// A config file couldn't be read or parsed.
+    File { path: PathBuf, message: String },
+}
+
+impl ConfigError {
+    fn invalid(option: &str, message: String) -> ConfigError {
+        ConfigError::InvalidValue {
+            option: option.to_string(),
+            message: message,
+        }
+    }
+
+    fn conflict(a: &str, b: &str, message: &str) -> ConfigError {
+        ConfigError::Conflict {
+            options: (a.to_string(), b.to_string()),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ConfigError::InvalidValue { ref option, ref message } => {
+                write!(f, "invalid `{}`: {}", option, message)
+            }
+            &ConfigError::Conflict { ref options, ref message } => {
+                write!(f, "`{}` conflicts with `{}`: {}", options.0, options.1, message)
+            }
+            &ConfigError::File { ref path, ref message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match self {
+            &ConfigError::InvalidValue { .. } => "invalid configuration value",
+            &ConfigError::Conflict { .. } => "conflicting configuration options",
+            &ConfigError::File { .. } => "invalid config file",
+        }
+    }
+}
+
+/// Options controlling how imports are combined and rendered. With the `serde` feature, configs
+/// can be (de)serialized, with any missing options taking their default values. Unknown options
+/// are rejected, so that misspelt ones aren't silently ignored.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Config {
+    /// The edition the generated imports are targeting.
+    pub edition: Edition,
+    /// How finely imports are merged. `Crate` and `One` need nested groups, so fall back to
+    /// `Module` for editions that don't support them.
+    pub granularity: Granularity,
+    /// Never merge imports from different top-level crates into one statement.
+    pub split_top_level_crates: bool,
+    /// The minimum number of items a module must import before they are gathered into a list.
+    pub min_list_length: usize,
+    /// The minimum number of distinct input statements that must contribute to a list before it
+    /// is emitted.
+    pub min_list_statements: usize,
+    /// How sibling names are ordered, both within lists and between statements.
+    pub ordering: ItemOrdering,
+    /// Where `self` is placed within a brace list.
+    pub self_position: SelfPosition,
+    /// Imports matching any of these patterns are never merged, but passed through verbatim.
+    pub preserve_paths: Vec<PathPattern>,
+    /// How glob imports are treated.
+    pub glob_policy: GlobPolicy,
+    /// Globs matching any of these patterns (e.g. `*::prelude::*`) are always emitted, whatever
+    /// the glob policy.
+    pub protected_globs: Vec<PathPattern>,
+    /// Groups that rendered imports are sorted into, in the order they are emitted.
+    pub groups: Vec<ImportGroup>,
+    /// How rendered imports are grouped: with `Preserve`, into `groups`.
+    pub group_imports: GroupImports,
+    /// Whether the rendered statements are formatted with prettyplease (which needs the `pretty`
+    /// feature), so that lists too long for a line are wrapped as rustfmt would wrap them.
+    pub pretty_print: bool,
+    /// Whether imports inside `#[cfg(test)]` modules are combined. Test imports (such as
+    /// `use super::*;`) often follow their own conventions.
+    pub process_test_modules: bool,
+    /// Whether problems found while combining are warnings or errors.
+    pub strictness: Strictness,
+    /// Whether globs are kept from absorbing explicit imports (as with `GlobPolicy::Keep`) in
+    /// source files that enable clippy's `wildcard_imports` lint, so that combining doesn't make
+    /// code lean on globs the lint rejects.
+    pub clippy_aware: bool,
+    /// Whether renames to the name an import would have anyway (`use a::b as b;`) are dropped.
+    pub strip_redundant_renames: bool,
+    /// Whether `self::` prefixes are removed (`self::a::b` becomes `a::b`), so that both
+    /// spellings are combined. If the combiner is told which module the imports are in, they
+    /// become absolute paths instead.
+    pub strip_self_prefix: bool,
+    /// Whether imports are being migrated from the 2015 edition, in which use paths are
+    /// crate-relative: paths starting with an external crate lose any leading `::`, and all other
+    /// absolute paths are prefixed with `crate::`. This applies to every import, including
+    /// preserved ones.
+    pub migrate_from_2015: bool,
+    /// The external crates the code being migrated depends on, besides those (like `std`) that
+    /// are always available.
+    pub extern_crates: Vec<String>,
+    /// Whether `extern crate` items in source files are converted into `use` statements (and
+    /// combined with those around them): `extern crate a as b;` becomes `use a as b;`. Items
+    /// with attributes (like `#[macro_use]`) and `extern crate self` are left as they are. Only
+    /// allowed from the 2018 edition.
+    pub convert_extern_crates: bool,
+    /// The name of the crate the imports are in. If it's given, imports starting with it are
+    /// combined with those starting with `crate::`.
+    pub crate_name: Option<String>,
+    /// The names that packages' crates have in code, for those that differ from the package
+    /// name with dashes replaced by underscores (e.g. dependencies renamed in `Cargo.toml`).
+    /// Imports starting with a package name (as users may write paths, e.g. `serde-json::Value`)
+    /// are respelt with the crate's name, as are `crate_name` and `extern_crates`.
+    pub crate_names: BTreeMap<String, String>,
+    /// Whether the crates imports start with are members of the workspace, path dependencies or
+    /// external crates, keyed by their names in code, as `CargoPackage::crate_kinds` finds from
+    /// `cargo metadata`. The crates are grouped by their kinds with
+    /// `GroupImports::StdExternalWorkspaceCrate`, and when migrating from the 2015 edition, they
+    /// count as external crates.
+    pub crate_kinds: BTreeMap<String, CrateKind>,
+    /// Whether imports from the current crate are written starting with its name (given by
+    /// `crate_name`), rather than `crate::`.
+    pub prefer_crate_name: bool,
+    /// What happens to imports using reserved keywords as names.
+    pub keyword_policy: KeywordPolicy,
+    /// Whether imports renamed to names from the standard prelude (`as Result`, `as Box`) are
+    /// warned about, as readers will assume the name means the prelude's.
+    pub lint_prelude_aliases: bool,
+    /// Imports with more segments in their paths than this are rejected, so that generated or
+    /// adversarial inputs can't exhaust the stack of the routines that walk the tree.
+    pub max_path_depth: usize,
+    /// Imports that would take the combiner past tracking this many modules and items are
+    /// rejected, so that generated or adversarial inputs can't exhaust memory.
+    pub max_nodes: usize,
+    /// If it's given, once the combiner is tracking more than this many modules and items, the
+    /// imports under the top-level crates and modules the fewest imports have gone into are
+    /// combined by themselves and taken out of the combiner's tree ("spilled"), until it's
+    /// tracking no more than half as many. Later imports from those crates are combined apart
+    /// from the spilled ones, so they can take more statements than they would otherwise, but
+    /// the memory a combiner that a whole corpus is streamed through uses stays bounded.
+    pub spill_threshold: Option<usize>,
+}
+
+/// The names accepted by `Config::preset`.
+pub const PRESET_NAMES: &'static [&'static str] = &["default", "rustfmt", "compact", "verbose"];
+
+impl Config {
+    /// Settings matching what rustfmt produces with `imports_granularity = "Module"`: any two or
+    /// more imports from the same module are merged into a list.
+    pub fn rustfmt_default() -> Config {
+        Config {
+            edition: Edition::Edition2018,
+            granularity: Granularity::Module,
+            min_list_length: 2,
+            ..Config::default()
+        }
+    }
+
+    /// As few statements as possible: one nested statement per crate, with globs absorbing
+    /// explicit imports.
+    pub fn compact() -> Config {
+        Config {
+            edition: Edition::Edition2018,
+            granularity: Granularity::Crate,
+            min_list_length: 2,
+            glob_policy: GlobPolicy::Absorb,
+            ..Config::default()
+        }
+    }
+
+    /// One statement per imported item, keeping crates on separate statements.
+    pub fn verbose() -> Config {
+        Config {
+            granularity: Granularity::Item,
+            split_top_level_crates: true,
+            glob_policy: GlobPolicy::Absorb,
+            ..Config::default()
+        }
+    }
+
+    /// Look up a preset by the name used in config files and on the command line (one of
+    /// `PRESET_NAMES`).
+    pub fn preset(name: &str) -> Option<Config> {
+        match name {
+            "default" => Some(Config::default()),
+            "rustfmt" => Some(Config::rustfmt_default()),
+            "compact" => Some(Config::compact()),
+            "verbose" => Some(Config::verbose()),
+            _ => None,
+        }
+    }
+
+    /// The name a package's crate has in code: as given by `crate_names`, or otherwise the
+    /// package name with dashes replaced by underscores.
+    pub fn crate_name_in_code(&self, package: &str) -> String {
+        self.crate_names.get(package).cloned().unwrap_or_else(|| package.replace('-', "_"))
+    }
+
+    /// Whether explicit imports of names in a module are dropped when a glob of the module is
+    /// emitted. They aren't with `GlobPolicy::Keep`, nor with `Strictness::Lossless`, as an
+    /// explicit import takes precedence over a glob's name of the same name.
+    pub fn absorbs_under_globs(&self) -> bool {
+        self.glob_policy != GlobPolicy::Keep && self.strictness != Strictness::Lossless
+    }
+
+    /// Whether a glob import of `path` should appear in the output.
+    pub fn emits_glob(&self, path: &[String]) -> bool {
+        match self.glob_policy {
+            GlobPolicy::Absorb | GlobPolicy::Keep => true,
+            GlobPolicy::Deny => {
+                let mut glob = path.to_vec();
+                glob.push("*".to_string());
+                self.protected_globs.iter().any(|p| p.matches_segments(&glob))
+            }
+        }
+    }
+
+    /// Override settings from `COMBINER_*` environment variables, so that CI can tighten the
+    /// settings without changing the project's config file:
+    ///
+    /// * `COMBINER_PRESET` - start from the named preset, before applying any other overrides
+    /// * `COMBINER_EDITION` - `2015`, `2018` or `2021`
+    /// * `COMBINER_GRANULARITY` - `item`, `module`, `crate` or `one`
+    /// * `COMBINER_MIN_LIST_LENGTH` and `COMBINER_MIN_LIST_STATEMENTS` - list thresholds
+    /// * `COMBINER_MAX_PATH_DEPTH` and `COMBINER_MAX_NODES` - limits on the input
+    /// * `COMBINER_SPILL_THRESHOLD` - the number of modules and items to spill imports above
+    /// * `COMBINER_ORDERING` - one of the `ItemOrdering` names, e.g. `version_sort`
+    /// * `COMBINER_GLOB_POLICY` - `absorb`, `keep` or `deny`
+    /// * `COMBINER_GROUP_IMPORTS` - `preserve`, `std_external_crate`,
+    ///   `std_external_workspace_crate` or `one`
+    /// * `COMBINER_KEYWORD_POLICY` - `reject` or `raw`
+    /// * `COMBINER_SPLIT_TOP_LEVEL_CRATES` - `true` or `false`
+    /// * `COMBINER_PROCESS_TEST_MODULES` and `COMBINER_CLIPPY_AWARE` - `true` or `false`
+    /// * `COMBINER_STRICTNESS` - `lenient`, `strict` or `lossless`
+    /// * `COMBINER_STRIP_REDUNDANT_RENAMES`, `COMBINER_STRIP_SELF_PREFIX`,
+    ///   `COMBINER_MIGRATE_FROM_2015`, `COMBINER_CONVERT_EXTERN_CRATES` and
+    ///   `COMBINER_LINT_PRELUDE_ALIASES` - `true` or `false`
+    /// * `COMBINER_CRATE_NAME` - the name of the crate, and `COMBINER_PREFER_CRATE_NAME` - `true`
+    ///   or `false`
+    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        self.apply_overrides(env::vars())
+    }
+
+    /// Apply `COMBINER_*` overrides from a set of variables. Variables without the `COMBINER_`
+    /// prefix, or that aren't config settings, are ignored.
+    pub fn apply_overrides<I>(&mut self, vars: I) -> Result<(), ConfigError>
+        where I: IntoIterator<Item = (String, String)>
+    {
+        fn parse<T>(name: &str, value: &str) -> Result<T, ConfigError>
+            where T: FromStr,
+                  T::Err: fmt::Display
+        {
+            value.parse().map_err(|e| ConfigError::invalid(name, format!("`{}`: {}", value, e)))
+        }
+        let mut vars: Vec<_> = vars.into_iter()
+            .filter(|&(ref name, _)| name.starts_with("COMBINER_"))
+            .collect();
+        // The preset replaces everything, so has to go first.
+        vars.sort_by_key(|&(ref name, _)| name != "COMBINER_PRESET");
+        for (name, value) in vars {
+            let value = value.trim();
+            match &name[..] {
+                "COMBINER_PRESET" => {
+                    *self = Config::preset(value).ok_or_else(|| {
+                            ConfigError::invalid(&name, format!("unknown preset `{}`", value))
+                        })?
+                }
+                "COMBINER_EDITION" => self.edition = parse(&name, value)?,
+                "COMBINER_GRANULARITY" => self.granularity = parse(&name, value)?,
+                "COMBINER_MIN_LIST_LENGTH" => self.min_list_length = parse(&name, value)?,
+                "COMBINER_MIN_LIST_STATEMENTS" => {
+                    self.min_list_statements = parse(&name, value)?
+                }
+                "COMBINER_MAX_PATH_DEPTH" => self.max_path_depth = parse(&name, value)?,
+                "COMBINER_MAX_NODES" => self.max_nodes = parse(&name, value)?,
+                "COMBINER_SPILL_THRESHOLD" => self.spill_threshold = Some(parse(&name, value)?),
+                "COMBINER_ORDERING" => self.ordering = parse(&name, value)?,
+                "COMBINER_GLOB_POLICY" => self.glob_policy = parse(&name, value)?,
+                "COMBINER_GROUP_IMPORTS" => self.group_imports = parse(&name, value)?,
+                "COMBINER_PRETTY_PRINT" => self.pretty_print = parse(&name, value)?,
+                "COMBINER_KEYWORD_POLICY" => self.keyword_policy = parse(&name, value)?,
+                "COMBINER_SPLIT_TOP_LEVEL_CRATES" => {
+                    self.split_top_level_crates = parse(&name, value)?
+                }
+                "COMBINER_PROCESS_TEST_MODULES" => {
+                    self.process_test_modules = parse(&name, value)?
+                }
+                "COMBINER_CLIPPY_AWARE" => self.clippy_aware = parse(&name, value)?,
+                "COMBINER_STRICTNESS" => self.strictness = parse(&name, value)?,
+                "COMBINER_STRIP_REDUNDANT_RENAMES" => {
+                    self.strip_redundant_renames = parse(&name, value)?
+                }
+                "COMBINER_STRIP_SELF_PREFIX" => self.strip_self_prefix = parse(&name, value)?,
+                "COMBINER_MIGRATE_FROM_2015" => self.migrate_from_2015 = parse(&name, value)?,
+                "COMBINER_CONVERT_EXTERN_CRATES" => {
+                    self.convert_extern_crates = parse(&name, value)?
+                }
+                "COMBINER_CRATE_NAME" => self.crate_name = Some(value.to_string()),
+                "COMBINER_PREFER_CRATE_NAME" => self.prefer_crate_name = parse(&name, value)?,
+                "COMBINER_LINT_PRELUDE_ALIASES" => {
+                    self.lint_prelude_aliases = parse(&name, value)?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The config for splitting imports apart again, the inverse of combining them: with `Item`
+    /// granularity into one statement per item, and otherwise into one per module.
+    pub fn splitting(&self, granularity: Granularity) -> Config {
+        let item = granularity == Granularity::Item;
+        Config {
+            granularity: if item { Granularity::Item } else { Granularity::Module },
+            split_top_level_crates: true,
+            // A single item is better as a simple path than a list.
+            min_list_length: 2,
+            min_list_statements: 1,
+            ..self.clone()
+        }
+    }
+
+    /// Parse a config from TOML, with any missing options taking their default values, or with a
+    /// `preset` key, the values of that preset.
+    #[cfg(feature = "config-file")]
+    pub fn from_toml(toml: &str) -> Result<Config, String> {
+        use toml::Value;
+        let mut options: ::toml::value::Table = ::toml::from_str(toml).map_err(|e| e.to_string())?;
+        // The preset replaces everything, so the other options are applied over it.
+        let preset = match options.remove("preset") {
+            Some(Value::String(name)) => {
+                Config::preset(&name).ok_or_else(|| format!("unknown preset `{}`", name))?
+            }
+            Some(value) => return Err(format!("invalid preset `{}`: expected a string", value)),
+            None => Config::default(),
+        };
+        let mut table = match Value::try_from(&preset).map_err(|e| e.to_string())? {
+            Value::Table(table) => table,
+            // A struct is always serialized as a table.
+            _ => unreachable!(),
+        };
+        table.extend(options);
+        Value::Table(table).try_into().map_err(|e: ::toml::de::Error| e.to_string())
+    }
+
+    /// Load a config file, such as one found by `find_config_file`.
+    #[cfg(feature = "config-file")]
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let file_error = |message: String| {
+            ConfigError::File {
+                path: path.to_path_buf(),
+                message: message,
+            }
+        };
+        let toml = ::files::read_file(path).map_err(|e| file_error(e.to_string()))?;
+        Config::from_toml(&toml).map_err(&file_error)
+    }
+
+    /// Check that the options make sense together, rather than having the combiner silently
+    /// ignore one of them.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_list_length == 0 {
+            return Err(ConfigError::invalid("min_list_length",
+                                            "lists need at least one item".to_string()));
+        }
+        if self.max_path_depth == 0 || self.max_nodes == 0 {
+            let option = if self.max_nodes == 0 { "max_nodes" } else { "max_path_depth" };
+            return Err(ConfigError::invalid(option, "no imports would be allowed".to_string()));
+        }
+        match self.spill_threshold {
+            Some(0) => {
+                return Err(ConfigError::invalid("spill_threshold",
+                                                "every import would be spilled".to_string()));
+            }
+            Some(threshold) if threshold >= self.max_nodes => {
+                return Err(ConfigError::conflict("spill_threshold",
+                                                 "max_nodes",
+                                                 "imports would be rejected before any are \
+                                                  spilled"));
+            }
+            _ => {}
+        }
+        let nested = self.granularity == Granularity::Crate || self.granularity == Granularity::One;
+        if nested && !self.edition.supports_nested_groups() {
+            return Err(ConfigError::conflict("granularity",
+                                             "edition",
+                                             "nested groups aren't available in the 2015 \
+                                              edition"));
+        }
+        if self.granularity == Granularity::One && self.min_list_length > 2 {
+            return Err(ConfigError::conflict("granularity",
+                                             "min_list_length",
+                                             "everything is merged into one statement, so \
+                                              shorter lists can't be kept apart"));
+        }
+        if self.prefer_crate_name && self.crate_name.is_none() {
+            return Err(ConfigError::invalid("prefer_crate_name",
+                                            "the crate's name isn't given in crate_name"
+                                                .to_string()));
+        }
+        if self.migrate_from_2015 && self.edition == Edition::Edition2015 {
+            return Err(ConfigError::conflict("migrate_from_2015",
+                                             "edition",
+                                             "migrating from the 2015 edition needs a later \
+                                              edition to migrate to"));
+        }
+        if self.convert_extern_crates && !self.edition.allows_extern_crate_conversion() {
+            return Err(ConfigError::conflict("convert_extern_crates",
+                                             "edition",
+                                             "the 2015 edition needs `extern crate` items"));
+        }
+        if self.strictness == Strictness::Lossless && self.glob_policy == GlobPolicy::Deny {
+            return Err(ConfigError::conflict("strictness",
+                                             "glob_policy",
+                                             "denying globs drops the names they import"));
+        }
+        if self.clippy_aware && self.glob_policy == GlobPolicy::Deny {
+            return Err(ConfigError::conflict("clippy_aware",
+                                             "glob_policy",
+                                             "denied globs can't absorb explicit imports in the \
+                                              first place"));
+        }
+        if self.group_imports != GroupImports::Preserve && !self.groups.is_empty() {
+            return Err(ConfigError::conflict("group_imports",
+                                             "groups",
+                                             "the configured groups are only used with \
+                                              `Preserve`"));
+        }
+        if self.pretty_print && !cfg!(feature = "pretty") {
+            return Err(ConfigError::invalid("pretty_print",
+                                            "the combiner was built without the `pretty` feature"
+                                                .to_string()));
+        }
+        if self.granularity == Granularity::Item && self.min_list_statements > 1 {
+            return Err(ConfigError::conflict("granularity",
+                                             "min_list_statements",
+                                             "item granularity never creates lists"));
+        }
+        Ok(())
+    }
+}
+
+/// The nearest config file to `dir`: the first of `CONFIG_FILE_NAMES` in `dir` or, failing
+/// that, its closest ancestor containing one. Like rustfmt's, this lets subdirectories override
+/// the config of the project they're in.
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        for name in CONFIG_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            edition: Edition::default(),
+            granularity: Granularity::default(),
+            split_top_level_crates: false,
+            min_list_length: DEFAULT_MIN_LIST_LENGTH,
+            min_list_statements: 1,
+            ordering: ItemOrdering::default(),
+            self_position: SelfPosition::default(),
+            preserve_paths: vec![],
+            glob_policy: GlobPolicy::default(),
+            protected_globs: vec![],
+            groups: vec![],
+            group_imports: GroupImports::default(),
+            pretty_print: false,
+            process_test_modules: true,
+            clippy_aware: false,
+            strictness: Strictness::default(),
+            strip_redundant_renames: true,
+            strip_self_prefix: false,
+            migrate_from_2015: false,
+            extern_crates: vec![],
+            convert_extern_crates: false,
+            crate_name: None,
+            crate_names: BTreeMap::new(),
+            crate_kinds: BTreeMap::new(),
+            prefer_crate_name: false,
+            keyword_policy: KeywordPolicy::default(),
+            lint_prelude_aliases: false,
+            max_path_depth: DEFAULT_MAX_PATH_DEPTH,
+            max_nodes: DEFAULT_MAX_NODES,
+            spill_threshold: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn presets() {
+        for name in PRESET_NAMES {
+            assert!(Config::preset(name).is_some());
+        }
+        assert_eq!(Config::preset("compact"), Some(Config::compact()));
+        assert_eq!(Config::preset("rustfmt").map(|c| c.min_list_length), Some(2));
+        assert_eq!(Config::preset("unknown"), None);
+    }
+    #[test]
+    fn overrides() {
+        let vars = vec![("COMBINER_GLOB_POLICY".to_string(), "deny".to_string()),
+                        ("COMBINER_MIN_LIST_LENGTH".to_string(), "5".to_string()),
+                        ("COMBINER_STRICTNESS".to_string(), "strict".to_string()),
+                        ("COMBINER_PRESET".to_string(), "compact".to_string()),
+                        ("PATH".to_string(), "/bin".to_string())];
+        let mut config = Config::default();
+        assert_eq!(config.apply_overrides(vars), Ok(()));
+        assert_eq!(config,
+                   Config {
+                       glob_policy: GlobPolicy::Deny,
+                       min_list_length: 5,
+                       strictness: Strictness::Strict,
+                       ..Config::compact()
+                   });
+        let vars = vec![("COMBINER_MIN_LIST_LENGTH".to_string(), "many".to_string())];
+        assert_eq!(Config::default().apply_overrides(vars),
+                   Err(ConfigError::InvalidValue {
+                       option: "COMBINER_MIN_LIST_LENGTH".to_string(),
+                       message: "`many`: invalid digit found in string".to_string(),
+                   }));
+    }
+    #[test]
+    fn validation() {
+        for name in PRESET_NAMES {
+            assert_eq!(Config::preset(name).unwrap().validate(), Ok(()));
+        }
+        let config = Config { granularity: Granularity::One, ..Config::compact() };
+        assert_eq!(config.validate(), Ok(()));
+        // Crates are kept on separate statements even when everything else is merged.
+        let config = Config { split_top_level_crates: true, ..config };
+        assert_eq!(config.validate(), Ok(()));
+        let config = Config { edition: Edition::Edition2015, ..Config::compact() };
+        assert!(config.validate().is_err());
+        let config = Config {
+            strictness: Strictness::Lossless,
+            glob_policy: GlobPolicy::Deny,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config { spill_threshold: Some(DEFAULT_MAX_NODES), ..Config::default() };
+        assert!(config.validate().is_err());
+        let config = Config {
+            group_imports: GroupImports::StdExternalCrate,
+            groups: vec![ImportGroup::new("std", &["std::**"])],
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config { pretty_print: true, ..Config::default() };
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "pretty"));
+    }
+    #[test]
+    fn conflicts() {
+        let conflicts = vec![(Config { edition: Edition::Edition2015, ..Config::compact() },
+                              ("granularity", "edition")),
+                             (Config {
+                                  granularity: Granularity::One,
+                                  min_list_length: 3,
+                                  ..Config::compact()
+                              },
+                              ("granularity", "min_list_length")),
+                             (Config { migrate_from_2015: true, ..Config::default() },
+                              ("migrate_from_2015", "edition")),
+                             (Config { convert_extern_crates: true, ..Config::default() },
+                              ("convert_extern_crates", "edition")),
+                             (Config {
+                                  strictness: Strictness::Lossless,
+                                  glob_policy: GlobPolicy::Deny,
+                                  ..Config::default()
+                              },
+                              ("strictness", "glob_policy")),
+                             (Config {
+                                  clippy_aware: true,
+                                  glob_policy: GlobPolicy::Deny,
+                                  ..Config::default()
+                              },
+                              ("clippy_aware", "glob_policy")),
+                             (Config {
+                                  group_imports: GroupImports::One,
+                                  groups: vec![ImportGroup::new("std", &["std::**"])],
+                                  ..Config::default()
+                              },
+                              ("group_imports", "groups")),
+                             (Config {
+                                  granularity: Granularity::Item,
+                                  min_list_statements: 2,
+                                  ..Config::default()
+                              },
+                              ("granularity", "min_list_statements")),
+                             (Config {
+                                  spill_threshold: Some(10),
+                                  max_nodes: 10,
+                                  ..Config::default()
+                              },
+                              ("spill_threshold", "max_nodes"))];
+        for (config, (a, b)) in conflicts {
+            match config.validate() {
+                Err(ConfigError::Conflict { options, .. }) => {
+                    assert_eq!(options, (a.to_string(), b.to_string()))
+                }
+                result => panic!("{} with {}: unexpected {:?}", a, b, result),
+            }
+        }
+        let config = Config { granularity: Granularity::One, ..Config::compact() };
+        assert_eq!(config.validate(), Ok(()));
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize() {
+        let json = r#"{
+            "edition": "2018",
+            "granularity": "Crate",
+            "protected_globs": ["*::prelude::*"],
+            "groups": [{ "name": "std", "patterns": ["std::**", "core::**"] }]
+        }"#;
+        let config: Config = ::serde_json::from_str(json).unwrap();
+        assert_eq!(config,
+                   Config {
+                       edition: Edition::Edition2018,
+                       granularity: Granularity::Crate,
+                       protected_globs: vec![PathPattern::from("*::prelude::*")],
+                       groups: vec![ImportGroup::new("std", &["std::**", "core::**"])],
+                       ..Config::default()
+                   });
+        let round_trip: Config =
+            ::serde_json::from_str(&::serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(round_trip, config);
+    }
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn config_files() {
+        assert_eq!(Config::from_toml("edition = \"2018\"\nmin_list_length = 2\n"),
+                   Ok(Config {
+                       edition: Edition::Edition2018,
+                       min_list_length: 2,
+                       ..Config::default()
+                   }));
+        assert!(Config::from_toml("edition = 2018").is_err());
+        assert_eq!(Config::from_toml("min_list_length = 3\npreset = \"compact\"\n"),
+                   Ok(Config { min_list_length: 3, ..Config::compact() }));
+        assert!(Config::from_toml("preset = \"nonsense\"").is_err());
+        assert!(Config::from_toml("min_list_lenght = 2").unwrap_err().contains("min_list_lenght"));
+
+        let root = ::std::env::temp_dir()
+            .join(format!("combiner-config-{}", ::std::process::id()));
+        let nested = root.join("a").join("b");
+        ::std::fs::create_dir_all(&nested).unwrap();
+        ::files::write_file(&root.join("combiner.toml"), "").unwrap();
+        assert_eq!(find_config_file(&nested), Some(root.join("combiner.toml")));
+        ::files::write_file(&root.join("a").join(".combiner.toml"), "").unwrap();
+        assert_eq!(find_config_file(&nested), Some(root.join("a").join(".combiner.toml")));
+        ::std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt;
+
+use {Path, ViewPath};
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Severity {
+    /// Something the combiner tidied up, that needs no action.
+    Note,
+    /// The imports were combined anyway, but may need a look.
+    Warning,
+    /// The imports shouldn't be combined.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Severity::Note => write!(f, "note"),
+            &Severity::Warning => write!(f, "warning"),
+            &Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What a diagnostic is about.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DiagnosticKind {
+    /// Imports of different paths bind the same name, e.g. `a::b as x` and `c::d as x`, so the
+    /// combined imports define it more than once.
+    NameConflict(String),
+    /// An import (the diagnostic's only path) was repeated, and this many duplicates of it were
+    /// removed.
+    DuplicatesRemoved(usize),
+    /// A glob import of this module overlaps with explicit imports of names in it (the
+    /// diagnostic's paths, after the glob itself). Merging them is only neutral if the names
+    /// really are in the module, rather than being explicitly imported to override a glob's
+    /// conflicting name.
+    GlobOverlap(Path),
+    /// Globs of more than one module (the diagnostic's paths) import this name in the same
+    /// namespace, and nothing imports it explicitly, so using it is ambiguous.
+    AmbiguousGlobs(String),
+    /// An explicit import (the first path) was kept rather than absorbed by a glob of its module,
+    /// as the glob of another module (the second path) also imports its name.
+    KeptUnderGlob,
+    /// An import (the diagnostic's only path) uses this reserved keyword as a name, so it won't
+    /// compile.
+    ReservedKeyword(String),
+    /// An import (the diagnostic's only path) was rejected, as its path has this many segments,
+    /// more than `Config::max_path_depth`.
+    PathTooDeep(usize),
+    /// The imports (the diagnostic's paths) were rejected, as the combiner was already tracking
+    /// `Config::max_nodes` modules and items.
+    TooManyNodes,
+    /// An import (the diagnostic's only path) is renamed to this name from the standard prelude,
+    /// which it shadows.
+    PreludeAlias(String),
+    /// A `ConflictResolver` aborted combining a glob of this module with explicit imports of
+    /// names in it (the diagnostic's paths, after the glob itself).
+    AbortedConflict(Path),
+    /// Imports rename these names in a cycle (the first to the second, and so on, with the last
+    /// renamed to the first), e.g. `a as b` with `b as a`, which is easy to miss in a list. The
+    /// diagnostic's paths are the renaming imports.
+    AliasCycle(Vec<String>),
+    /// Imports from different origins (e.g. a module and an extern crate) start with this same
+    /// name, so they were kept apart rather than combined. The diagnostic's paths are the
+    /// imports from each origin.
+    AmbiguousOrigin(String),
+}
+
+impl DiagnosticKind {
+    /// The kind's name in snake case, e.g. `name_conflict`, for reporting diagnostics to tools.
+    pub fn name(&self) -> &'static str {
+        match self {
+            &DiagnosticKind::NameConflict(_) => "name_conflict",
+            &DiagnosticKind::DuplicatesRemoved(_) => "duplicates_removed",
+            &DiagnosticKind::GlobOverlap(_) => "glob_overlap",
+            &DiagnosticKind::AmbiguousGlobs(_) => "ambiguous_globs",
+            &DiagnosticKind::KeptUnderGlob => "kept_under_glob",
+            &DiagnosticKind::ReservedKeyword(_) => "reserved_keyword",
+            &DiagnosticKind::PathTooDeep(_) => "path_too_deep",
+            &DiagnosticKind::TooManyNodes => "too_many_nodes",
+            &DiagnosticKind::PreludeAlias(_) => "prelude_alias",
+            &DiagnosticKind::AbortedConflict(_) => "aborted_conflict",
+            &DiagnosticKind::AliasCycle(_) => "alias_cycle",
+            &DiagnosticKind::AmbiguousOrigin(_) => "ambiguous_origin",
+        }
+    }
+}
+
+/// Something the combiner noticed about the imports it was given.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    pub message: String,
+    /// The imports involved, as simple paths.
+    pub paths: Vec<ViewPath>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// What could be done about the diagnostic, if there's anything to suggest.
+    pub fn help(&self) -> Option<String> {
+        match self.kind {
+            DiagnosticKind::NameConflict(_) => {
+                Some("rename the imports with `as`, so that each name is only bound once"
+                    .to_string())
+            }
+            DiagnosticKind::GlobOverlap(ref module) => {
+                Some(format!("if the explicit imports override names from `{}::*`, mark them \
+                              with `// combiner: keep`",
+                             module.join("::")))
+            }
+            DiagnosticKind::AmbiguousGlobs(ref name) => {
+                Some(format!("import `{}` explicitly from the module it's meant to come from",
+                             name))
+            }
+            DiagnosticKind::ReservedKeyword(ref keyword) => {
+                Some(format!("use the raw identifier `r#{}`, or set `keyword_policy = \"Raw\"`",
+                             keyword))
+            }
+            DiagnosticKind::PathTooDeep(_) => {
+                Some("raise `max_path_depth`, if the path is meant to be that long".to_string())
+            }
+            DiagnosticKind::TooManyNodes => {
+                Some("raise `max_nodes`, or set `spill_threshold`".to_string())
+            }
+            DiagnosticKind::PreludeAlias(ref name) => {
+                Some(format!("rename the import to something other than `{}`", name))
+            }
+            DiagnosticKind::DuplicatesRemoved(_) |
+            DiagnosticKind::KeptUnderGlob |
+            DiagnosticKind::AbortedConflict(_) |
+            DiagnosticKind::AliasCycle(_) |
+            DiagnosticKind::AmbiguousOrigin(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Why `combine_imports_strict` refused to combine imports.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombineError {
+    /// An import doesn't look like one the parser produced cleanly, e.g. it has an empty or
+    /// malformed segment, or doesn't come back the same when rendered and parsed again.
+    Malformed { import: ViewPath, message: String },
+    /// Combining the imports would be lossy or ambiguous. These are the warnings and errors.
+    Diagnostics(Vec<Diagnostic>),
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &CombineError::Malformed { ref import, ref message } => {
+                write!(f, "malformed import `{}`: {}", import, message)
+            }
+            &CombineError::Diagnostics(ref diagnostics) => {
+                let messages: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+        }
+    }
+}
+
+impl Error for CombineError {
+    fn description(&self) -> &str {
+        match self {
+            &CombineError::Malformed { .. } => "malformed import",
+            &CombineError::Diagnostics(_) => "imports can't be combined cleanly",
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp;
+
+/// The number of unchanged lines shown around each change in a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffLine<'a> {
+    Context(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A line-by-line diff of `old` and `new`.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Changes are usually confined to the use statements, so only run the (quadratic) longest
+    // common subsequence over the part between the common prefix and suffix.
+    let prefix = old.iter().zip(&new).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Context(l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(DiffLine::Context(a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(a[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(b[j..].iter().map(|l| DiffLine::Added(l)));
+    lines.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Context(l)));
+    lines
+}
+
+/// A unified diff between `old` and `new`, or an empty string if they have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = lines.iter()
+        .enumerate()
+        .filter(|&(_, l)| match *l {
+            DiffLine::Context(_) => false,
+            _ => true,
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Gather the changes into hunks, merging them when their context would overlap.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = cmp::min(i + CONTEXT_LINES + 1, lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    // Line numbers (zero-based) of the start of the current line in the old and new text.
+    let (mut old_line, mut new_line, mut index) = (0, 0, 0);
+    for (start, end) in hunks {
+        for line in &lines[index..start] {
+            match *line {
+                DiffLine::Context(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                DiffLine::Removed(_) => old_line += 1,
+                DiffLine::Added(_) => new_line += 1,
+            }
+        }
+        let hunk = &lines[start..end];
+        let old_len = hunk.iter().filter(|l| match **l {
+                DiffLine::Added(_) => false,
+                _ => true,
+            })
+            .count();
+        let new_len = hunk.iter().filter(|l| match **l {
+                DiffLine::Removed(_) => false,
+                _ => true,
+            })
+            .count();
+        // An empty range is numbered by the line before it.
+        let old_start = if old_len == 0 { old_line } else { old_line + 1 };
+        let new_start = if new_len == 0 { new_line } else { new_line + 1 };
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_len, new_start, new_len));
+        for line in hunk {
+            diff.push_str(&match *line {
+                DiffLine::Context(l) => format!(" {}\n", l),
+                DiffLine::Removed(l) => format!("-{}\n", l),
+                DiffLine::Added(l) => format!("+{}\n", l),
+            });
+        }
+        old_line += old_len;
+        new_line += new_len;
+        index = end;
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn unified() {
+        let old = "use a::b;\nuse a::c;\nuse a::d;\n\nfn main() {}\n1\n2\n3\n4\n5\n\
+                   use x;\nuse y;\n";
+        let new = "use a::{b, c, d};\n\nfn main() {}\n1\n2\n3\n4\n5\nuse {x, y};\n";
+        assert_eq!(unified_diff(old, new, "a/f.rs", "b/f.rs"),
+                   "--- a/f.rs\n+++ b/f.rs\n\
+                    @@ -1,6 +1,4 @@\n\
+                    -use a::b;\n-use a::c;\n-use a::d;\n+use a::{b, c, d};\n \n fn main() {}\n 1\n\
+                    @@ -8,5 +6,4 @@\n\
+                    \x203\n 4\n 5\n-use x;\n-use y;\n+use {x, y};\n");
+        assert_eq!(unified_diff(old, old, "a", "b"), "");
+    }
+}
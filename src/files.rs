@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use Config;
+use source::combine_source;
+
+pub fn read_file(path: &Path) -> io::Result<String> {
+    let mut source = String::new();
+    File::open(path)?.read_to_string(&mut source)?;
+    Ok(source)
+}
+
+pub fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    let _span = debug_span!("write", path = %path.display()).entered();
+    File::create(path)?.write_all(contents.as_bytes())
+}
+
+/// Combine the imports in a file in place, returning whether it changed.
+pub fn combine_file(path: &Path, config: &Config) -> io::Result<bool> {
+    let source = read_file(path)?;
+    let combined = combine_source(&source, config);
+    if combined == source {
+        return Ok(false);
+    }
+    write_file(path, &combined)?;
+    Ok(true)
+}
+
+/// The name of the combiner's own ignore file, read alongside `.gitignore` and `.ignore`.
+pub const IGNORE_FILE_NAME: &'static str = ".combinerignore";
+
+/// All the `.rs` files under `dir`, in sorted order. Hidden directories and `target`
+/// directories are skipped, as are files ignored by `.gitignore`, `.ignore` or `.combinerignore`
+/// files (in `dir`, its subdirectories or its parents).
+pub fn find_rust_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let walker = WalkBuilder::new(dir)
+        .hidden(true)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .filter_entry(|entry| entry.file_name() != "target")
+        .build();
+    let mut files = vec![];
+    for entry in walker {
+        let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let path = entry.path();
+        if path.extension().map_or(false, |e| e == "rs") &&
+           entry.file_type().map_or(false, |t| t.is_file()) {
+            files.push(path.to_path_buf());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `text` matches a glob `pattern`, in which `?` matches any character except `/`, `*`
+/// matches any run of characters except `/`, and `**/` matches any number of directories.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(p: &[char], t: &[char]) -> bool {
+        match p.first() {
+            None => t.is_empty(),
+            Some(&'*') if p.get(1) == Some(&'*') => {
+                // `**/` can match nothing at all; otherwise `**` crosses directories.
+                let rest = if p.get(2) == Some(&'/') { &p[3..] } else { &p[2..] };
+                (0..t.len() + 1).any(|n| matches(rest, &t[n..]))
+            }
+            Some(&'*') => {
+                (0..t.len() + 1).take_while(|&n| n == 0 || t[n - 1] != '/')
+                    .any(|n| matches(&p[1..], &t[n..]))
+            }
+            Some(&'?') => !t.is_empty() && t[0] != '/' && matches(&p[1..], &t[1..]),
+            Some(c) => t.first() == Some(c) && matches(&p[1..], &t[1..]),
+        }
+    }
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    matches(&p, &t)
+}
+
+/// Include and exclude globs for the files found when walking a directory. Patterns containing
+/// a `/` are matched against the path relative to the directory being walked, and other
+/// patterns against the file name alone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileFilter {
+    /// If not empty, only files matching one of these patterns are included.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        let path = relative_path.to_string_lossy().replace('\\', "/");
+        let name = relative_path.file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        let matches = |pattern: &String| if pattern.contains('/') {
+            glob_match(pattern, &path)
+        } else {
+            glob_match(pattern, &name)
+        };
+        (self.include.is_empty() || self.include.iter().any(&matches)) &&
+        !self.exclude.iter().any(&matches)
+    }
+
+    /// The `.rs` files under `dir` passing the filter.
+    pub fn find_rust_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(find_rust_files(dir)?
+            .into_iter()
+            .filter(|f| self.matches(f.strip_prefix(dir).unwrap_or(f)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    #[test]
+    fn globs() {
+        assert!(glob_match("*.rs", "lib.rs"));
+        assert!(!glob_match("*.rs", "src/lib.rs"));
+        assert!(glob_match("src/**/*.rs", "src/lib.rs"));
+        assert!(glob_match("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(glob_match("**/generated/*", "a/generated/x.rs"));
+        assert!(glob_match("?.rs", "a.rs"));
+        assert!(!glob_match("src/*.rs", "src/a/b.rs"));
+    }
+    #[test]
+    fn filters() {
+        let filter = FileFilter {
+            include: vec!["src/**/*.rs".to_string()],
+            exclude: vec!["*_generated.rs".to_string()],
+        };
+        assert!(filter.matches(Path::new("src/a/b.rs")));
+        assert!(!filter.matches(Path::new("src/a/b_generated.rs")));
+        assert!(!filter.matches(Path::new("tests/b.rs")));
+    }
+}
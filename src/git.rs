@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Which changes to ask git for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitChanges {
+    /// Files changed in the working tree relative to a ref (e.g. `origin/master`).
+    Since(String),
+    /// Files with staged changes.
+    Staged,
+}
+
+// Run git in `dir`, returning its output.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("couldn't run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}",
+                           args[0],
+                           String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The `.rs` files that have been added or modified in the git repository containing `dir`,
+/// as absolute paths. Deleted files aren't included.
+pub fn git_changed_files(dir: &Path, changes: &GitChanges) -> Result<Vec<PathBuf>, String> {
+    let toplevel = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let names = match *changes {
+        GitChanges::Since(ref since) => {
+            git(dir, &["diff", "--name-only", "--diff-filter=ACMR", since, "--"])?
+        }
+        GitChanges::Staged => git(dir, &["diff", "--name-only", "--diff-filter=ACMR", "--cached"])?,
+    };
+    Ok(parse_name_list(Path::new(toplevel.trim()), &names))
+}
+
+/// Parse the output of `git diff --name-only`, keeping the `.rs` files and making their paths
+/// absolute using the repository's top-level directory.
+pub fn parse_name_list(toplevel: &Path, names: &str) -> Vec<PathBuf> {
+    names.lines()
+        .map(|name| name.trim())
+        .filter(|name| name.ends_with(".rs"))
+        .map(|name| toplevel.join(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    #[test]
+    fn name_list() {
+        assert_eq!(parse_name_list(Path::new("/repo"), "src/lib.rs\nREADME.md\nsrc/a/b.rs\n"),
+                   vec![PathBuf::from("/repo/src/lib.rs"), PathBuf::from("/repo/src/a/b.rs")]);
+        assert_eq!(parse_name_list(Path::new("/repo"), ""), Vec::<PathBuf>::new());
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use files::{read_file, write_file};
+
+/// The name of the journal file written (in the current directory) by a run that makes backups.
+pub const JOURNAL_FILE_NAME: &'static str = ".combiner-journal";
+
+/// The path of the backup of `path` made before rewriting it: `path` with `.orig` appended.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".orig");
+    PathBuf::from(backup)
+}
+
+/// A record of the files backed up during a run, so that the run can be undone. It's saved as a
+/// list of absolute paths, one per line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Journal {
+    path: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl Journal {
+    /// A new, empty journal to be saved at `path`.
+    pub fn new(path: &Path) -> Journal {
+        Journal {
+            path: path.to_path_buf(),
+            files: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Journal> {
+        Ok(Journal {
+            path: path.to_path_buf(),
+            files: read_file(path)?.lines().filter(|l| !l.is_empty()).map(PathBuf::from).collect(),
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut contents = String::new();
+        for file in &self.files {
+            contents.push_str(&file.to_string_lossy());
+            contents.push('\n');
+        }
+        write_file(&self.path, &contents)
+    }
+
+    /// The files backed up so far.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Copy `file` to its backup path and record it in the journal. A file that's already in
+    /// the journal isn't copied again, as it may have been rewritten since its backup was made.
+    pub fn backup(&mut self, file: &Path) -> io::Result<()> {
+        let file = file.canonicalize()?;
+        if !self.files.contains(&file) {
+            fs::copy(&file, backup_path(&file))?;
+            self.files.push(file);
+        }
+        Ok(())
+    }
+
+    /// Restore every file in the journal from its backup, removing the backups and the journal
+    /// itself, and returning the restored files.
+    pub fn undo(self) -> io::Result<Vec<PathBuf>> {
+        for file in &self.files {
+            let backup = backup_path(file);
+            fs::copy(&backup, file)?;
+            fs::remove_file(&backup)?;
+        }
+        fs::remove_file(&self.path)?;
+        Ok(self.files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use files::{read_file, write_file};
+    #[test]
+    fn backup_and_undo() {
+        assert_eq!(backup_path(Path::new("src/lib.rs")), Path::new("src/lib.rs.orig"));
+
+        let dir = env::temp_dir().join(format!("combiner-journal-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        let journal_path = dir.join(JOURNAL_FILE_NAME);
+        write_file(&file, "original").unwrap();
+
+        let mut journal = Journal::new(&journal_path);
+        journal.backup(&file).unwrap();
+        write_file(&file, "rewritten").unwrap();
+        // Processing the file again in the same run keeps the backup of the original.
+        journal.backup(&file).unwrap();
+        journal.save().unwrap();
+        write_file(&file, "rewritten again").unwrap();
+
+        let journal = Journal::load(&journal_path).unwrap();
+        assert_eq!(journal.files().len(), 1);
+        journal.undo().unwrap();
+        assert_eq!(read_file(&file).unwrap(), "original");
+        assert!(!backup_path(&file).exists());
+        assert!(!journal_path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
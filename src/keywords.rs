@@ -0,0 +1,42 @@
+use Edition;
+
+// The keywords (used or reserved) that are never identifiers, besides the path keywords (`self`,
+// `Self`, `super` and `crate`), which are allowed in paths but can't be raw identifiers.
+const KEYWORDS: &'static [&'static str] =
+    &["as", "break", "const", "continue", "else", "enum", "extern", "false", "fn", "for", "if",
+      "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+      "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract",
+      "become", "box", "do", "final", "macro", "override", "priv", "typeof", "unsized",
+      "virtual", "yield"];
+
+// The keywords added by the 2018 edition.
+const KEYWORDS_2018: &'static [&'static str] = &["async", "await", "dyn", "try"];
+
+// The names the standard prelude brings into every module (in every edition).
+const PRELUDE_NAMES: &'static [&'static str] =
+    &["AsMut", "AsRef", "Box", "Clone", "Copy", "Default", "DoubleEndedIterator", "Drop", "Eq",
+      "Err", "ExactSizeIterator", "Extend", "Fn", "FnMut", "FnOnce", "From", "Into",
+      "IntoIterator", "Iterator", "None", "Ok", "Option", "Ord", "PartialEq", "PartialOrd",
+      "Result", "Send", "Sized", "Some", "String", "Sync", "ToOwned", "ToString", "Unpin", "Vec",
+      "drop"];
+
+/// Whether `word` is one of the names the standard prelude brings into scope.
+pub fn is_prelude_name(word: &str) -> bool {
+    PRELUDE_NAMES.contains(&word)
+}
+
+/// Whether `word` is a keyword that can't be used as a path segment or name in `edition`
+/// (unless it's written as a raw identifier, like `r#fn`).
+pub fn is_reserved_keyword(word: &str, edition: Edition) -> bool {
+    KEYWORDS.contains(&word) ||
+    (edition >= Edition::Edition2018 && KEYWORDS_2018.contains(&word))
+}
+
+/// `word` as a raw identifier if it's a reserved keyword in `edition`.
+pub fn raw_identifier(word: &str, edition: Edition) -> String {
+    if is_reserved_keyword(word, edition) {
+        format!("r#{}", word)
+    } else {
+        word.to_string()
+    }
+}
@@ -1,28 +1,192 @@
+#[cfg(feature = "syntex")]
 extern crate syntex_syntax as syntax;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+#[cfg(feature = "config-file")]
+extern crate toml;
+#[cfg(feature = "fs")]
+extern crate ignore;
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(feature = "lsp")]
+extern crate lsp_server;
+#[cfg(feature = "lsp")]
+extern crate lsp_types;
+// PyO3's macros refer to `::core`, which is only in the extern prelude from the 2018 edition.
+#[cfg(feature = "python")]
+extern crate core;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "pretty")]
+extern crate prettyplease;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "fx-children")]
+extern crate rustc_hash;
+#[cfg(feature = "ra-syntax")]
+extern crate ra_ap_syntax;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+extern crate smallvec;
+#[cfg(feature = "pretty")]
+extern crate syn;
+#[cfg(test)]
+extern crate rustfix;
+#[macro_use]
+extern crate tracing;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::mem;
+use std::sync::{Arc, OnceLock};
 use std::vec::Vec;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use tree::{ChildMap, Cursor, GroupLen, ImportTree, ROOT};
+
+mod borrowed;
+#[cfg(feature = "fs")]
+mod cache;
+mod config;
+mod diagnostics;
+mod diff;
+#[cfg(feature = "fs")]
+mod files;
+#[cfg(feature = "fs")]
+mod git;
+#[cfg(feature = "fs")]
+mod journal;
+mod keywords;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod metadata;
+mod ordering;
+mod parser;
+mod path;
+mod pattern;
+#[cfg(feature = "pretty")]
+mod pretty;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "ra-syntax")]
+mod ra_syntax;
+mod render;
+mod resolver;
+mod rustc_style;
+mod source;
+mod stats;
+mod suggestions;
+mod symbols;
+mod tree;
+mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use borrowed::ViewPathRef;
+#[cfg(feature = "fs")]
+pub use cache::{CACHE_FILE_NAME, HashCache, content_hash};
+pub use config::{CONFIG_FILE_NAMES, Config, ConfigError, Edition, GlobPolicy, Granularity,
+                 GroupImports, KeywordPolicy, PRESET_NAMES, Strictness, find_config_file};
+pub use diagnostics::{CombineError, Diagnostic, DiagnosticKind, Severity};
+pub use diff::{DiffLine, diff_lines, unified_diff};
+#[cfg(feature = "fs")]
+pub use files::{FileFilter, IGNORE_FILE_NAME, combine_file, find_rust_files, glob_match,
+                read_file, write_file};
+#[cfg(feature = "fs")]
+pub use git::{GitChanges, git_changed_files, parse_name_list};
+#[cfg(feature = "fs")]
+pub use journal::{JOURNAL_FILE_NAME, Journal, backup_path};
+pub use keywords::{is_prelude_name, is_reserved_keyword, raw_identifier};
+#[cfg(feature = "lsp")]
+pub use lsp::{COMBINE_IMPORTS_TITLE, LanguageServer};
+pub use metadata::{CargoDependency, CargoPackage, CargoTarget, CrateKind, containing_package,
+                   parse_metadata};
+#[cfg(feature = "fs")]
+pub use metadata::cargo_metadata;
+pub use ordering::{ItemOrdering, SelfPosition};
+pub use parser::{ParserBackend, StringSplitter};
+pub use path::Path;
+pub use pattern::PathPattern;
+#[cfg(feature = "pretty")]
+pub use pretty::{pretty_print_statements, use_item};
+#[cfg(feature = "ra-syntax")]
+pub use ra_syntax::{view_path_from_use, view_path_from_use_tree};
+pub use render::{EditionError, ImportGroup, downgrade_for_edition, render_imports,
+                 std_external_crate_group, validate_imports};
+pub use resolver::{Conflict, ConflictResolver, Resolution};
+pub use rustc_style::render_diagnostic;
+pub use source::{DuplicateImport, SourceDiagnostic, SourceEdit, UseBlock, apply_edits,
+                 combine_source, combine_source_with_parser, duplicate_imports,
+                 enables_wildcard_imports_lint, find_use_blocks, find_use_blocks_with,
+                 line_column, normalize_source, source_diagnostics, source_edits,
+                 source_edits_with_parser, split_source, utf16_position};
+pub use stats::{CombinerMetrics, ImportStats};
+pub use suggestions::{COMBINABLE_IMPORTS_CODE, rustc_diagnostic};
+pub use symbols::{Namespace, SymbolTable};
+pub use verify::{Divergence, Mismatch, verify, verify_equivalent, verify_with};
+#[cfg(feature = "wasm")]
+pub use wasm::{combine, compute_edits};
+
+// With fewer top-level names than this, combining their imports in parallel isn't worth it.
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_NAMES: usize = 32;
+
+// Batches of imports with paths at least this deep (as generated bindings have) have the chains
+// of modules that only lead to other modules compressed, once they've been added.
+const CHAIN_COMPRESSION_DEPTH: usize = 16;
+
+/// The crates that are always available without being declared as dependencies.
+pub const SYSROOT_CRATES: &'static [&'static str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+/// The segments of a path written as text, such as `a::b::c`, borrowed from it. Walking them
+/// this way doesn't allocate, as `as_path` does.
+pub fn path_segments(p: &str) -> impl Iterator<Item = &str> {
+    p.split("::")
+}
 
-pub type Path = Vec<String>;
 pub fn as_path(p: &str) -> Path {
-    p.split("::").map(String::from).collect()
+    path_segments(p).map(String::from).collect()
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// The path of an import in the combiner's tree. A chain of `super`s is a single segment (e.g.
+// `super::super`), so that imports relative to different ancestors are never combined under
+// each other.
+fn tree_path(path: Path) -> Path {
+    let supers = path.iter().take_while(|s| *s == "super").count();
+    if supers < 2 {
+        return path;
+    }
+    let mut tree_path = Path::from(path[..supers].join("::"));
+    tree_path.extend(path[supers..].iter().cloned());
+    tree_path
+}
+
+// A path in the combiner's tree as an import's path.
+fn expand_tree_path(path: &[String]) -> Path {
+    path.iter().flat_map(|s| path_segments(s)).map(String::from).collect()
+}
+
+// Whether the first segment of a path in the combiner's tree makes it relative to the current
+// module (or one of its ancestors), so that it can't be nested in a brace group.
+fn is_relative_root(segment: &str) -> bool {
+    segment == "self" || segment == "super" || segment.starts_with("super::")
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Item(pub String, pub Option<String>);
 
 impl<'a> From<&'a str> for Item {
     fn from(s: &str) -> Item {
-        let trimmed = s.trim();
-        let elements: Vec<&str> = trimmed.split_whitespace().collect();
-        if elements.len() == 3 && elements[1] == "as" {
-            Item(elements[0].to_string(), Some(elements[2].to_string()))
-        } else {
-            Item(trimmed.to_string(), None)
-        }
+        let (name, rename) = borrowed::parse_item(s);
+        Item(name.to_string(), rename.map(String::from))
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ViewPath {
     /// `foo::bar::baz as quux`
     ///
@@ -36,36 +200,280 @@ pub enum ViewPath {
 
     /// `foo::bar::{a,b,c}`
     ViewPathList(Path, Vec<Item>),
+
+    /// `foo::{bar::{a, b}, baz::*, self}`, where each nested view path is relative to the prefix
+    ViewPathNested(Path, Vec<ViewPath>),
 }
 
 impl<'a> From<&'a str> for ViewPath {
     fn from(s: &str) -> ViewPath {
-        let path = as_path(s);
-        let mut trimmed_path = path[0..path.len() - 1].to_vec();
-        let last = path.last().map(|s| s.clone()).unwrap_or(String::new());
-        if path.len() > 1 && "*" == last.as_str() {
-            ViewPath::ViewPathGlob(trimmed_path)
-        } else if last.starts_with("{") && last.ends_with("}") {
-            let items: Vec<_> = last[1..last.len() - 1].split(",").map(|s| Item::from(s)).collect();
-            if items.len() == 1 && items[0].0 == "self" {
-                ViewPath::ViewPathSimple(trimmed_path, items[0].1.clone())
-            } else {
-                ViewPath::ViewPathList(trimmed_path, items)
+        ViewPathRef::parse(s).to_view_path()
+    }
+}
+
+impl ViewPath {
+    pub fn path(&self) -> &Path {
+        match self {
+            &ViewPath::ViewPathSimple(ref p, _) => p,
+            &ViewPath::ViewPathGlob(ref p) => p,
+            &ViewPath::ViewPathList(ref p, _) => p,
+            &ViewPath::ViewPathNested(ref p, _) => p,
+        }
+    }
+
+    fn path_mut(&mut self) -> &mut Path {
+        match self {
+            &mut ViewPath::ViewPathSimple(ref mut p, _) => p,
+            &mut ViewPath::ViewPathGlob(ref mut p) => p,
+            &mut ViewPath::ViewPathList(ref mut p, _) => p,
+            &mut ViewPath::ViewPathNested(ref mut p, _) => p,
+        }
+    }
+
+    /// This view path with `prefix` prepended to its path. A nested `self` becomes the prefix
+    /// itself.
+    pub fn prefixed(&self, prefix: &[String]) -> ViewPath {
+        let mut vp = match self {
+            &ViewPath::ViewPathSimple(ref p, ref rename) if p.len() == 1 && p[0] == "self" => {
+                return ViewPath::ViewPathSimple(Path::from(prefix), rename.clone());
             }
-        } else {
-            let last_path_element_as_item = Item::from(&last[..]);
-            trimmed_path.push(last_path_element_as_item.0);
-            ViewPath::ViewPathSimple(trimmed_path, last_path_element_as_item.1)
+            _ => self.clone(),
+        };
+        {
+            let path = vp.path_mut();
+            let mut full_path = Path::from(prefix);
+            full_path.extend(path.drain(..));
+            *path = full_path;
+        }
+        vp
+    }
+
+    // As `prefixed`, consuming this view path.
+    fn into_prefixed(mut self, prefix: &[String]) -> ViewPath {
+        if let ViewPath::ViewPathSimple(ref p, ref mut rename) = self {
+            if p.len() == 1 && p[0] == "self" {
+                return ViewPath::ViewPathSimple(Path::from(prefix), rename.take());
+            }
+        }
+        {
+            let path = self.path_mut();
+            let mut full_path = Path::with_capacity(prefix.len() + path.len());
+            full_path.extend(prefix.iter().cloned());
+            full_path.extend(path.drain(..));
+            *path = full_path;
+        }
+        self
+    }
+
+    /// A brace group of `nested` under `path`, using a plain list when none of the nested view
+    /// paths need more than a single name.
+    pub fn group(path: Path, nested: Vec<ViewPath>) -> ViewPath {
+        let mut items = vec![];
+        for vp in &nested {
+            match vp {
+                &ViewPath::ViewPathSimple(ref p, ref rename) if p.len() == 1 => {
+                    items.push(Item(p[0].clone(), rename.clone()))
+                }
+                _ => return ViewPath::ViewPathNested(path, nested),
+            }
+        }
+        ViewPath::ViewPathList(path, items)
+    }
+
+    /// The equivalent non-nested view paths.
+    pub fn flatten(&self) -> Vec<ViewPath> {
+        match self {
+            &ViewPath::ViewPathNested(ref p, ref nested) => {
+                nested.iter().flat_map(|vp| vp.prefixed(p).flatten()).collect()
+            }
+            _ => vec![self.clone()],
+        }
+    }
+
+    // The number of path segments (including list items) in the view path, and the number of
+    // bytes it has allocated on the heap.
+    fn sizes(&self) -> (usize, usize) {
+        let string = |s: &String| s.capacity();
+        match self {
+            &ViewPath::ViewPathSimple(ref p, ref rename) => {
+                (p.len(), p.heap_bytes() + rename.as_ref().map_or(0, string))
+            }
+            &ViewPath::ViewPathGlob(ref p) => (p.len(), p.heap_bytes()),
+            &ViewPath::ViewPathList(ref p, ref items) => {
+                let names: usize = items.iter()
+                    .map(|&Item(ref name, ref rename)| {
+                        name.capacity() + rename.as_ref().map_or(0, string)
+                    })
+                    .sum();
+                (p.len() + items.len(),
+                 p.heap_bytes() + items.capacity() * mem::size_of::<Item>() + names)
+            }
+            &ViewPath::ViewPathNested(ref p, ref nested) => {
+                let mut sizes = (p.len(),
+                                 p.heap_bytes() + nested.capacity() * mem::size_of::<ViewPath>());
+                for vp in nested {
+                    let (segments, bytes) = vp.sizes();
+                    sizes.0 += segments;
+                    sizes.1 += bytes;
+                }
+                sizes
+            }
+        }
+    }
+
+    /// The equivalent simple and glob view paths, one for each name imported.
+    pub fn simple_paths(&self) -> Vec<ViewPath> {
+        let mut paths = vec![];
+        for vp in self.flatten() {
+            match vp {
+                ViewPath::ViewPathList(p, items) => {
+                    for Item(name, rename) in items {
+                        if name == "*" {
+                            paths.push(ViewPath::ViewPathGlob(p.clone()));
+                            continue;
+                        }
+                        let mut path = p.clone();
+                        if name != "self" {
+                            path.push(name);
+                        }
+                        paths.push(ViewPath::ViewPathSimple(path, rename));
+                    }
+                }
+                vp => paths.push(vp),
+            }
+        }
+        paths
+    }
+
+    /// This 2015 edition view path rewritten for later editions, in which use paths are no
+    /// longer crate-relative. Paths starting with one of `extern_crates` (or one of
+    /// `SYSROOT_CRATES`) lose any leading `::`, and other paths are prefixed with `crate::`.
+    /// Paths relative to `self`, `super` or `crate` are left as they are.
+    pub fn migrated_from_2015(&self, extern_crates: &[String]) -> ViewPath {
+        let mut path = self.path().clone();
+        if path.first().map_or(false, |s| s.is_empty()) {
+            path.remove(0);
+        }
+        let first = match path.first() {
+            Some(first) => first.clone(),
+            // `use ::{a, b};` imports from each crate or module separately.
+            None => {
+                return match self {
+                    &ViewPath::ViewPathGlob(_) => ViewPath::ViewPathGlob(as_path("crate")),
+                    &ViewPath::ViewPathList(_, ref items) => {
+                        let nested = items.iter()
+                            .map(|&Item(ref name, ref rename)| {
+                                ViewPath::ViewPathSimple(Path::from(name.clone()), rename.clone())
+                                    .migrated_from_2015(extern_crates)
+                            })
+                            .collect();
+                        ViewPath::ViewPathNested(Path::new(), nested)
+                    }
+                    &ViewPath::ViewPathNested(_, ref nested) => {
+                        let nested =
+                            nested.iter().map(|vp| vp.migrated_from_2015(extern_crates)).collect();
+                        ViewPath::ViewPathNested(Path::new(), nested)
+                    }
+                    &ViewPath::ViewPathSimple(..) => self.clone(),
+                };
+            }
+        };
+        let external = SYSROOT_CRATES.contains(&&first[..]) || extern_crates.contains(&first);
+        if first == "self" || first == "super" || first == "crate" {
+            return self.clone();
+        } else if !external {
+            path.insert(0, "crate".to_string());
+        }
+        let mut vp = self.clone();
+        *vp.path_mut() = path;
+        vp
+    }
+
+    // This view path with each name (path segment, item or rename) mapped through `f`.
+    fn map_names<F: Fn(&str) -> String>(&self, f: &F) -> ViewPath {
+        let path: Path = self.path().iter().map(|s| f(s)).collect();
+        match self {
+            &ViewPath::ViewPathSimple(_, ref rename) => {
+                ViewPath::ViewPathSimple(path, rename.as_ref().map(|r| f(r)))
+            }
+            &ViewPath::ViewPathGlob(_) => ViewPath::ViewPathGlob(path),
+            &ViewPath::ViewPathList(_, ref items) => {
+                let items = items.iter()
+                    .map(|&Item(ref name, ref rename)| Item(f(name), rename.as_ref().map(|r| f(r))))
+                    .collect();
+                ViewPath::ViewPathList(path, items)
+            }
+            &ViewPath::ViewPathNested(_, ref nested) => {
+                ViewPath::ViewPathNested(path, nested.iter().map(|vp| vp.map_names(f)).collect())
+            }
+        }
+    }
+
+    // This view path from the combiner's tree with the path of each statement expanded.
+    fn expand_tree_root(&mut self) {
+        if let &mut ViewPath::ViewPathNested(ref p, ref mut nested) = self {
+            if p.is_empty() {
+                for vp in nested {
+                    vp.expand_tree_root();
+                }
+                return;
+            }
+        }
+        let path = self.path_mut();
+        *path = expand_tree_path(path);
+    }
+
+    // 2015 edition use paths are crate-relative, so `crate::a::b` is spelt `::a::b`.
+    fn to_edition_2015_root(&mut self) {
+        self.rename_root("crate", "");
+    }
+
+    // Replace a first segment of `from` with `to`.
+    fn rename_root(&mut self, from: &str, to: &str) {
+        self.map_root(&|root| if root == from { Some(to.to_string()) } else { None });
+    }
+
+    // Replace the first segment of each path with what `f` maps it to (if anything), returning
+    // whether any were replaced.
+    fn map_root<F: Fn(&str) -> Option<String>>(&mut self, f: &F) -> bool {
+        if let &mut ViewPath::ViewPathNested(ref p, ref mut nested) = self {
+            if p.is_empty() {
+                return nested.iter_mut().fold(false, |mapped, vp| vp.map_root(f) || mapped);
+            }
+        }
+        let path = self.path_mut();
+        match path.first().and_then(|s| f(s)) {
+            Some(root) => {
+                path[0] = root;
+                true
+            }
+            None => false,
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ImportNode {
     pub has_self: bool,
     pub has_glob: bool,
-    pub renames: Vec<String>,
-    pub children: BTreeMap<String, ImportNode>,
+    /// The names this node is imported as, other than its own.
+    pub renames: BTreeSet<String>,
+    // The indices of the children in the combiner's `ImportTree`.
+    children: ChildMap,
+    // The node's name, when it's the end of a compressed chain of nodes (such as `b::c::d`,
+    // which its parent has under `b`).
+    chain: Option<String>,
+    /// The (zero-based) indices of the input statements that imported this node.
+    pub statements: BTreeSet<usize>,
+    /// Whether explicit imports of this module's children are kept alongside its glob, rather
+    /// than absorbed by it, as a `ConflictResolver` chose.
+    pub keeps_explicit: bool,
+    // The length of the node's imports as a nested group, once it's been worked out. It's
+    // cleared whenever the node, or anything under it, changes. (It's a `OnceLock` rather than
+    // a `Cell` so that the tree can be shared between threads.)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    group_len: OnceLock<GroupLen>,
 }
 
 impl ImportNode {
@@ -73,168 +481,1742 @@ impl ImportNode {
         ImportNode {
             has_self: false,
             has_glob: false,
-            renames: vec![],
-            children: BTreeMap::new(),
+            renames: BTreeSet::new(),
+            children: ChildMap::default(),
+            chain: None,
+            statements: BTreeSet::new(),
+            keeps_explicit: false,
+            group_len: OnceLock::new(),
+        }
+    }
+    fn self_or_rename(rename: Option<String>) -> ImportNode {
+        ImportNode {
+            has_self: rename.is_none(),
+            renames: rename.into_iter().collect(),
+            ..ImportNode::new()
+        }
+    }
+    fn just_glob() -> ImportNode {
+        ImportNode { has_glob: true, ..ImportNode::new() }
+    }
+    // Combine the flags of `b` into this node.
+    fn combine_flags(&mut self, b: &ImportNode) {
+        self.has_self |= b.has_self;
+        self.has_glob |= b.has_glob;
+        self.keeps_explicit |= b.keeps_explicit;
+    }
+    // `as _` only brings a trait into scope, which importing it by any name already does.
+    fn drop_underscore_rename(&mut self) {
+        if self.has_self || self.renames.iter().any(|r| r != "_") {
+            self.renames.remove("_");
+        }
+    }
+    fn ordered_renames(&self, config: &Config) -> Vec<&String> {
+        let mut renames: Vec<_> = self.renames.iter().collect();
+        renames.sort_by(|a, b| config.ordering.compare(a, b));
+        renames
+    }
+    // The imports for this node as a list of view paths relative to it, using nested groups for
+    // children that import more than one thing. `node_path` borrows its segments from the tree.
+    fn get_nested_imports<'a>(&'a self,
+                              tree: &'a ImportTree,
+                              config: &Config,
+                              self_already_consumed: bool,
+                              node_path: &mut Vec<&'a str>)
+                              -> Vec<ViewPath> {
+        let node = self;
+        let self_path = Path::from("self".to_string());
+        let mut imports = vec![];
+        if node.has_self && !self_already_consumed {
+            imports.push(ViewPath::ViewPathSimple(self_path.clone(), None));
+        }
+        for r in node.ordered_renames(config) {
+            imports.push(ViewPath::ViewPathSimple(self_path.clone(), Some(r.clone())));
+        }
+        let emit_glob = node.has_glob && config.emits_glob(&owned_path(node_path));
+        if emit_glob {
+            imports.push(ViewPath::ViewPathGlob(Path::new()));
+        }
+        let absorb = emit_glob && config.absorbs_under_globs() && !node.keeps_explicit;
+        let mut children = vec![];
+        for (name, child) in tree.ordered_children(node, config) {
+            node_path.push(name);
+            let mut nested = child.get_nested_imports(tree, config, absorb, node_path);
+            node_path.pop();
+            let name = as_path(name);
+            if nested.len() == 1 {
+                children.push(nested.remove(0).into_prefixed(&name));
+            } else if !nested.is_empty() {
+                children.push(ViewPath::group(name, nested));
+            }
+        }
+        match config.self_position {
+            SelfPosition::First => imports.append(&mut children),
+            SelfPosition::Last => {
+                children.append(&mut imports);
+                imports = children;
+            }
+            SelfPosition::Sorted => {
+                imports.append(&mut children);
+                imports.sort_by(|a, b| {
+                    let a = a.path().first().map_or("*", |s| s.as_str());
+                    let b = b.path().first().map_or("*", |s| s.as_str());
+                    config.ordering.compare(a, b)
+                });
+            }
+        }
+        imports
+    }
+}
+
+// A path of segments borrowed from the tree, copied for an import (with any segments that are
+// compressed chains split up).
+fn owned_path(segments: &[&str]) -> Path {
+    segments.iter().flat_map(|s| path_segments(s)).map(String::from).collect()
+}
+
+// Add the imports for `node` itself, returning whether they consumed the selves and
+// renames of its children. `node_path` borrows its segments from the tree, and is only copied
+// for the imports that are added.
+fn get_imports_for_node(tree: &ImportTree,
+                        config: &Config,
+                        node: &ImportNode,
+                        self_already_consumed: bool,
+                        renames_already_consumed: bool,
+                        node_path: &[&str],
+                        imports: &mut Vec<ViewPath>)
+                        -> (bool, bool) {
+    let mut consumed_child_selves = false;
+    let mut consumed_child_renames = false;
+    let need_self_declaration = node.has_self && !self_already_consumed;
+    let emit_glob = node.has_glob && config.emits_glob(&owned_path(node_path));
+    let absorb = emit_glob && config.absorbs_under_globs() && !node.keeps_explicit;
+
+    // A list is only possible if the config allows one here and it would be long enough. The
+    // items are counted first, so that the list is allocated at its full length, or not at all.
+    let renames = node.ordered_renames(config);
+    let list_possible = config.granularity != Granularity::Item &&
+                        !(config.split_top_level_crates && node_path.is_empty());
+    let mut list_len = 0;
+    if list_possible {
+        list_len = need_self_declaration as usize +
+                   if renames_already_consumed { 0 } else { renames.len() } +
+                   tree.stored_children(node)
+            .map(|(_, child)| (child.has_self && !absorb) as usize + child.renames.len())
+            .sum::<usize>();
+    }
+    // First construct a list of the imports that can be expressed for this node
+    let mut use_list: Vec<Item> = vec![];
+    // The input statements contributing to the list.
+    let mut statements: BTreeSet<usize> = BTreeSet::new();
+    if list_possible && list_len >= config.min_list_length {
+        use_list.reserve_exact(list_len);
+        if need_self_declaration {
+            use_list.push(Item("self".to_string(), None));
+        }
+        if !renames_already_consumed {
+            use_list.extend(renames.iter().map(|r| Item("self".to_string(), Some((*r).clone()))));
+        }
+        if need_self_declaration || (!renames_already_consumed && !renames.is_empty()) {
+            statements.extend(node.statements.iter());
+        }
+        for (child_name, child_node) in tree.ordered_children(node, config) {
+            let list_len = use_list.len();
+            if child_node.has_self && !absorb {
+                use_list.push(Item(child_name.clone(), None));
+            }
+            use_list.extend(child_node.ordered_renames(config)
+                .into_iter()
+                .map(|r| Item(child_name.clone(), Some(r.clone()))));
+            if use_list.len() > list_len {
+                statements.extend(child_node.statements.iter());
+            }
+        }
+        match config.self_position {
+            SelfPosition::First => {}
+            // A stable sort, so the selves keep their order after the other items.
+            SelfPosition::Last => use_list.sort_by_key(|i| i.0 == "self"),
+            SelfPosition::Sorted => {
+                use_list.sort_by(|a, b| config.ordering.compare(&a.0, &b.0));
+            }
+        }
+    }
+    // Now - are we going to use the list? Yes, if it has sufficient elements...
+    let will_use_list = list_possible && use_list.len() >= config.min_list_length &&
+                        statements.len() >= config.min_list_statements;
+    if !will_use_list && list_len > 1 {
+        trace!(path = %node_path.join("::"),
+               items = list_len,
+               statements = statements.len(),
+               "not combining into a list");
+    }
+    if will_use_list {
+        // As we're using the list, add in any 'self' declaration
+        imports.push(ViewPath::ViewPathList(owned_path(node_path), use_list));
+        consumed_child_selves = true;
+        consumed_child_renames = true;
+    } else {
+        if need_self_declaration {
+            imports.push(ViewPath::ViewPathSimple(owned_path(node_path), None));
+        }
+        if !renames_already_consumed {
+            imports.extend(renames.iter()
+                .map(|r| ViewPath::ViewPathSimple(owned_path(node_path), Some((*r).clone()))));
+        }
+    }
+    if emit_glob {
+        imports.push(ViewPath::ViewPathGlob(owned_path(node_path)));
+        consumed_child_selves |= absorb;
+    }
+    (consumed_child_selves, consumed_child_renames)
+}
+// The statements for the top-level `name` and everything under it: one nested statement, if
+// `nested`, or otherwise those `get_imports_under_node` gives.
+fn get_top_level_imports(tree: &ImportTree,
+                         config: &Config,
+                         nested: bool,
+                         name: &String,
+                         node: &ImportNode,
+                         consumed: (bool, bool))
+                         -> Vec<ViewPath> {
+    let mut imports = vec![];
+    if nested {
+        let mut nested = node.get_nested_imports(tree, config, false, &mut vec![name.as_str()]);
+        let path = Path::from(name.clone());
+        if nested.len() == 1 {
+            imports.push(nested.remove(0).into_prefixed(&path));
+        } else if !nested.is_empty() {
+            imports.push(ViewPath::group(path, nested));
+        }
+    } else {
+        get_imports_under_node(tree, config, node, name, consumed, &mut imports);
+    }
+    imports
+}
+
+// The imports for the top-level `name` and everything under it, given what the root consumed.
+// The tree is walked with an explicit stack rather than recursively, so that very deep paths
+// can't overflow the stack. Each entry is the depth of the node's parent path, the node's name
+// and the node, with what its parent consumed.
+fn get_imports_under_node(tree: &ImportTree,
+                          config: &Config,
+                          node: &ImportNode,
+                          name: &String,
+                          consumed: (bool, bool),
+                          imports: &mut Vec<ViewPath>) {
+    // The segments of the node's path, borrowed from the tree.
+    let mut node_path: Vec<&str> = vec![];
+    let mut stack = vec![(0, name, node, consumed.0, consumed.1)];
+    while let Some((depth, name, node, self_consumed, renames_consumed)) = stack.pop() {
+        node_path.truncate(depth);
+        node_path.push(name);
+        let (child_selves, child_renames) = get_imports_for_node(tree,
+                                                                 config,
+                                                                 node,
+                                                                 self_consumed,
+                                                                 renames_consumed,
+                                                                 &node_path,
+                                                                 imports);
+        // Pushed in reverse, so that the children are popped in order.
+        let children = tree.ordered_children(node, config);
+        for (child_name, child) in children.into_iter().rev() {
+            stack.push((node_path.len(), child_name, child, child_selves, child_renames));
+        }
+    }
+}
+
+// The combined imports of a combiner (with its own config), produced a top-level name at a time.
+struct ImportIter<'a> {
+    combiner: &'a ImportCombiner,
+    nested: bool,
+    // What the root consumed of the top-level names' imports.
+    consumed: (bool, bool),
+    // The top-level names whose imports are still to be produced.
+    names: ::std::vec::IntoIter<(&'a String, &'a ImportNode)>,
+    // The imports of the current top-level name that are still to be produced.
+    pending: ::std::vec::IntoIter<ViewPath>,
+    // The preserved imports still to be put back, and the number of imports produced so far
+    // (which is where they go back in).
+    preserved: ::std::iter::Peekable<::std::slice::Iter<'a, (usize, ViewPath)>>,
+    produced: usize,
+}
+
+impl<'a> ImportIter<'a> {
+    // Imports from the tree, with the paths they're written with.
+    fn finish(&self, mut imports: Vec<ViewPath>) -> Vec<ViewPath> {
+        for vp in &mut imports {
+            vp.expand_tree_root();
+            if !self.combiner.config.edition.supports_crate_prefix() {
+                vp.to_edition_2015_root();
+            }
+        }
+        imports
+    }
+}
+
+impl<'a> Iterator for ImportIter<'a> {
+    type Item = ViewPath;
+    fn next(&mut self) -> Option<ViewPath> {
+        loop {
+            let produced = self.produced;
+            let preserved = match self.preserved.peek() {
+                Some(&&(index, _)) if index <= produced => self.preserved.next(),
+                _ => None,
+            };
+            if let Some(&(_, ref vp)) = preserved {
+                self.produced += 1;
+                return Some(vp.clone());
+            }
+            if let Some(vp) = self.pending.next() {
+                self.produced += 1;
+                return Some(vp);
+            }
+            match self.names.next() {
+                Some((name, node)) => {
+                    let combiner = self.combiner;
+                    let imports = combiner.top_level_imports(&combiner.config,
+                                                             true,
+                                                             self.nested,
+                                                             name,
+                                                             node,
+                                                             self.consumed);
+                    self.pending = self.finish(imports).into_iter();
+                }
+                // The rest of the preserved imports go at the end.
+                None => {
+                    self.produced += 1;
+                    return self.preserved.next().map(|&(_, ref vp)| vp.clone());
+                }
+            }
+        }
+    }
+}
+
+// Define a representation of imports that is intended to simpliy the process of compressing and
+// optimising the import list.
+//
+// With the `serde` feature, a combiner can be serialized (and deserialized) with the imports
+// it's been given, to be combined later or elsewhere. Its caches are left out, and rebuilt as
+// they're needed.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImportCombiner {
+    tree: ImportTree,
+    config: Config,
+    // Imports that bypass combining, along with the number of imports added before each.
+    preserved: Vec<(usize, ViewPath)>,
+    import_count: usize,
+    // The path of the module the imports are in, relative to the crate root, if it's known.
+    module: Option<Path>,
+    // The number of modules and items in the tree.
+    node_count: usize,
+    // Errors for the imports rejected for exceeding the configured limits, and for the conflicts
+    // a resolver aborted.
+    rejected: Vec<Diagnostic>,
+    // The number of times each simple or glob import has been added, keyed by its rendering.
+    // Like the tree, it's shared with the combiner's clones until one of them changes it.
+    occurrences: Arc<BTreeMap<String, (ViewPath, usize)>>,
+    // The origins imports starting with each name were tagged with, in the order they were
+    // added, along with the imports from each. Only the first origin's imports are combined.
+    origins: Arc<BTreeMap<String, Vec<(String, Vec<ViewPath>)>>>,
+    // The combined imports under each top-level name (with the combiner's own config), along
+    // with whether the root consumed their selves and renames. A name's imports are dropped
+    // when anything under it changes, so that only those are combined again.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    statement_cache: RefCell<BTreeMap<String, ((bool, bool), Arc<Vec<ViewPath>>)>>,
+    // The whole import list (with the combiner's own config), from `cached_import_list`. It's
+    // dropped whenever an import is added.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    import_list: OnceLock<Vec<ViewPath>>,
+    // Where the last node was added, while adding a batch of imports with
+    // `add_imports_sorted`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cursor: Option<Cursor>,
+    // The keys of the occurrences of each import combined into the tree by `add_imports` or
+    // `add_imports_sorted`, so that repeats of it are only counted. It's cleared whenever nodes
+    // are taken out of the tree.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    seen: HashMap<ViewPath, Vec<String>>,
+    // The combined imports under the top-level names spilled out of the tree (with the paths
+    // they're written with), in the order they were spilled.
+    spilled: Vec<(String, Vec<ViewPath>)>,
+}
+
+impl ImportCombiner {
+    pub fn new() -> ImportCombiner {
+        ImportCombiner::with_config(Config::default())
+    }
+
+    pub fn with_config(mut config: Config) -> ImportCombiner {
+        config.crate_name = config.crate_name.as_ref().map(|name| config.crate_name_in_code(name));
+        // Crates known from cargo metadata are external too, except for the crate itself.
+        let known = config.crate_kinds
+            .keys()
+            .filter(|&name| Some(name) != config.crate_name.as_ref())
+            .cloned();
+        config.extern_crates = config.extern_crates
+            .iter()
+            .map(|name| config.crate_name_in_code(name))
+            .chain(known)
+            .collect();
+        ImportCombiner {
+            tree: ImportTree::new(),
+            config: config,
+            preserved: vec![],
+            import_count: 0,
+            module: None,
+            node_count: 0,
+            rejected: vec![],
+            occurrences: Arc::new(BTreeMap::new()),
+            origins: Arc::new(BTreeMap::new()),
+            statement_cache: RefCell::new(BTreeMap::new()),
+            import_list: OnceLock::new(),
+            cursor: None,
+            seen: HashMap::new(),
+            spilled: vec![],
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The length of the combined imports under `path` written on one line as a nested group,
+    /// such as `{self, c::{d, e}}` for `a::b` in `use a::b::{self, c::{d, e}};` (or just the
+    /// item, if there's only one), or `None` if nothing under it is imported. Lengths are cached
+    /// on the tree until it changes, so that fitting statements to a width doesn't keep
+    /// rendering them.
+    pub fn group_len(&self, path: &[String]) -> Option<usize> {
+        let path = tree_path(Path::from(path));
+        let (node, rest) = self.tree.find_path(&path)?;
+        let mut node_path = expand_tree_path(&path);
+        node_path.extend(path_segments(rest).filter(|s| !s.is_empty()).map(String::from));
+        let group = self.tree.group_len(node, &self.config, &mut node_path);
+        if rest.is_empty() {
+            let group = group.with_self(node.has_self);
+            if group.items > 0 {
+                return Some(group.written_len());
+            }
+        } else if group.items > 0 {
+            // Partway along a chain, the only item is the rest of the chain.
+            return Some(rest.len() + "::".len() + group.written_len());
+        }
+        None
+    }
+
+    /// How many nodes, segments and renames the combiner is storing, and roughly how much memory
+    /// it's using.
+    pub fn metrics(&self) -> CombinerMetrics {
+        let mut metrics = self.tree.metrics();
+        // The segments of the imports kept out of the tree are counted, but not those of the
+        // copies of imports kept for diagnostics and caching.
+        metrics.heap_bytes += self.preserved.capacity() * mem::size_of::<(usize, ViewPath)>();
+        for &(_, ref vp) in &self.preserved {
+            let (segments, bytes) = vp.sizes();
+            metrics.segments += segments;
+            metrics.heap_bytes += bytes;
+        }
+        metrics.heap_bytes += self.spilled.capacity() * mem::size_of::<(String, Vec<ViewPath>)>();
+        for &(ref name, ref vps) in &self.spilled {
+            metrics.heap_bytes += name.capacity() + vps.capacity() * mem::size_of::<ViewPath>();
+            for vp in vps {
+                let (segments, bytes) = vp.sizes();
+                metrics.segments += segments;
+                metrics.heap_bytes += bytes;
+            }
+        }
+        let mut copies: Vec<&ViewPath> = vec![];
+        for diagnostic in &self.rejected {
+            metrics.heap_bytes += diagnostic.message.capacity() +
+                                  diagnostic.paths.capacity() * mem::size_of::<ViewPath>();
+            copies.extend(&diagnostic.paths);
+        }
+        for (key, &(ref vp, _)) in self.occurrences.iter() {
+            metrics.heap_bytes += key.capacity() + mem::size_of::<(String, (ViewPath, usize))>();
+            copies.push(vp);
+        }
+        for (root, origins) in self.origins.iter() {
+            metrics.heap_bytes += root.capacity() +
+                                  origins.capacity() * mem::size_of::<(String, Vec<ViewPath>)>();
+            for &(ref origin, ref vps) in origins {
+                metrics.heap_bytes += origin.capacity() +
+                                      vps.capacity() * mem::size_of::<ViewPath>();
+                copies.extend(vps);
+            }
+        }
+        metrics.heap_bytes += copies.iter().map(|vp| vp.sizes().1).sum::<usize>();
+        for (name, &(_, ref vps)) in self.statement_cache.borrow().iter() {
+            metrics.heap_bytes += name.capacity() + vps.capacity() * mem::size_of::<ViewPath>() +
+                                  vps.iter().map(|vp| vp.sizes().1).sum::<usize>();
+        }
+        if let Some(list) = self.import_list.get() {
+            metrics.heap_bytes += list.capacity() * mem::size_of::<ViewPath>() +
+                                  list.iter().map(|vp| vp.sizes().1).sum::<usize>();
+        }
+        metrics
+    }
+
+    /// Release the memory the combiner no longer needs: the nodes that import nothing, the slots
+    /// in its tree that nodes were combined out of, and the excess capacity of its collections.
+    /// Chains of modules that only lead to other modules are compressed into one node each.
+    /// Worth doing once a large batch of imports has been added to a combiner that's kept.
+    pub fn compact(&mut self) {
+        self.node_count -= self.tree.compact();
+        self.tree.compress_chains();
+        self.preserved.shrink_to_fit();
+        self.rejected.shrink_to_fit();
+        self.seen = HashMap::new();
+        for origins in Arc::make_mut(&mut self.origins).values_mut() {
+            origins.shrink_to_fit();
+            for &mut (_, ref mut vps) in origins {
+                vps.shrink_to_fit();
+            }
+        }
+        for &mut (_, ref mut vps) in self.statement_cache.get_mut().values_mut() {
+            if let Some(vps) = Arc::get_mut(vps) {
+                vps.shrink_to_fit();
+            }
+        }
+    }
+
+    /// Set the path of the module the imports are in (relative to the crate root), so that
+    /// `strip_self_prefix` can rewrite `self::` paths as absolute ones.
+    pub fn set_module(&mut self, module: &[String]) {
+        self.module = Some(Path::from(module));
+        // `self::` paths seen so far may be rewritten differently now.
+        self.seen.clear();
+    }
+
+    /// Add imports, skipping the tree work for any that are the same as one already added by
+    /// this or an earlier `add_imports` or `add_imports_sorted` (as merge artifacts and generated
+    /// code often repeat `use` lines): they're only counted, for `diagnostics`. With a
+    /// `min_list_statements` above 1, every statement has to be recorded in the tree, so they're
+    /// all added in full.
+    pub fn add_imports(&mut self, vps: &[&ViewPath]) {
+        for &vp in vps {
+            self.add_seen_import(vp);
+        }
+        self.compress_deep_chains(vps);
+    }
+
+    // Add an import, only counting it if it's the same as one already combined into the tree.
+    fn add_seen_import(&mut self, vp: &ViewPath) {
+        let dedup = self.config.min_list_statements <= 1;
+        if let (true, Some(keys)) = (dedup, self.seen.get(vp).cloned()) {
+            self.add_repeated_import(&keys);
+            return;
+        }
+        let strictness = self.config.strictness;
+        let spilled = self.spilled.len();
+        if let Some(keys) = self.add_tagged_import(vp.clone(), None, &strictness) {
+            // Spilling the import's own nodes would leave its keys dangling.
+            if dedup && self.spilled.len() == spilled {
+                self.seen.insert(vp.clone(), keys);
+            }
+        }
+    }
+
+    // Compress the chains of modules in the tree if any of `vps` are as deep as generated
+    // bindings' imports tend to be.
+    fn compress_deep_chains(&mut self, vps: &[&ViewPath]) {
+        if vps.iter().any(|vp| vp.path().len() >= CHAIN_COMPRESSION_DEPTH) {
+            self.tree.compress_chains();
+        }
+    }
+
+    // Count another occurrence of an import already combined into the tree, given the keys of
+    // its occurrences.
+    fn add_repeated_import(&mut self, keys: &[String]) {
+        self.import_list.take();
+        self.import_count += 1;
+        let occurrences = Arc::make_mut(&mut self.occurrences);
+        for key in keys {
+            if let Some(occurrence) = occurrences.get_mut(key) {
+                occurrence.1 += 1;
+            }
+        }
+    }
+
+    /// Add imports that are (mostly) already in order, as they are when they're extracted from
+    /// a file that's already been combined. Each import's nodes are found from where the
+    /// previous import's were rather than from the root, so the siblings in a run of imports
+    /// are added without descending the tree for each one. The imports are combined the same as
+    /// with `add_imports`, whatever their order.
+    pub fn add_imports_sorted(&mut self, vps: &[&ViewPath]) {
+        self.cursor = Some(Cursor::default());
+        for vp in vps {
+            self.add_seen_import(vp);
+        }
+        self.cursor = None;
+        self.compress_deep_chains(vps);
+    }
+
+    pub fn add_import(&mut self, vp: &ViewPath) {
+        self.add_import_owned(vp.clone());
+    }
+
+    /// Add an import, moving its paths into the combiner rather than copying them.
+    pub fn add_import_owned(&mut self, vp: ViewPath) {
+        let strictness = self.config.strictness;
+        self.add_tagged_import(vp, None, &strictness);
+    }
+
+    /// Add an import borrowing from the source it was parsed from, copying its segments only
+    /// now that it's going into the combiner.
+    pub fn add_import_ref(&mut self, vp: &ViewPathRef) {
+        self.add_import_owned(vp.to_view_path());
+    }
+
+    /// Add an import, with `resolver` deciding what happens if combining it with the imports
+    /// already added would be lossy or ambiguous (rather than the config's `strictness`).
+    pub fn add_import_with_resolver<R: ConflictResolver>(&mut self, vp: &ViewPath, resolver: &R) {
+        self.add_tagged_import(vp.clone(), None, resolver);
+    }
+
+    /// Add an import tagged with where its first segment comes from, e.g. `"crate"` or
+    /// `"module"` for a project with both an extern crate and a module called `log`. Imports
+    /// starting with the same name but tagged with different origins are never combined with
+    /// each other; those from the first origin seen are combined as usual, and the rest are kept
+    /// as they are, with a diagnostic for the ambiguity. Untagged imports are combined with any.
+    pub fn add_import_from(&mut self, vp: &ViewPath, origin: &str) {
+        let strictness = self.config.strictness;
+        self.add_tagged_import(vp.clone(), Some(origin), &strictness);
+    }
+
+    // Add an import, returning the keys of its occurrences if it's combined into the tree
+    // (rather than preserved, rejected or kept apart for its origin).
+    fn add_tagged_import<R: ConflictResolver>(&mut self,
+                                              vp: ViewPath,
+                                              origin: Option<&str>,
+                                              resolver: &R)
+                                              -> Option<Vec<String>> {
+        self.import_list.take();
+        let index = self.import_count;
+        self.import_count += 1;
+        // Package names (which can't be path segments if they have dashes) become crate names.
+        let mut vp = vp;
+        vp.map_root(&|root| self.package_crate_name(root));
+        if self.config.migrate_from_2015 {
+            vp = vp.migrated_from_2015(&self.config.extern_crates);
+        }
+        if let Some(ref name) = self.config.crate_name {
+            if self.config.prefer_crate_name {
+                vp.rename_root("crate", name);
+            } else {
+                vp.rename_root(name, "crate");
+            }
+        }
+        if self.config.keyword_policy == KeywordPolicy::Raw {
+            let edition = self.config.edition;
+            vp = vp.map_names(&|name| raw_identifier(name, edition));
+        }
+        if self.config.preserve_paths.iter().any(|p| p.matches(&vp)) {
+            trace!("preserving `{}`, as it matches preserve_paths", vp);
+            self.preserved.push((index, vp));
+            return None;
+        }
+        if self.config.strip_self_prefix {
+            vp = self.without_self_prefix(&vp);
+        }
+        let simple_paths = vp.simple_paths();
+        if !self.within_limits(&vp, &simple_paths) {
+            return None;
+        }
+        if let Some(origin) = origin {
+            if !self.record_origin(origin, &simple_paths) {
+                trace!(origin, "keeping `{}` apart, as its root has another origin", vp);
+                self.preserved.push((index, vp));
+                return None;
+            }
+        }
+        let occurrences = Arc::make_mut(&mut self.occurrences);
+        let mut keys = Vec::with_capacity(simple_paths.len());
+        for simple in simple_paths {
+            let key = simple.to_string();
+            occurrences.entry(key.clone()).or_insert((simple, 0)).1 += 1;
+            keys.push(key);
+        }
+        self.add_statement(vp, index, resolver);
+        self.spill_if_needed();
+        Some(keys)
+    }
+
+    // If the tree has more nodes than the configured `spill_threshold`, spill the top-level
+    // names the fewest statements have gone into (and the biggest of those first) until it has
+    // no more than half as many.
+    fn spill_if_needed(&mut self) {
+        let threshold = match self.config.spill_threshold {
+            Some(threshold) if self.node_count > threshold => threshold,
+            _ => return,
+        };
+        let mut names: Vec<_> = self.tree
+            .stored_children(self.tree.root())
+            .map(|(name, node)| (self.tree.count_under(node), name.clone()))
+            .collect();
+        names.sort_by(|&((a_nodes, a_statements), ref a), &((b_nodes, b_statements), ref b)| {
+            a_statements.cmp(&b_statements).then(b_nodes.cmp(&a_nodes)).then(a.cmp(b))
+        });
+        for ((nodes, _), name) in names {
+            if self.node_count <= threshold / 2 {
+                break;
+            }
+            self.spill(name);
+            self.node_count -= nodes;
+        }
+        // The cursor's nodes may have been spilled.
+        if let Some(ref mut cursor) = self.cursor {
+            *cursor = Cursor::default();
+        }
+    }
+
+    // Combine the imports under the top-level `name` by themselves, and take them out of the
+    // tree. The occurrences of imports under it are forgotten too, so that they aren't kept
+    // either.
+    fn spill(&mut self, name: String) {
+        let mut imports = match self.tree.child(self.tree.root(), &name) {
+            Some(node) => {
+                let nested = self.config.edition.supports_nested_groups() &&
+                             (self.config.granularity == Granularity::Crate ||
+                              self.config.granularity == Granularity::One);
+                get_top_level_imports(&self.tree, &self.config, nested, &name, node, (false, false))
+            }
+            None => return,
+        };
+        debug!(name = %name, imports = imports.len(), "spilling imports");
+        for vp in &mut imports {
+            vp.expand_tree_root();
+        }
+        self.tree.remove_child(ROOT, &name);
+        self.statement_cache.get_mut().remove(&name);
+        self.seen.clear();
+        Arc::make_mut(&mut self.occurrences)
+            .retain(|_, &mut (ref vp, _)| tree_path(vp.path().clone()).first() != Some(&name));
+        self.spilled.push((name, imports));
+    }
+
+    /// Take the imports spilled out of the tree so far (see `Config::spill_threshold`), which are
+    /// then no longer part of the combined imports. Writing these out as a corpus is streamed
+    /// through the combiner keeps the memory it uses bounded.
+    pub fn take_spilled(&mut self) -> Vec<ViewPath> {
+        self.import_list.take();
+        let mut imports: Vec<ViewPath> = mem::replace(&mut self.spilled, vec![])
+            .into_iter()
+            .flat_map(|(_, imports)| imports)
+            .collect();
+        if !self.config.edition.supports_crate_prefix() {
+            for vp in &mut imports {
+                vp.to_edition_2015_root();
+            }
+        }
+        imports
+    }
+
+    // Whether an import is within the configured limits, recording an error if it isn't.
+    fn within_limits(&mut self, vp: &ViewPath, simple_paths: &[ViewPath]) -> bool {
+        let depth = simple_paths.iter().map(|p| p.path().len()).max().unwrap_or(0);
+        if depth > self.config.max_path_depth {
+            debug!(depth, "rejecting an import with too deep a path");
+            self.rejected.push(Diagnostic {
+                kind: DiagnosticKind::PathTooDeep(depth),
+                severity: Severity::Error,
+                message: format!("rejected an import with {} segments in its path, more than the \
+                                  limit of {}",
+                                 depth,
+                                 self.config.max_path_depth),
+                paths: vec![vp.clone()],
+            });
+            return false;
+        }
+        let new_nodes = self.new_node_count(simple_paths);
+        if self.node_count + new_nodes <= self.config.max_nodes {
+            return true;
+        }
+        // Every import rejected for there being too many nodes is listed in the same error.
+        let max_nodes = self.config.max_nodes;
+        if let Some(diagnostic) = self.rejected
+            .iter_mut()
+            .find(|d| d.kind == DiagnosticKind::TooManyNodes) {
+            diagnostic.paths.push(vp.clone());
+            diagnostic.message = format!("rejected {} imports, as there were more than {} modules \
+                                          and items",
+                                         diagnostic.paths.len(),
+                                         max_nodes);
+            return false;
+        }
+        debug!(nodes = self.node_count, "rejecting imports as there are too many nodes");
+        self.rejected.push(Diagnostic {
+            kind: DiagnosticKind::TooManyNodes,
+            severity: Severity::Error,
+            message: format!("rejected an import, as there were more than {} modules and items",
+                             self.config.max_nodes),
+            paths: vec![vp.clone()],
+        });
+        false
+    }
+
+    // The number of modules and items adding an import's simple paths would add to the tree.
+    fn new_node_count(&self, simple_paths: &[ViewPath]) -> usize {
+        let mut new_paths = BTreeSet::new();
+        for simple in simple_paths {
+            let path = tree_path(simple.path().clone());
+            for len in 1..path.len() + 1 {
+                if self.tree.find_path(&path[..len]).is_none() {
+                    new_paths.insert(path[..len].to_vec());
+                }
+            }
+        }
+        new_paths.len()
+    }
+
+    // The crate name to use in place of `root`, if it's a package name.
+    fn package_crate_name(&self, root: &str) -> Option<String> {
+        if root.contains('-') || self.config.crate_names.contains_key(root) {
+            Some(self.config.crate_name_in_code(root))
+        } else {
+            None
+        }
+    }
+
+    // Record the origin of an import's simple paths, returning whether it can be combined with
+    // those already added (as each name it starts with has no other origin yet).
+    fn record_origin(&mut self, origin: &str, simple_paths: &[ViewPath]) -> bool {
+        let mut combinable = true;
+        let all_origins = Arc::make_mut(&mut self.origins);
+        for simple in simple_paths {
+            let root = match simple.path().first() {
+                Some(root) => root.clone(),
+                None => continue,
+            };
+            let origins = all_origins.entry(root).or_insert_with(Vec::new);
+            combinable &= origins.first().map_or(true, |o| o.0 == origin);
+            match origins.iter().position(|o| o.0 == origin) {
+                Some(i) => origins[i].1.push(simple.clone()),
+                None => origins.push((origin.to_string(), vec![simple.clone()])),
+            }
+        }
+        combinable
+    }
+
+    // `vp` with its leading `self::` replaced by the path of the module it's in (a `crate::`
+    // path, except in 2015 where use paths are already crate-relative), or if that isn't known,
+    // removed.
+    fn without_self_prefix(&self, vp: &ViewPath) -> ViewPath {
+        if let &ViewPath::ViewPathNested(ref p, ref nested) = vp {
+            if p.is_empty() {
+                let nested = nested.iter().map(|n| self.without_self_prefix(n)).collect();
+                return ViewPath::ViewPathNested(Path::new(), nested);
+            }
+        }
+        if vp.path().first().map_or(true, |s| s != "self") {
+            return vp.clone();
+        }
+        let mut path = Path::new();
+        if let Some(ref module) = self.module {
+            if self.config.edition.supports_crate_prefix() {
+                path.push("crate".to_string());
+            }
+            path.extend(module.iter().cloned());
+        }
+        path.extend(vp.path()[1..].iter().cloned());
+        // `use self::*;` at the crate root has nothing to be written as instead.
+        let named = match vp {
+            &ViewPath::ViewPathSimple(..) |
+            &ViewPath::ViewPathGlob(_) => !path.is_empty(),
+            _ => true,
+        };
+        if !named {
+            return vp.clone();
+        }
+        let mut vp = vp.clone();
+        *vp.path_mut() = path;
+        vp
+    }
+    fn add_statement<R: ConflictResolver>(&mut self, vp: ViewPath, index: usize, resolver: &R) {
+        use ViewPath::*;
+        match vp {
+            // Globs and simple declarations are easy enough.
+            ViewPathGlob(p) => self.add_node(p, ImportNode::just_glob(), index, resolver),
+            ViewPathSimple(p, rename) => {
+                self.add_node(p, ImportNode::self_or_rename(rename), index, resolver)
+            }
+            ViewPathList(p, items) => {
+                for Item(name, rename) in items {
+                    let mut path = p.clone();
+                    let node = if name == "*" {
+                        ImportNode::just_glob()
+                    } else {
+                        ImportNode::self_or_rename(rename)
+                    };
+                    if name != "*" && name != "self" {
+                        path.push(name);
+                    }
+                    self.add_node(path, node, index, resolver);
+                }
+            }
+            ViewPathNested(p, nested) => {
+                for vp in nested {
+                    self.add_statement(vp.into_prefixed(&p), index, resolver);
+                }
+            }
+        }
+    }
+    fn add_node<R: ConflictResolver>(&mut self,
+                                     path: Path,
+                                     mut node: ImportNode,
+                                     statement: usize,
+                                     resolver: &R) {
+        let mut path = tree_path(path);
+        if let Some(name) = path.first() {
+            self.statement_cache.get_mut().remove(name);
+        }
+        node.statements.insert(statement);
+        if let (true, Some(name)) = (self.config.strip_redundant_renames, path.last()) {
+            if node.renames.remove(name) {
+                node.has_self = true;
+            }
+        }
+        // The node is combined from its parent, so that it can be checked against a glob there.
+        // Only the segments of new nodes are copied into the tree.
+        let (parent, node) = match path.pop() {
+            Some(name) => {
+                let (parent, added) = match self.cursor {
+                    Some(ref mut cursor) => self.tree.get_or_add_path_from(&path, cursor),
+                    None => self.tree.get_or_add_path(&path),
+                };
+                self.node_count += added;
+                if !self.tree.node(parent).children.contains_key(&name) {
+                    self.node_count += 1;
+                }
+                let mut wrapper = ImportNode::new();
+                wrapper.children.insert(name, self.tree.add(node));
+                (parent, wrapper)
+            }
+            None => (ROOT, node),
+        };
+        let aborted = self.tree.combine_with_owned(parent, node, resolver, &mut path);
+        for conflict in aborted {
+            let mut paths = vec![ViewPath::ViewPathGlob(conflict.module.clone())];
+            paths.extend(conflict.names.iter().map(|name| {
+                let mut path = conflict.module.clone();
+                path.push(name.clone());
+                ViewPath::ViewPathSimple(path, None)
+            }));
+            self.rejected.push(Diagnostic {
+                message: format!("combining `{}::*` with explicit imports of {} was aborted",
+                                 conflict.module.join("::"),
+                                 conflict.names.join(", ")),
+                kind: DiagnosticKind::AbortedConflict(conflict.module),
+                severity: Severity::Error,
+                paths: paths,
+            });
+        }
+    }
+    pub fn get_import_list(&self) -> Vec<ViewPath> {
+        self.get_import_list_with(&self.config)
+    }
+
+    /// The same as `get_import_list`, but kept until another import is added, so that getting
+    /// the list again (say, to apply the changes a preview showed) doesn't combine the imports
+    /// again.
+    pub fn cached_import_list(&self) -> &[ViewPath] {
+        self.import_list.get_or_init(|| self.get_import_list())
+    }
+
+    /// The combined imports, or if the imports have problems that are errors (such as imports
+    /// rejected for exceeding the configured limits), the diagnostics for them.
+    pub fn try_get_import_list(&self) -> Result<Vec<ViewPath>, Vec<Diagnostic>> {
+        let diagnostics = self.diagnostics();
+        if diagnostics.iter().any(|d| d.is_error()) {
+            return Err(diagnostics);
+        }
+        Ok(self.get_import_list())
+    }
+
+    /// Notes, warnings and errors about the imports added so far. Rejected imports and reserved
+    /// keywords used as names are always errors, and with `Strictness::Strict` or `Lossless`,
+    /// so are names bound more than once.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let severity = match self.config.strictness {
+            Strictness::Lenient => Severity::Warning,
+            Strictness::Strict | Strictness::Lossless => Severity::Error,
+        };
+        let mut diagnostics = self.rejected.clone();
+        diagnostics.extend(self.reserved_keywords());
+        if self.config.lint_prelude_aliases {
+            diagnostics.extend(self.prelude_aliases());
+        }
+        diagnostics.extend(self.name_conflicts(severity));
+        diagnostics.extend(self.alias_cycles());
+        diagnostics.extend(self.ambiguous_origins(severity));
+        // Explicit imports are only merged into globs when they're absorbed.
+        if self.config.absorbs_under_globs() {
+            diagnostics.extend(self.glob_overlaps());
+        }
+        diagnostics.extend(self.duplicates_removed());
+        diagnostics
+    }
+
+    // Errors for the imports using reserved keywords as names.
+    fn reserved_keywords(&self) -> Vec<Diagnostic> {
+        let preserved = self.preserved.iter().flat_map(|&(_, ref vp)| vp.simple_paths());
+        let imports = self.occurrences.values().map(|&(ref vp, _)| vp.clone()).chain(preserved);
+        let mut diagnostics = vec![];
+        for vp in imports {
+            let rename = match vp {
+                ViewPath::ViewPathSimple(_, ref rename) => rename.clone(),
+                _ => None,
+            };
+            let keyword = vp.path()
+                .iter()
+                .chain(rename.iter())
+                .find(|name| is_reserved_keyword(name, self.config.edition))
+                .cloned();
+            if let Some(keyword) = keyword {
+                diagnostics.push(Diagnostic {
+                    message: format!("`{}` can't be imported, as `{}` is a reserved keyword",
+                                     vp,
+                                     keyword),
+                    kind: DiagnosticKind::ReservedKeyword(keyword),
+                    severity: Severity::Error,
+                    paths: vec![vp],
+                });
+            }
+        }
+        diagnostics
+    }
+
+    // Warnings for the sets of imports that rename names in a cycle, such as `a as b` with
+    // `b as a`.
+    fn alias_cycles(&self) -> Vec<Diagnostic> {
+        // Each cycle is found from its smallest name, so only once.
+        fn find_cycles(start: &str,
+                       renames: &BTreeMap<String, Vec<(String, ViewPath)>>,
+                       cycle: &mut Vec<String>,
+                       cycles: &mut Vec<Vec<String>>) {
+            let last = cycle[cycle.len() - 1].clone();
+            for &(ref alias, _) in renames.get(&last).map_or(&[][..], |r| &r[..]) {
+                if alias == start {
+                    cycles.push(cycle.clone());
+                } else if alias.as_str() > start && !cycle.contains(alias) {
+                    cycle.push(alias.clone());
+                    find_cycles(start, renames, cycle, cycles);
+                    cycle.pop();
+                }
+            }
+        }
+        let preserved = self.preserved.iter().flat_map(|&(_, ref vp)| vp.simple_paths());
+        let imports = self.occurrences.values().map(|&(ref vp, _)| vp.clone()).chain(preserved);
+        // The renames of each name, with the imports making them.
+        let mut renames: BTreeMap<String, Vec<(String, ViewPath)>> = BTreeMap::new();
+        for vp in imports {
+            if let ViewPath::ViewPathSimple(ref p, Some(ref alias)) = vp {
+                match p.last() {
+                    Some(name) if name != alias && alias != "_" => {
+                        renames.entry(name.clone())
+                            .or_insert_with(Vec::new)
+                            .push((alias.clone(), vp.clone()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut cycles = vec![];
+        for name in renames.keys() {
+            find_cycles(name, &renames, &mut vec![name.clone()], &mut cycles);
+        }
+        cycles.into_iter()
+            .map(|cycle| {
+                let mut paths = vec![];
+                for (i, name) in cycle.iter().enumerate() {
+                    let alias = &cycle[(i + 1) % cycle.len()];
+                    paths.extend(renames[name]
+                        .iter()
+                        .filter(|r| r.0 == *alias)
+                        .map(|r| r.1.clone()));
+                }
+                let imports: Vec<_> = paths.iter().map(|vp| format!("`{}`", vp)).collect();
+                Diagnostic {
+                    message: format!("{} swap names around", imports.join(", ")),
+                    kind: DiagnosticKind::AliasCycle(cycle),
+                    severity: Severity::Warning,
+                    paths: paths,
+                }
+            })
+            .collect()
+    }
+
+    // Warnings for the imports renamed to (and so shadowing) names from the standard prelude.
+    fn prelude_aliases(&self) -> Vec<Diagnostic> {
+        let preserved = self.preserved.iter().flat_map(|&(_, ref vp)| vp.simple_paths());
+        self.occurrences
+            .values()
+            .map(|&(ref vp, _)| vp.clone())
+            .chain(preserved)
+            .filter_map(|vp| {
+                let alias = match vp {
+                    ViewPath::ViewPathSimple(ref p, Some(ref rename)) => {
+                        if p.last() == Some(rename) || !is_prelude_name(rename) {
+                            return None;
+                        }
+                        rename.clone()
+                    }
+                    _ => return None,
+                };
+                Some(Diagnostic {
+                    message: format!("`{}` shadows the prelude's `{}`", vp, alias),
+                    kind: DiagnosticKind::PreludeAlias(alias),
+                    severity: Severity::Warning,
+                    paths: vec![vp],
+                })
+            })
+            .collect()
+    }
+
+    // Warnings for globs imported alongside explicit imports of names in the same module.
+    fn glob_overlaps(&self) -> Vec<Diagnostic> {
+        fn find_overlaps(tree: &ImportTree,
+                         node: &ImportNode,
+                         path: &mut Path,
+                         diagnostics: &mut Vec<Diagnostic>) {
+            if node.has_glob && !node.keeps_explicit {
+                let module = expand_tree_path(path);
+                let explicit: Vec<ViewPath> = tree.children(node)
+                    .filter(|&(_, child)| child.has_self)
+                    .map(|(name, _)| {
+                        let mut child_path = module.clone();
+                        child_path.push(name.clone());
+                        ViewPath::ViewPathSimple(child_path, None)
+                    })
+                    .collect();
+                if !explicit.is_empty() {
+                    let glob = ViewPath::ViewPathGlob(module.clone());
+                    let imports: Vec<_> = explicit.iter().map(|vp| format!("`{}`", vp)).collect();
+                    let mut paths = vec![glob.clone()];
+                    paths.extend(explicit);
+                    diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::GlobOverlap(module),
+                        severity: Severity::Warning,
+                        message: format!("`{}` overlaps with the explicit imports {}",
+                                         glob,
+                                         imports.join(", ")),
+                        paths: paths,
+                    });
+                }
+            }
+            for (name, child) in tree.children(node) {
+                path.push(name.clone());
+                find_overlaps(tree, child, path, diagnostics);
+                path.pop();
+            }
+        }
+        let mut diagnostics = vec![];
+        find_overlaps(&self.tree, self.tree.root(), &mut Path::new(), &mut diagnostics);
+        diagnostics
+    }
+
+    // Notes of the imports that were added more than once, and so only appear once in the
+    // output.
+    fn duplicates_removed(&self) -> Vec<Diagnostic> {
+        self.occurrences
+            .values()
+            .filter(|&&(_, count)| count > 1)
+            .map(|&(ref vp, count)| {
+                let message = if count == 2 {
+                    format!("removed a duplicate of `{}`", vp)
+                } else {
+                    format!("removed {} duplicates of `{}`", count - 1, vp)
+                };
+                Diagnostic {
+                    kind: DiagnosticKind::DuplicatesRemoved(count - 1),
+                    severity: Severity::Note,
+                    message: message,
+                    paths: vec![vp.clone()],
+                }
+            })
+            .collect()
+    }
+
+    /// Warnings about the names that glob imports make ambiguous, and the explicit imports that
+    /// `get_import_list_with_symbols` keeps to avoid that, according to a symbol table.
+    pub fn symbol_diagnostics<S: SymbolTable>(&self, symbols: &S) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        for (path, other) in self.ambiguous_absorptions(&self.config, symbols) {
+            let explicit = ViewPath::ViewPathSimple(path.clone(), None);
+            let module_glob = ViewPath::ViewPathGlob(Path::from(&path[..path.len() - 1]));
+            let other_glob = ViewPath::ViewPathGlob(other);
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::KeptUnderGlob,
+                severity: Severity::Warning,
+                message: format!("kept `{}` alongside `{}`, as `{}` also imports `{}`",
+                                 explicit,
+                                 module_glob,
+                                 other_glob,
+                                 path[path.len() - 1]),
+                paths: vec![explicit, other_glob],
+            });
         }
+        let bindings = self.bindings();
+        let globs = self.glob_modules();
+        let mut ambiguous: BTreeMap<String, Vec<ViewPath>> = BTreeMap::new();
+        for (i, &(ref a, _)) in globs.iter().enumerate() {
+            let a_names = symbols.names(a);
+            for &(ref b, _) in &globs[i + 1..] {
+                for (name, namespace) in symbols.names(b) {
+                    let explicit = bindings.contains_key(&name);
+                    if explicit || !a_names.contains(&(name.clone(), namespace)) {
+                        continue;
+                    }
+                    let globs = ambiguous.entry(name).or_insert_with(Vec::new);
+                    for module in &[a, b] {
+                        let glob = ViewPath::ViewPathGlob((*module).clone());
+                        if !globs.contains(&glob) {
+                            globs.push(glob);
+                        }
+                    }
+                }
+            }
+        }
+        for (name, globs) in ambiguous {
+            let imports: Vec<_> = globs.iter().map(|vp| format!("`{}`", vp)).collect();
+            diagnostics.push(Diagnostic {
+                message: format!("`{}` is ambiguous, as it's imported by {}",
+                                 name,
+                                 imports.join(", ")),
+                kind: DiagnosticKind::AmbiguousGlobs(name),
+                severity: Severity::Warning,
+                paths: globs,
+            });
+        }
+        diagnostics
     }
-    fn self_or_rename(rename: &Option<String>) -> ImportNode {
-        ImportNode {
-            has_self: rename.is_none(),
-            has_glob: false,
-            renames: rename.iter().map(String::clone).collect(),
-            children: BTreeMap::new(),
+
+    // The paths of the modules imported by globs, with their nodes.
+    fn glob_modules(&self) -> Vec<(Path, &ImportNode)> {
+        fn find_globs<'a>(tree: &'a ImportTree,
+                          node: &'a ImportNode,
+                          path: &mut Path,
+                          globs: &mut Vec<(Path, &'a ImportNode)>) {
+            if node.has_glob {
+                globs.push((expand_tree_path(path), node));
+            }
+            for (name, child) in tree.children(node) {
+                path.push(name.clone());
+                find_globs(tree, child, path, globs);
+                path.pop();
+            }
         }
+        let mut globs = vec![];
+        find_globs(&self.tree, self.tree.root(), &mut Path::new(), &mut globs);
+        globs
     }
-    fn just_glob() -> ImportNode {
-        ImportNode {
-            has_self: false,
-            has_glob: true,
-            renames: vec![],
-            children: BTreeMap::new(),
+
+    // The explicit imports that emitted globs would absorb, but that have names the glob of
+    // another module also imports (in the same namespace), with that other module. Without
+    // them, the name would be ambiguous.
+    fn ambiguous_absorptions<S: SymbolTable>(&self,
+                                             config: &Config,
+                                             symbols: &S)
+                                             -> Vec<(Path, Path)> {
+        let globs = self.glob_modules();
+        let mut absorptions = vec![];
+        for &(ref module, node) in &globs {
+            if !config.emits_glob(module) {
+                continue;
+            }
+            for (name, _) in self.tree.children(node).filter(|&(_, c)| c.has_self) {
+                let namespaces = symbols.namespaces(module, name);
+                let other = globs.iter().map(|g| &g.0).find(|other| {
+                    *other != module &&
+                    symbols.namespaces(other, name).iter().any(|n| namespaces.contains(n))
+                });
+                if let Some(other) = other {
+                    let mut path = module.clone();
+                    path.push(name.clone());
+                    absorptions.push((path, other.clone()));
+                }
+            }
         }
+        absorptions
     }
-    fn combine_with(&mut self, b: &ImportNode) {
-        self.has_self |= b.has_self;
-        self.has_glob |= b.has_glob;
-        for r in &b.renames {
-            if !self.renames.contains(r) {
-                self.renames.push(r.clone());
+
+    // Names bound by imports of more than one path.
+    fn name_conflicts(&self, severity: Severity) -> Vec<Diagnostic> {
+        self.bindings()
+            .into_iter()
+            .filter(|&(_, ref vps)| {
+                vps.iter().map(|vp| vp.path()).collect::<BTreeSet<_>>().len() > 1
+            })
+            .map(|(name, vps)| {
+                let imports: Vec<_> = vps.iter().map(|vp| format!("`{}`", vp)).collect();
+                Diagnostic {
+                    message: format!("`{}` is imported more than once: {}",
+                                     name,
+                                     imports.join(", ")),
+                    kind: DiagnosticKind::NameConflict(name),
+                    severity: severity,
+                    paths: vps,
+                }
+            })
+            .collect()
+    }
+
+    // Diagnostics for the names imports start with that are tagged with more than one origin.
+    fn ambiguous_origins(&self, severity: Severity) -> Vec<Diagnostic> {
+        self.origins
+            .iter()
+            .filter(|&(_, origins)| origins.len() > 1)
+            .map(|(name, origins)| {
+                let tags: Vec<_> = origins.iter().map(|o| format!("`{}`", o.0)).collect();
+                Diagnostic {
+                    message: format!("imports of `{}` from {} were kept apart, as `{}` is \
+                                      ambiguous",
+                                     name,
+                                     tags.join(" and "),
+                                     name),
+                    kind: DiagnosticKind::AmbiguousOrigin(name.clone()),
+                    severity: severity,
+                    paths: origins.iter().flat_map(|o| o.1.iter().cloned()).collect(),
+                }
+            })
+            .collect()
+    }
+
+    // The names bound by explicit imports, with the imports (as simple paths) binding them.
+    fn bindings(&self) -> BTreeMap<String, Vec<ViewPath>> {
+        fn add_bindings(tree: &ImportTree,
+                        node: &ImportNode,
+                        path: &mut Path,
+                        bindings: &mut BTreeMap<String, Vec<ViewPath>>) {
+            if let (true, Some(name)) = (node.has_self, path.last()) {
+                let name = expand_tree_path(&[name.clone()]).pop().unwrap_or(name.clone());
+                bindings.entry(name)
+                    .or_insert_with(Vec::new)
+                    .push(ViewPath::ViewPathSimple(expand_tree_path(path), None));
+            }
+            // `as _` imports don't bind a name.
+            for rename in node.renames.iter().filter(|r| *r != "_") {
+                bindings.entry(rename.clone())
+                    .or_insert_with(Vec::new)
+                    .push(ViewPath::ViewPathSimple(expand_tree_path(path), Some(rename.clone())));
+            }
+            for (name, child) in tree.children(node) {
+                path.push(name.clone());
+                add_bindings(tree, child, path, bindings);
+                path.pop();
             }
         }
-        self.renames.sort();
-        for (k, v) in &b.children {
-            if self.children.contains_key(k) {
-                self.children.get_mut(k).map(|existing| existing.combine_with(v));
-            } else {
-                self.children.insert(k.clone(), v.clone());
+        let mut bindings = BTreeMap::new();
+        add_bindings(&self.tree, self.tree.root(), &mut Path::new(), &mut bindings);
+        for &(_, ref vp) in &self.preserved {
+            for simple in vp.simple_paths() {
+                let name = match simple {
+                    ViewPath::ViewPathSimple(_, Some(ref rename)) if rename != "_" => {
+                        rename.clone()
+                    }
+                    ViewPath::ViewPathSimple(ref p, None) if !p.is_empty() => {
+                        p[p.len() - 1].clone()
+                    }
+                    _ => continue,
+                };
+                bindings.entry(name).or_insert_with(Vec::new).push(simple);
             }
         }
+        bindings
     }
-}
 
-const CONFIG_MIN_IMPORT_ITEM_LIST_LENGTH: usize = 3;
+    /// Get the combined imports as they would be produced under a different config. Options that
+    /// apply as imports are added (`preserve_paths`) still come from the combiner's own config.
+    pub fn get_import_list_with(&self, config: &Config) -> Vec<ViewPath> {
+        self.import_list(config, &[])
+    }
 
-// Define a representation of imports that is intended to simpliy the process of compressing and
-// optimising the import list.
-#[derive(Clone, Debug, PartialEq)]
-pub struct ImportCombiner {
-    root: ImportNode,
-}
+    /// Get the combined imports, using a symbol table to avoid merges that would make names
+    /// ambiguous: explicit imports are kept alongside a glob of their module when the glob of
+    /// another module imports the same name.
+    pub fn get_import_list_with_symbols<S: SymbolTable>(&self, symbols: &S) -> Vec<ViewPath> {
+        let kept: Vec<Path> = self.ambiguous_absorptions(&self.config, symbols)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        self.import_list(&self.config, &kept)
+    }
 
-impl ImportCombiner {
-    pub fn new() -> ImportCombiner {
-        ImportCombiner { root: ImportNode::new() }
+    // The imports for the top-level `name`, whose selves and renames the root `consumed` (in
+    // nested groups if `nested`), from the cache if `cache` is set and they're in it.
+    fn top_level_imports(&self,
+                         config: &Config,
+                         cache: bool,
+                         nested: bool,
+                         name: &String,
+                         node: &ImportNode,
+                         consumed: (bool, bool))
+                         -> Vec<ViewPath> {
+        if cache {
+            if let Some(cached) = self.cached_imports(name, consumed) {
+                return cached;
+            }
+        }
+        let imports = get_top_level_imports(&self.tree, config, nested, name, node, consumed);
+        if cache {
+            let cached = Arc::new(imports.clone());
+            self.statement_cache.borrow_mut().insert(name.clone(), (consumed, cached));
+        }
+        imports
     }
 
-    pub fn add_imports(&mut self, vps: &[&ViewPath]) {
-        for vp in vps {
-            self.add_import(vp);
+    // The cached imports under a top-level name, if the root consumed the same of them.
+    fn cached_imports(&self, name: &String, consumed: (bool, bool)) -> Option<Vec<ViewPath>> {
+        match self.statement_cache.borrow().get(name) {
+            Some(&(key, ref cached)) if key == consumed => Some(cached.to_vec()),
+            _ => None,
         }
     }
 
-    pub fn add_import(&mut self, vp: &ViewPath) {
-        use ViewPath::*;
-        match vp {
-            // Globs and simple declarations are easy enough.
-            &ViewPathGlob(ref p) => self.add_node(p, ImportNode::just_glob()),
-            &ViewPathSimple(ref p, ref rename) => {
-                self.add_node(p, ImportNode::self_or_rename(rename))
-            }
-            &ViewPathList(ref p, ref items) => {
-                let mut path = p.clone();
-                for i in items {
-                    if i.0 == "self" {
-                        self.add_node(&path, ImportNode::self_or_rename(&i.1));
-                    } else {
-                        path.push(i.0.clone());
-                        self.add_node(&path, ImportNode::self_or_rename(&i.1));
-                        path.pop();
-                    }
+    // `top_level_imports` for each of the top-level `names`, in the same order.
+    #[cfg(not(feature = "parallel"))]
+    fn all_top_level_imports(&self,
+                             config: &Config,
+                             cache: bool,
+                             nested: bool,
+                             names: &[(&String, &ImportNode)],
+                             consumed: (bool, bool))
+                             -> Vec<Vec<ViewPath>> {
+        names.iter()
+            .map(|&(name, node)| {
+                self.top_level_imports(config, cache, nested, name, node, consumed)
+            })
+            .collect()
+    }
+
+    // `top_level_imports` for each of the top-level `names`, in the same order. The names'
+    // subtrees are independent, so (when there are enough of them) those that aren't cached
+    // are combined in parallel.
+    #[cfg(feature = "parallel")]
+    fn all_top_level_imports(&self,
+                             config: &Config,
+                             cache: bool,
+                             nested: bool,
+                             names: &[(&String, &ImportNode)],
+                             consumed: (bool, bool))
+                             -> Vec<Vec<ViewPath>> {
+        use rayon::prelude::*;
+        if names.len() < PARALLEL_MIN_NAMES {
+            return names.iter()
+                .map(|&(name, node)| {
+                    self.top_level_imports(config, cache, nested, name, node, consumed)
+                })
+                .collect();
+        }
+        // The cache can only be used from this thread, so it's looked up first and filled in
+        // afterwards.
+        let cached: Vec<_> = names.iter()
+            .map(|&(name, _)| if cache { self.cached_imports(name, consumed) } else { None })
+            .collect();
+        let tree = &self.tree;
+        let imports: Vec<_> = names.par_iter()
+            .zip(cached.into_par_iter())
+            .map(|(&(name, node), cached)| match cached {
+                Some(imports) => (imports, true),
+                None => (get_top_level_imports(tree, config, nested, name, node, consumed), false),
+            })
+            .collect();
+        if cache {
+            let mut statement_cache = self.statement_cache.borrow_mut();
+            for (&(name, _), &(ref imports, was_cached)) in names.iter().zip(&imports) {
+                if !was_cached {
+                    statement_cache.insert(name.clone(), (consumed, Arc::new(imports.clone())));
                 }
             }
         }
+        imports.into_iter().map(|(imports, _)| imports).collect()
     }
-    fn add_node(&mut self, path: &[String], node: ImportNode) {
-        fn add_node_internal<'a>(node: &'a mut ImportNode, path: &[String]) -> &'a mut ImportNode {
-            if path.len() == 0 {
-                node
-            } else {
-                let next_node =
-                    node.children.entry(path[0].clone()).or_insert_with(|| ImportNode::new());
-                add_node_internal(next_node, &path[1..])
+
+    // The combined imports, with the explicit imports of `kept` emitted even if they're under an
+    // emitted glob.
+    fn import_list(&self, config: &Config, kept: &[Path]) -> Vec<ViewPath> {
+        let mut import_list: Vec<ViewPath> = vec![];
+        let nested = config.edition.supports_nested_groups() &&
+                     (config.granularity == Granularity::Crate ||
+                      config.granularity == Granularity::One);
+        // Only the combiner's own config is cached.
+        let cache = ::std::ptr::eq(config, &self.config);
+        let root = self.tree.root();
+        if !nested {
+            // The root's own imports, followed by those for each top-level name.
+            let consumed = get_imports_for_node(&self.tree,
+                                                config,
+                                                root,
+                                                false,
+                                                false,
+                                                &[],
+                                                &mut import_list);
+            let names = self.tree.ordered_children(root, config);
+            for imports in self.all_top_level_imports(config, cache, false, &names, consumed) {
+                import_list.extend(imports);
             }
-        }
-        add_node_internal(&mut self.root, path).combine_with(&node);
-    }
-    pub fn get_import_list(&self) -> Vec<ViewPath> {
-        fn get_imports_for_node(node: &ImportNode,
-                                self_already_consumed: bool,
-                                renames_already_consumed: bool,
-                                mut node_path: &mut Path,
-                                mut imports: &mut Vec<ViewPath>) {
-            let mut consumed_child_selves = false;
-            let mut consumed_child_renames = false;
-            let need_self_declaration = node.has_self && !self_already_consumed;
-
-            // First construct a list of the imports that can be expressed for this node
-            let mut use_list: Vec<Item> = vec![];
-            if need_self_declaration {
-                use_list.push(Item("self".to_string(), None));
-            }
-            if !renames_already_consumed {
-                use_list.extend(node.renames.iter().map(|r| Item("self".to_string(), Some(r.clone()))));
-            }
-            for (child_name, child_node) in &node.children {
-                if child_node.has_self && !node.has_glob {
-                    use_list.push(Item(child_name.clone(), None));
-                }
-                use_list.extend(child_node.renames
-                    .iter()
-                    .map(|r| Item(child_name.clone(), Some(r.clone()))));
-            }
-            // Now - are we going to use the list? Yes, if it has sufficient elements...
-            let will_use_list = use_list.len() >= CONFIG_MIN_IMPORT_ITEM_LIST_LENGTH;
-            if will_use_list {
-                // As we're using the list, add in any 'self' declaration
-                imports.push(ViewPath::ViewPathList(node_path.clone(), use_list));
-                consumed_child_selves = true;
-                consumed_child_renames = true;
-            } else {
-                if need_self_declaration {
-                    imports.push(ViewPath::ViewPathSimple(node_path.clone(), None));
-                }
-                if !renames_already_consumed {
-                    imports.extend(node.renames
-                        .iter()
-                        .map(|r| ViewPath::ViewPathSimple(node_path.clone(), Some(r.clone()))));
+        } else {
+            // A statement for each top-level crate or module.
+            let mut statements = vec![];
+            let mut relative = vec![];
+            let names = self.tree.ordered_children(root, config);
+            let statements_by_name =
+                self.all_top_level_imports(config, cache, true, &names, (false, false));
+            for (&(name, _), statement) in names.iter().zip(statements_by_name) {
+                if is_relative_root(name) {
+                    relative.extend(statement);
+                } else {
+                    statements.extend(statement);
                 }
             }
-            if node.has_glob {
-                imports.push(ViewPath::ViewPathGlob(node_path.clone()));
-                consumed_child_selves = true;
+            // With one statement for everything, paths relative to the current module still
+            // need their own statements.
+            if config.granularity == Granularity::One && !config.split_top_level_crates &&
+               statements.len() > 1 {
+                statements = vec![ViewPath::group(Path::new(), statements)];
             }
-            for (child_name, child_node) in &node.children {
-                node_path.push(child_name.clone());
-                get_imports_for_node(child_node,
-                                     consumed_child_selves,
-                                     consumed_child_renames,
-                                     &mut node_path,
-                                     &mut imports);
-                node_path.pop();
+            import_list.extend(statements);
+            import_list.extend(relative);
+        }
+        for vp in &mut import_list {
+            vp.expand_tree_root();
+        }
+        if !self.spilled.is_empty() {
+            import_list = self.with_spilled(config, import_list);
+        }
+        // Kept imports go after the statement with their module's glob.
+        for path in kept {
+            let glob = ViewPath::ViewPathGlob(Path::from(&path[..path.len() - 1]));
+            let index = import_list.iter()
+                .position(|vp| vp.simple_paths().contains(&glob))
+                .map_or(import_list.len(), |i| i + 1);
+            import_list.insert(index, ViewPath::ViewPathSimple(path.clone(), None));
+        }
+        if !config.edition.supports_crate_prefix() {
+            for vp in &mut import_list {
+                vp.to_edition_2015_root();
             }
         }
-        let mut import_list: Vec<ViewPath> = vec![];
-        get_imports_for_node(&self.root, false, false, &mut vec![], &mut import_list);
+        // Preserved imports go back in at their original position, as far as the (shorter)
+        // combined list allows.
+        for &(index, ref vp) in &self.preserved {
+            let index = std::cmp::min(index, import_list.len());
+            import_list.insert(index, vp.clone());
+        }
         import_list
     }
+
+    // The imports from the tree with the spilled imports merged in, each spilled name's before
+    // the imports of the first top-level name that comes after it.
+    fn with_spilled(&self, config: &Config, imports: Vec<ViewPath>) -> Vec<ViewPath> {
+        let mut spilled: Vec<_> = self.spilled.iter().collect();
+        spilled.sort_by(|a, b| config.ordering.compare(&a.0, &b.0));
+        let mut spilled = spilled.into_iter().peekable();
+        let mut merged = Vec::with_capacity(imports.len());
+        for vp in imports {
+            if let Some(first) = vp.path().first() {
+                while let Some(&&(ref name, ref vps)) = spilled.peek() {
+                    let name = path_segments(name).next().unwrap_or("");
+                    if config.ordering.compare(first, name) != Ordering::Greater {
+                        break;
+                    }
+                    merged.extend(vps.iter().cloned());
+                    spilled.next();
+                }
+            }
+            merged.push(vp);
+        }
+        merged.extend(spilled.flat_map(|&(_, ref vps)| vps.iter().cloned()));
+        merged
+    }
+
+    /// The combined imports, as `get_import_list` gives them, but produced as they're iterated
+    /// over (a top-level crate or module at a time) rather than collected first. With
+    /// `Granularity::One`, where everything is in one statement, or once imports have been
+    /// spilled, they're collected anyway.
+    pub fn get_import_iter(&self) -> impl Iterator<Item = ViewPath> + '_ {
+        let config = &self.config;
+        let root = self.tree.root();
+        let mut iter = ImportIter {
+            combiner: self,
+            nested: config.edition.supports_nested_groups() &&
+                    (config.granularity == Granularity::Crate ||
+                     config.granularity == Granularity::One),
+            consumed: (false, false),
+            names: self.tree.ordered_children(root, config).into_iter(),
+            pending: vec![].into_iter(),
+            preserved: self.preserved.iter().peekable(),
+            produced: 0,
+        };
+        if !self.spilled.is_empty() ||
+           (iter.nested && config.granularity == Granularity::One &&
+            !config.split_top_level_crates) {
+            iter.names = vec![].into_iter();
+            iter.pending = self.get_import_list().into_iter();
+            iter.preserved = [].iter().peekable();
+        } else if !iter.nested {
+            let mut imports = vec![];
+            iter.consumed = get_imports_for_node(&self.tree,
+                                                 config,
+                                                 root,
+                                                 false,
+                                                 false,
+                                                 &[],
+                                                 &mut imports);
+            iter.pending = iter.finish(imports).into_iter();
+        } else {
+            // Paths relative to the current module go after the others.
+            let mut names: Vec<_> = iter.names.collect();
+            names.sort_by_key(|&(name, _)| is_relative_root(name));
+            iter.names = names.into_iter();
+        }
+        iter
+    }
+
+    /// Render the combined imports as `use` statements, grouped as configured.
+    pub fn render(&self) -> String {
+        self.render_with(&self.config)
+    }
+
+    pub fn render_with(&self, config: &Config) -> String {
+        render_imports(&self.get_import_list_with(config), config)
+    }
 }
 
 pub fn combine_imports(vps: &[&ViewPath]) -> Vec<ViewPath> {
-    let mut combiner = ImportCombiner::new();
+    combine_imports_with_config(vps, Config::default())
+}
+
+pub fn combine_imports_with_config(vps: &[&ViewPath], config: Config) -> Vec<ViewPath> {
+    if let Some(imports) = combine_few_imports(vps, &config) {
+        return imports;
+    }
+    let mut combiner = ImportCombiner::with_config(config);
     combiner.add_imports(vps);
     combiner.get_import_list()
 }
 
+// The combined imports for one or two imports that the combiner would leave as they are (bar
+// their order), without building a tree: plain imports from different crates or modules, with
+// none of the config's rewriting applying to them. Editors combine imports after every edit, so
+// this is the common case.
+fn combine_few_imports(vps: &[&ViewPath], config: &Config) -> Option<Vec<ViewPath>> {
+    let rewrites = config.migrate_from_2015 || config.crate_name.is_some() ||
+                   !config.crate_names.is_empty() ||
+                   config.keyword_policy == KeywordPolicy::Raw ||
+                   !config.preserve_paths.is_empty();
+    let nested = config.edition.supports_nested_groups() &&
+                 config.granularity == Granularity::One && !config.split_top_level_crates;
+    // Single items are only put in lists when lists can be that short, and two crates are only
+    // put in one statement with `Granularity::One`.
+    if vps.len() > 2 || rewrites || config.min_list_length < 2 || (nested && vps.len() > 1) {
+        return None;
+    }
+    let segments: usize = vps.iter().map(|vp| vp.path().len()).sum();
+    if segments >= config.max_nodes {
+        return None;
+    }
+    for vp in vps {
+        let plain = match *vp {
+            &ViewPath::ViewPathSimple(ref p, ref rename) => {
+                // A path of one segment would go in a list with the root's other items.
+                p.len() > 1 && p.len() <= config.max_path_depth &&
+                rename.as_ref().map_or(true, |r| Some(r) != p.last()) &&
+                p.iter().all(|s| !s.is_empty() && s != "self" && s != "super" && !s.contains('-'))
+            }
+            _ => false,
+        };
+        if !plain {
+            return None;
+        }
+    }
+    let mut imports: Vec<ViewPath> = vps.iter().map(|&vp| vp.clone()).collect();
+    if imports.len() == 2 {
+        match config.ordering.compare(&imports[0].path()[0], &imports[1].path()[0]) {
+            Ordering::Less => {}
+            Ordering::Greater => imports.swap(0, 1),
+            // The same crate or module (or names ordered as if they were) is left to the tree.
+            Ordering::Equal => return None,
+        }
+    }
+    if !config.edition.supports_crate_prefix() {
+        for vp in &mut imports {
+            vp.to_edition_2015_root();
+        }
+    }
+    Some(imports)
+}
+
+/// Combine imports without making any names ambiguous, according to a symbol table, along with
+/// the diagnostics for them (including those from the symbol table).
+pub fn combine_with_symbols<S: SymbolTable>(vps: &[&ViewPath],
+                                            config: Config,
+                                            symbols: &S)
+                                            -> (Vec<ViewPath>, Vec<Diagnostic>) {
+    let mut combiner = ImportCombiner::with_config(config);
+    combiner.add_imports(vps);
+    let mut diagnostics = combiner.diagnostics();
+    diagnostics.extend(combiner.symbol_diagnostics(symbols));
+    (combiner.get_import_list_with_symbols(symbols), diagnostics)
+}
+
+/// Combine imports, along with the diagnostics for them: the duplicates removed, globs
+/// overlapping with explicit imports, and names bound more than once. The imports are combined
+/// even if some of the diagnostics are errors.
+pub fn combine_with_diagnostics(vps: &[&ViewPath],
+                                config: Config)
+                                -> (Vec<ViewPath>, Vec<Diagnostic>) {
+    let mut combiner = ImportCombiner::with_config(config);
+    combiner.add_imports(vps);
+    (combiner.get_import_list(), combiner.diagnostics())
+}
+
+/// Combine imports, or fail if anything about them is suspicious: imports that look malformed,
+/// or any warnings or errors from combining them, including globs that would absorb explicit
+/// imports. For automated pipelines, where best-effort output isn't good enough.
+pub fn combine_imports_strict(vps: &[&ViewPath],
+                              config: Config)
+                              -> Result<Vec<ViewPath>, CombineError> {
+    for vp in vps {
+        let malformed = |message: String| {
+            Err(CombineError::Malformed {
+                import: (*vp).clone(),
+                message: message,
+            })
+        };
+        for simple in vp.simple_paths() {
+            let (path, rename) = match simple {
+                ViewPath::ViewPathSimple(p, rename) => (p, rename),
+                ViewPath::ViewPathGlob(p) => (p, None),
+                _ => continue,
+            };
+            // Only a 2015 edition path can start with an empty segment (for a leading `::`).
+            let segments = path.iter().enumerate().filter(|&(i, s)| !(i == 0 && s.is_empty()));
+            for (_, segment) in segments.chain(rename.iter().map(|r| (0, r))) {
+                let valid = segment.starts_with("r#") ||
+                            segment.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if segment.is_empty() || !valid {
+                    return malformed(format!("`{}` isn't a valid name", segment));
+                }
+            }
+        }
+        if let Err(mismatch) = vp.verify_roundtrip() {
+            return malformed(mismatch.to_string());
+        }
+    }
+    let mut combiner = ImportCombiner::with_config(config);
+    for vp in vps {
+        combiner.add_import_with_resolver(vp, &|_: &Conflict| Resolution::Abort);
+    }
+    let diagnostics: Vec<_> = combiner.diagnostics()
+        .into_iter()
+        .filter(|d| d.severity >= Severity::Warning)
+        .collect();
+    if !diagnostics.is_empty() {
+        return Err(CombineError::Diagnostics(diagnostics));
+    }
+    Ok(combiner.get_import_list())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,23 +2225,24 @@ mod tests {
         assert_eq!(ViewPath::from("a::b::c"),
                    ViewPath::ViewPathSimple(vec!["a".to_string(),
                                                  "b".to_string(),
-                                                 "c".to_string()],
+                                                 "c".to_string()].into(),
                                             None));
         assert_eq!(ViewPath::from("a::b::c as rename"),
                    ViewPath::ViewPathSimple(vec!["a".to_string(),
                                                  "b".to_string(),
-                                                 "c".to_string()],
+                                                 "c".to_string()].into(),
                                             Some("rename".to_string())));
         assert_eq!(ViewPath::from("::a::b::c"),
                    ViewPath::ViewPathSimple(vec!["".to_string(),
                                                  "a".to_string(),
                                                  "b".to_string(),
-                                                 "c".to_string()],
+                                                 "c".to_string()].into(),
                                             None));
         assert_eq!(ViewPath::from("::a::b::*"),
-                   ViewPath::ViewPathGlob(vec!["".to_string(), "a".to_string(), "b".to_string()]));
+                   ViewPath::ViewPathGlob(vec!["".to_string(), "a".to_string(), "b".to_string()]
+                       .into()));
         assert_eq!(ViewPath::from("::a::b::{self, d ,e as   x, f}"),
-                   ViewPath::ViewPathList(vec!["".to_string(), "a".to_string(), "b".to_string()],
+                   ViewPath::ViewPathList(as_path("::a::b"),
                                           vec![Item("self".to_string(), None),
                                                Item("d".to_string(), None),
                                                Item("e".to_string(), Some("x".to_string())),
@@ -267,7 +2250,7 @@ mod tests {
         assert_eq!(ViewPath::from("::a::b::{self}"),
                    ViewPath::ViewPathSimple(vec!["".to_string(),
                                                  "a".to_string(),
-                                                 "b".to_string()],
+                                                 "b".to_string()].into(),
                                             None));
     }
     #[test]
@@ -329,6 +2312,413 @@ mod tests {
                         ViewPath::from("a::b::*")]);
     }
     #[test]
+    fn globs_in_lists() {
+        assert_eq!(ViewPath::from("a::b::{self, *}"),
+                   ViewPath::ViewPathNested(as_path("a::b"),
+                                            vec![ViewPath::from("self"),
+                                                 ViewPath::ViewPathGlob(Path::new())]));
+        assert_eq!(ViewPath::from("a::b::{*}").simple_paths(),
+                   vec![ViewPath::from("a::b::*")]);
+        let list = ViewPath::ViewPathList(as_path("a::b"), vec![Item::from("*"), Item::from("c")]);
+        assert_eq!(list.to_string(), "a::b::{*, c}");
+        assert_eq!(combine_imports(&[&ViewPath::from("a::b::{self, *}"), &list]),
+                   vec![ViewPath::from("a::b"), ViewPath::from("a::b::*")]);
+        let config = Config { granularity: Granularity::Crate, ..Config::compact() };
+        assert_eq!(combine_imports_with_config(&[&ViewPath::from("a::b::{self, *}"),
+                                                 &ViewPath::from("a::c")],
+                                               config)
+                       .iter()
+                       .map(|vp| vp.to_string())
+                       .collect::<Vec<_>>(),
+                   vec!["a::{b::{self, *}, c}"]);
+    }
+    #[test]
+    fn owned_imports() {
+        let vps = [ViewPath::from("a::{b, c::{self, d as e}, f::*}"),
+                   ViewPath::from("super::super::x"),
+                   ViewPath::from("a::{g, self}")];
+        let mut owned = ImportCombiner::with_config(Config::compact());
+        for vp in &vps {
+            owned.add_import_owned(vp.clone());
+        }
+        let borrowed: Vec<&ViewPath> = vps.iter().collect();
+        assert_eq!(owned.get_import_list(),
+                   combine_imports_with_config(&borrowed, Config::compact()));
+    }
+    #[test]
+    fn group_lengths() {
+        let mut combiner = ImportCombiner::with_config(Config::compact());
+        for vp in &["a::{self, b::{c, d as e}, f::*}", "a::g as h", "x::y", "m::*", "m::n"] {
+            combiner.add_import(&ViewPath::from(*vp));
+        }
+        let rendered: Vec<_> = combiner.get_import_list().iter().map(|vp| vp.to_string()).collect();
+        assert_eq!(rendered, vec!["a::{self, b::{c, d as e}, f::*, g as h}", "m::*", "x::y"]);
+        for statement in &rendered {
+            let (name, group) = statement.split_at(statement.find("::").unwrap());
+            assert_eq!(combiner.group_len(&as_path(name)), Some(group.len() - 2));
+        }
+        assert_eq!(combiner.group_len(&as_path("a::b")), Some("{c, d as e}".len()));
+        assert_eq!(combiner.group_len(&as_path("a::q")), None);
+        // Adding to the tree clears the cached lengths along the way.
+        combiner.add_import(&ViewPath::from("a::b::z"));
+        assert_eq!(combiner.group_len(&as_path("a::b")), Some("{c, d as e, z}".len()));
+        assert_eq!(combiner.group_len(&as_path("a")),
+                   Some("{self, b::{c, d as e, z}, f::*, g as h}".len()));
+    }
+    #[test]
+    fn incremental() {
+        for config in &[Config::default(), Config::compact()] {
+            let mut combiner = ImportCombiner::with_config(config.clone());
+            let mut vps = vec![];
+            for vp in &["a::{b, c}", "x::y", "a::d::*", "x::z", "a::b as e"] {
+                vps.push(ViewPath::from(*vp));
+                combiner.add_import(vps.last().unwrap());
+                let all: Vec<&ViewPath> = vps.iter().collect();
+                assert_eq!(combiner.get_import_list(),
+                           combine_imports_with_config(&all, config.clone()));
+            }
+            // Only the imports under `a` are combined again.
+            combiner.add_import(&ViewPath::from("a::f"));
+            let cached: Vec<_> = combiner.statement_cache.borrow().keys().cloned().collect();
+            assert_eq!(cached, vec!["x"]);
+            vps.push(ViewPath::from("a::f"));
+            let all: Vec<&ViewPath> = vps.iter().collect();
+            assert_eq!(combiner.get_import_list(),
+                       combine_imports_with_config(&all, config.clone()));
+            assert_eq!(combiner.statement_cache.borrow().len(), 2);
+        }
+    }
+    #[test]
+    fn import_iter() {
+        let configs = [Config::default(),
+                       Config::compact(),
+                       Config { granularity: Granularity::One, ..Config::compact() },
+                       Config { edition: Edition::Edition2015, ..Config::default() }];
+        for config in &configs {
+            let mut combiner = ImportCombiner::with_config(Config {
+                preserve_paths: vec![PathPattern::from("p::*")],
+                ..config.clone()
+            });
+            for vp in &["p::q", "a::{b, c}", "super::super::x", "crate::y", "p::r", "a::d::*",
+                        "self::z", "p::s"] {
+                combiner.add_import(&ViewPath::from(*vp));
+            }
+            assert_eq!(combiner.get_import_iter().collect::<Vec<_>>(),
+                       combiner.get_import_list());
+        }
+        let combiner = ImportCombiner::new();
+        assert_eq!(combiner.get_import_iter().next(), None);
+    }
+    #[test]
+    fn cached_list() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_import(&ViewPath::from("a::{b, c, d}"));
+        let list = combiner.cached_import_list().as_ptr();
+        assert_eq!(combiner.cached_import_list().as_ptr(), list);
+        assert_eq!(combiner.cached_import_list(), &combiner.get_import_list()[..]);
+        combiner.add_imports(&[&ViewPath::from("a::e")]);
+        assert_eq!(combiner.cached_import_list(), &[ViewPath::from("a::{b, c, d, e}")]);
+    }
+    #[test]
+    fn repeated_imports() {
+        let imports: Vec<_> = ["a::b", "a::{c, d}", "p::q", "a::b", "a::{c, d}", "p::q", "a::e"]
+            .iter()
+            .map(|vp| ViewPath::from(*vp))
+            .collect();
+        for min_list_statements in 1..3 {
+            let config = Config {
+                preserve_paths: vec![PathPattern::from("p::*")],
+                min_list_length: 2,
+                min_list_statements: min_list_statements,
+                ..Config::default()
+            };
+            let mut batch = ImportCombiner::with_config(config.clone());
+            batch.add_imports(&imports.iter().collect::<Vec<_>>());
+            let mut one_by_one = ImportCombiner::with_config(config.clone());
+            for vp in &imports {
+                one_by_one.add_import(vp);
+            }
+            assert_eq!(batch.get_import_list(), one_by_one.get_import_list());
+            assert_eq!(batch.diagnostics(), one_by_one.diagnostics());
+            assert_eq!(batch.get_import_list()[1..],
+                       [ViewPath::from("p::q"), ViewPath::from("p::q")]);
+            // Repeats of the imports added by earlier calls are recognised too.
+            let (first, second) = imports.split_at(3);
+            let mut split = ImportCombiner::with_config(config);
+            split.add_imports(&first.iter().collect::<Vec<_>>());
+            split.add_imports_sorted(&second.iter().collect::<Vec<_>>());
+            assert_eq!(split.get_import_list(), batch.get_import_list());
+            assert_eq!(split.diagnostics(), batch.diagnostics());
+            assert_eq!(split.seen.len(), if min_list_statements == 1 { 3 } else { 0 });
+            split.compact();
+            assert!(split.seen.is_empty());
+        }
+    }
+    #[test]
+    fn deep_chains() {
+        let deep = (0..CHAIN_COMPRESSION_DEPTH).map(|i| format!("m{}", i)).collect::<Vec<_>>();
+        let deep = deep.join("::");
+        let imports: Vec<_> = vec![format!("{}::{{A, B as C}}", deep),
+                                   format!("{}::D", deep),
+                                   format!("x::{}::E", deep),
+                                   format!("x::{}::*", deep),
+                                   "x::y::z".to_string()]
+            .iter()
+            .map(|vp| ViewPath::from(vp.as_str()))
+            .collect();
+        let configs = vec![Config::default(),
+                           Config { granularity: Granularity::Crate, ..Config::default() },
+                           Config { min_list_length: 1, ..Config::default() },
+                           Config { edition: Edition::Edition2015, ..Config::default() }];
+        for config in configs {
+            let mut compressed = ImportCombiner::with_config(config.clone());
+            compressed.add_imports(&imports.iter().collect::<Vec<_>>());
+            let mut uncompressed = ImportCombiner::with_config(config);
+            for vp in &imports {
+                uncompressed.add_import(vp);
+            }
+            assert!(compressed.metrics().nodes < uncompressed.metrics().nodes);
+            assert_eq!(compressed.metrics().segments, uncompressed.metrics().segments);
+            assert_eq!(compressed.get_import_list(), uncompressed.get_import_list());
+            assert_eq!(compressed.diagnostics(), uncompressed.diagnostics());
+            for path in &["m0::m1", "x::m0", &deep] {
+                assert_eq!(compressed.group_len(&as_path(path)),
+                           uncompressed.group_len(&as_path(path)));
+            }
+            // Adding imports along the chains expands them again.
+            let vp = ViewPath::from("m0::m1::F");
+            compressed.add_import(&vp);
+            uncompressed.add_import(&vp);
+            assert_eq!(compressed.get_import_list(), uncompressed.get_import_list());
+        }
+    }
+    #[test]
+    fn spilling() {
+        let mut combiner = ImportCombiner::with_config(Config {
+            spill_threshold: Some(12),
+            ..Config::rustfmt_default()
+        });
+        for vp in &["std::io::{Read, Write}", "std::fmt", "a::b", "c::d::e", "a::f", "x::y::z::w",
+                    "a::g", "c::d::h"] {
+            combiner.add_import(&ViewPath::from(*vp));
+            assert!(combiner.metrics().nodes <= 12);
+        }
+        // `x`, `c` and then `a` were spilled, leaving `std`, which more imports went into.
+        let list = combiner.get_import_list();
+        assert_eq!(list,
+                   ["a::g", "a::{b, f}", "c::d::h", "c::d::e", "std::fmt", "std::io::{Read, Write}",
+                    "x::y::z::w"]
+                       .iter()
+                       .map(|vp| ViewPath::from(*vp))
+                       .collect::<Vec<_>>());
+        assert_eq!(combiner.get_import_iter().collect::<Vec<_>>(), list);
+        assert_eq!(combiner.take_spilled(),
+                   vec![ViewPath::from("x::y::z::w"),
+                        ViewPath::from("c::d::e"),
+                        ViewPath::from("a::{b, f}")]);
+        assert_eq!(combiner.get_import_list().len(), 4);
+        assert_eq!(combiner.take_spilled(), vec![]);
+        // A repeat of an import that's been spilled goes back into the tree.
+        let fmt = ViewPath::from("std::fmt");
+        combiner.add_imports(&[&fmt]);
+        combiner.spill("std".to_string());
+        assert!(combiner.seen.is_empty());
+        combiner.add_imports(&[&fmt]);
+        combiner.take_spilled();
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::g"), ViewPath::from("c::d::h"), fmt]);
+    }
+    #[test]
+    fn few_imports() {
+        let inputs: Vec<Vec<&str>> = vec![vec![],
+                                          vec!["a::b"],
+                                          vec!["b::c", "a::b::d"],
+                                          vec!["crate::a", "std::io as stdio"],
+                                          vec!["a::b", "A::c"],
+                                          vec!["a::b", "a::c"],
+                                          vec!["a", "b"],
+                                          vec!["a::b as b"],
+                                          vec!["self::a", "b::c"],
+                                          vec!["a::*", "b::c"],
+                                          vec!["a::b", "b::c", "c::d"]];
+        let configs: Vec<Config> = PRESET_NAMES.iter()
+            .map(|name| Config::preset(name).unwrap())
+            .chain(vec![Config { edition: Edition::Edition2015, ..Config::default() },
+                        Config { granularity: Granularity::One, ..Config::default() },
+                        Config { ordering: ItemOrdering::CaseInsensitive, ..Config::default() },
+                        Config { min_list_length: 1, ..Config::default() },
+                        Config { strip_redundant_renames: true, ..Config::default() },
+                        Config { max_nodes: 3, ..Config::default() }])
+            .collect();
+        for config in configs {
+            for input in &inputs {
+                let vps: Vec<_> = input.iter().map(|vp| ViewPath::from(*vp)).collect();
+                let vps: Vec<_> = vps.iter().collect();
+                let mut combiner = ImportCombiner::with_config(config.clone());
+                combiner.add_imports(&vps);
+                assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                           combiner.get_import_list(),
+                           "{:?} with {:?}",
+                           input,
+                           config);
+            }
+        }
+        let (a, b) = (ViewPath::from("b::c"), ViewPath::from("a::b::d"));
+        assert_eq!(combine_few_imports(&[&a, &b], &Config::default()), Some(vec![b, a]));
+        let (a, b) = (ViewPath::from("a::b"), ViewPath::from("a::c"));
+        assert_eq!(combine_few_imports(&[&a, &b], &Config::default()), None);
+    }
+    #[test]
+    fn snapshots() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_import_from(&ViewPath::from("a::{b, c, d}"), "crate");
+        let list = combiner.get_import_list();
+        let mut preview = combiner.clone();
+        assert_eq!(preview, combiner);
+        preview.add_import_from(&ViewPath::from("a::e"), "module");
+        preview.add_import(&ViewPath::from("f::g"));
+        assert_eq!(combiner.get_import_list(), list);
+        assert_eq!(combiner.diagnostics(), vec![]);
+        assert_eq!(preview.get_import_list().len(), list.len() + 2);
+        assert_eq!(preview.diagnostics().len(), 1);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_import_from(&ViewPath::from("a::{self, b, c as d}"), "crate");
+        combiner.add_import_from(&ViewPath::from("a::e"), "module");
+        combiner.add_imports(&[&ViewPath::from("f::g::h::i::j::*"),
+                               &ViewPath::from("k::l as x"),
+                               &ViewPath::from("m::n as x")]);
+        combiner.get_import_list();
+        let json = ::serde_json::to_string(&combiner).unwrap();
+        let mut restored: ImportCombiner = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_import_list(), combiner.get_import_list());
+        assert_eq!(restored.diagnostics(), combiner.diagnostics());
+        combiner.add_import(&ViewPath::from("f::g::o"));
+        restored.add_import(&ViewPath::from("f::g::o"));
+        assert_eq!(restored.get_import_list(), combiner.get_import_list());
+
+        let vp = ViewPath::from("a::b::{self, c as d, *}");
+        assert_eq!(::serde_json::from_value::<ViewPath>(::serde_json::to_value(&vp).unwrap())
+                       .unwrap(),
+                   vp);
+        assert_eq!(::serde_json::to_value(Item::from("c as d")).unwrap(), json!(["c", "d"]));
+    }
+    #[test]
+    fn compacting() {
+        let mut combiner = ImportCombiner::new();
+        for i in 0..100 {
+            combiner.add_import(&ViewPath::from(&format!("a::b{}::{{c, d, e}}", i % 10)[..]));
+        }
+        let list = combiner.get_import_list();
+        let metrics = combiner.metrics();
+        combiner.compact();
+        assert_eq!(combiner.get_import_list(), list);
+        assert_eq!(combiner.metrics().nodes, metrics.nodes);
+        assert!(combiner.metrics().heap_bytes < metrics.heap_bytes);
+        combiner.add_import(&ViewPath::from("a::b1::f"));
+        assert_eq!(combiner.metrics().nodes, metrics.nodes + 1);
+    }
+    #[test]
+    fn metrics() {
+        let mut combiner = ImportCombiner::with_config(Config {
+            preserve_paths: vec![PathPattern::from("p::*")],
+            ..Config::default()
+        });
+        assert_eq!(combiner.metrics().nodes, 0);
+        assert_eq!(combiner.metrics().heap_bytes, combiner.tree.metrics().heap_bytes);
+        for vp in &["a::b::{c, d as e}", "a::b::d", "a::f as g", "p::q"] {
+            combiner.add_import(&ViewPath::from(*vp));
+        }
+        let metrics = combiner.metrics();
+        // `a`, `a::b`, `a::b::c`, `a::b::d` and `a::f`, and the segments of `p::q`.
+        assert_eq!((metrics.nodes, metrics.segments, metrics.renames), (5, 7, 2));
+        assert!(metrics.heap_bytes > combiner.tree.metrics().heap_bytes);
+        combiner.add_import(&ViewPath::from("h::i::j"));
+        assert!(combiner.metrics().heap_bytes > metrics.heap_bytes);
+    }
+    #[test]
+    fn segments() {
+        assert_eq!(path_segments("a::b::c").collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(path_segments("::a").collect::<Vec<_>>(), vec!["", "a"]);
+        assert_eq!(path_segments("").count(), 1);
+        assert_eq!(as_path("a::b"), path_segments("a::b").map(String::from).collect::<Path>());
+    }
+    #[test]
+    fn sorted_batches() {
+        let imports: Vec<_> = ["a::b::c", "a::b::d", "a::b::e::f", "a::g", "a::g::h", "b::*",
+                               "b::c", "c::d as e"]
+            .iter()
+            .map(|vp| ViewPath::from(*vp))
+            .collect();
+        let mut sorted = ImportCombiner::new();
+        sorted.add_imports_sorted(&imports.iter().collect::<Vec<_>>());
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&imports.iter().collect::<Vec<_>>());
+        assert_eq!(sorted, combiner);
+        // Out of order, they're still combined.
+        let mut unsorted = ImportCombiner::new();
+        unsorted.add_imports_sorted(&imports.iter().rev().collect::<Vec<_>>());
+        assert_eq!(unsorted.get_import_list(), combiner.get_import_list());
+        assert_eq!(unsorted.group_len(&as_path("a::b")), Some("{c, d, e::f}".len()));
+    }
+    #[test]
+    fn many_crates() {
+        // Enough top-level names to be combined in parallel, with the `parallel` feature.
+        let mut imports = vec![];
+        for i in 0..100 {
+            imports.push(ViewPath::from(&format!("c{:02}::a::{{b, c}}", i)[..]));
+            imports.push(ViewPath::from(&format!("c{:02}::d", i)[..]));
+        }
+        for config in &[Config::default(), Config::compact()] {
+            let mut combiner = ImportCombiner::with_config(config.clone());
+            combiner.add_imports(&imports.iter().rev().collect::<Vec<_>>());
+            let list = combiner.get_import_list();
+            assert_eq!(combiner.get_import_list(), list);
+            assert_eq!(combiner.get_import_list_with(&config.clone()), list);
+            if config.granularity == Granularity::Crate {
+                assert_eq!(list.len(), 100);
+                assert_eq!(list[42], ViewPath::from("c42::{a::{b, c}, d}"));
+            } else {
+                // Lists of two are split up by default.
+                assert_eq!(list.len(), 300);
+                assert_eq!(list[126], ViewPath::from("c42::a::b"));
+            }
+        }
+    }
+    #[test]
+    fn wide_modules() {
+        // Added in reverse, so the children have to be sorted whichever way they're stored.
+        let mut combiner = ImportCombiner::new();
+        for i in (0..2000).rev() {
+            combiner.add_import(&ViewPath::from(&format!("a::m{:04}", i)[..]));
+        }
+        combiner.add_import(&ViewPath::from("a::m0001::x"));
+        let list = combiner.get_import_list();
+        assert_eq!(list.len(), 2);
+        let rendered = list[0].to_string();
+        assert!(rendered.starts_with("a::{m0000, m0001, m0002, m0003"));
+        assert!(rendered.ends_with("m1998, m1999}"));
+        assert_eq!(list[1], ViewPath::from("a::m0001::x"));
+    }
+    #[test]
+    fn deep_paths() {
+        let deep: Vec<String> = (0..500).map(|i| format!("m{}", i)).collect();
+        let mut wide = deep.clone();
+        wide[499] = "n".to_string();
+        let vps = [ViewPath::ViewPathSimple(deep.clone().into(), None),
+                   ViewPath::ViewPathSimple(wide.into(), None),
+                   ViewPath::ViewPathGlob(Path::from(&deep[..250]))];
+        let vps: Vec<&ViewPath> = vps.iter().collect();
+        let config = Config { max_path_depth: 1000, min_list_length: 2, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::ViewPathGlob(Path::from(&deep[..250])),
+                        ViewPath::ViewPathList(Path::from(&deep[..499]),
+                                               vec![Item::from("m499"), Item::from("n")])]);
+    }
+    #[test]
     fn combine_lists() {
         assert_eq!(combine_imports(&vec![&ViewPath::from("a::b::{g,h,a,b}"),
                                          &ViewPath::from("a::b::{c,d,e}")]),
@@ -383,4 +2773,607 @@ mod tests {
                         ViewPath::from("a::b::d as yy"),
                         ViewPath::from("c")]);
     }
+    #[test]
+    fn edition_controls_crate_prefix() {
+        let vps = [&ViewPath::from("crate::a::b"), &ViewPath::from("c::d")];
+        assert_eq!(combine_imports_with_config(&vps, Config::default()),
+                   vec![ViewPath::from("c::d"), ViewPath::from("::a::b")]);
+        let config = Config { edition: Edition::Edition2018, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("c::d"), ViewPath::from("crate::a::b")]);
+    }
+    #[test]
+    fn configurable_item_ordering() {
+        let vps = [&ViewPath::from("a::{Foo, bar, Baz}"), &ViewPath::from("a::u16")];
+        assert_eq!(combine_imports(&vps), vec![ViewPath::from("a::{Baz, Foo, bar, u16}")]);
+        let config = Config { ordering: ItemOrdering::CaseInsensitive, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{bar, Baz, Foo, u16}")]);
+    }
+    #[test]
+    fn configurable_self_position() {
+        let vps = [&ViewPath::from("a::{self as x, b, t}"), &ViewPath::from("a")];
+        assert_eq!(combine_imports(&vps),
+                   vec![ViewPath::from("a::{self, self as x, b, t}")]);
+        let config = Config { self_position: SelfPosition::Last, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{b, t, self, self as x}")]);
+        let config = Config { self_position: SelfPosition::Sorted, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{b, self, self as x, t}")]);
+    }
+    #[test]
+    fn preserved_paths_are_not_merged() {
+        let vps = [&ViewPath::from("diesel::prelude::*"),
+                   &ViewPath::from("a::b"),
+                   &ViewPath::from("diesel::prelude::Foo"),
+                   &ViewPath::from("a::c"),
+                   &ViewPath::from("a::d")];
+        let config = Config {
+            preserve_paths: vec![PathPattern::from("diesel::prelude::*")],
+            ..Config::default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("diesel::prelude::*"),
+                        ViewPath::from("a::{b, c, d}"),
+                        ViewPath::from("diesel::prelude::Foo")]);
+    }
+    #[test]
+    fn denied_globs_except_protected() {
+        let vps = [&ViewPath::from("a::b::*"),
+                   &ViewPath::from("a::b::c"),
+                   &ViewPath::from("x::prelude::*"),
+                   &ViewPath::from("x::prelude::Y")];
+        let config = Config {
+            glob_policy: GlobPolicy::Deny,
+            protected_globs: vec![PathPattern::from("*::prelude::*")],
+            ..Config::default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::b::c"), ViewPath::from("x::prelude::*")]);
+    }
+    #[test]
+    fn lossless() {
+        let vps = [&ViewPath::from("a::b::*"),
+                   &ViewPath::from("a::b::c"),
+                   &ViewPath::from("a::b::{d, e}"),
+                   &ViewPath::from("x::y")];
+        let config = Config { strictness: Strictness::Lossless, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                   vec![ViewPath::from("a::b::{c, d, e}"),
+                        ViewPath::from("a::b::*"),
+                        ViewPath::from("x::y")]);
+        let config = Config { granularity: Granularity::Crate, ..Config::compact() };
+        assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                   vec![ViewPath::from("a::b::*"), ViewPath::from("x::y")]);
+        let config = Config { strictness: Strictness::Lossless, ..config };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::group(as_path("a::b"),
+                                        vec![ViewPath::ViewPathGlob(Path::new()),
+                                             ViewPath::from("c"),
+                                             ViewPath::from("d"),
+                                             ViewPath::from("e")]),
+                        ViewPath::from("x::y")]);
+    }
+    #[test]
+    fn conflict_resolvers() {
+        let vps = [ViewPath::from("a::b::c"), ViewPath::from("a::b::*"), ViewPath::from("x::y")];
+        let combine = |resolution: Resolution| {
+            let mut combiner = ImportCombiner::new();
+            let conflicts = ::std::cell::RefCell::new(vec![]);
+            for vp in &vps {
+                combiner.add_import_with_resolver(vp, &|conflict: &Conflict| {
+                    conflicts.borrow_mut().push(conflict.clone());
+                    resolution
+                });
+            }
+            (combiner.try_get_import_list(), conflicts.into_inner())
+        };
+        let (imports, conflicts) = combine(Resolution::PreferGlob);
+        assert_eq!(imports, Ok(vec![ViewPath::from("a::b::*"), ViewPath::from("x::y")]));
+        assert_eq!(conflicts,
+                   vec![Conflict {
+                            module: as_path("a::b"),
+                            names: vec!["c".to_string()],
+                        }]);
+        assert_eq!(combine(Resolution::KeepBoth).0,
+                   Ok(vec![ViewPath::from("a::b::*"),
+                           ViewPath::from("a::b::c"),
+                           ViewPath::from("x::y")]));
+        assert_eq!(combine(Resolution::PreferExplicit).0,
+                   Ok(vec![ViewPath::from("a::b::c"), ViewPath::from("x::y")]));
+        let diagnostics = combine(Resolution::Abort).0.unwrap_err();
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::AbortedConflict(as_path("a::b")));
+    }
+    #[test]
+    fn lists_need_enough_contributing_statements() {
+        let vps = [&ViewPath::from("a::b::c"), &ViewPath::from("a::b::{d, e}")];
+        let config = Config { min_list_statements: 2, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::b::{c, d, e}")]);
+        let config = Config { min_list_statements: 3, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::b::c"),
+                        ViewPath::from("a::b::d"),
+                        ViewPath::from("a::b::e")]);
+    }
+    #[test]
+    fn split_nested_path() {
+        assert_eq!(ViewPath::from("a::{b::{c, d as e}, f::*, self}"),
+                   ViewPath::ViewPathNested(as_path("a"),
+                                            vec![ViewPath::from("b::{c, d as e}"),
+                                                 ViewPath::from("f::*"),
+                                                 ViewPath::from("self")]));
+        assert_eq!(ViewPath::from("::{a::b, c}"),
+                   ViewPath::ViewPathNested(as_path(""),
+                                            vec![ViewPath::from("a::b"), ViewPath::from("c")]));
+        assert_eq!(ViewPath::from("a::{b::{c, d as e}, f::*, self}").flatten(),
+                   vec![ViewPath::from("a::b::{c, d as e}"),
+                        ViewPath::from("a::f::*"),
+                        ViewPath::from("a")]);
+    }
+    #[test]
+    fn granularity() {
+        let vps = [&ViewPath::from("a::b::c"),
+                   &ViewPath::from("a::b::{d, e}"),
+                   &ViewPath::from("a::f"),
+                   &ViewPath::from("x::y")];
+        let config = Config {
+            edition: Edition::Edition2018,
+            granularity: Granularity::Item,
+            ..Config::default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config).len(), 5);
+        let config = Config {
+            edition: Edition::Edition2018,
+            granularity: Granularity::Crate,
+            ..Config::default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{b::{c, d, e}, f}"), ViewPath::from("x::y")]);
+        let config = Config {
+            edition: Edition::Edition2018,
+            granularity: Granularity::One,
+            ..Config::default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                   vec![ViewPath::from("{a::{b::{c, d, e}, f}, x::y}")]);
+        let config = Config { split_top_level_crates: true, ..config };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{b::{c, d, e}, f}"), ViewPath::from("x::y")]);
+        // Nested groups aren't available in 2015, so that falls back to per-module lists.
+        let config = Config { granularity: Granularity::Crate, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::b::{c, d, e}"),
+                        ViewPath::from("a::f"),
+                        ViewPath::from("x::y")]);
+    }
+    #[test]
+    fn split_top_level_crates() {
+        let vps = [&ViewPath::from("a"), &ViewPath::from("b"), &ViewPath::from("c")];
+        assert_eq!(combine_imports(&vps), vec![ViewPath::from("{a, b, c}")]);
+        let config = Config { split_top_level_crates: true, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a"), ViewPath::from("b"), ViewPath::from("c")]);
+    }
+    #[test]
+    fn render_with_other_configs() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&[&ViewPath::from("a::b::c"), &ViewPath::from("a::b::{d, e}")]);
+        assert_eq!(combiner.get_import_list(), vec![ViewPath::from("a::b::{c, d, e}")]);
+        assert_eq!(combiner.get_import_list_with(&Config::verbose()),
+                   vec![ViewPath::from("a::b::c"),
+                        ViewPath::from("a::b::d"),
+                        ViewPath::from("a::b::e")]);
+        assert_eq!(combiner.render_with(&Config::compact()), "use a::b::{c, d, e};\n");
+    }
+    #[test]
+    fn name_conflicts() {
+        let vps = [&ViewPath::from("a::b as x"),
+                   &ViewPath::from("c::{d as x, e as _}"),
+                   &ViewPath::from("f::e as _"),
+                   &ViewPath::from("a::b as x"),
+                   &ViewPath::from("g::x")];
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.diagnostics()[0],
+                   Diagnostic {
+                       kind: DiagnosticKind::NameConflict("x".to_string()),
+                       severity: Severity::Warning,
+                       message: "`x` is imported more than once: `a::b as x`, `c::d as x`, \
+                                 `g::x`"
+                           .to_string(),
+                       paths: vec![ViewPath::from("a::b as x"),
+                                   ViewPath::from("c::d as x"),
+                                   ViewPath::from("g::x")],
+                   });
+        assert!(combiner.try_get_import_list().is_ok());
+        let mut combiner = ImportCombiner::with_config(Config {
+            strictness: Strictness::Strict,
+            ..Config::default()
+        });
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.try_get_import_list().map_err(|d| d[0].severity),
+                   Err(Severity::Error));
+        combiner = ImportCombiner::with_config(combiner.config().clone());
+        combiner.add_imports(&[&ViewPath::from("a::b"), &ViewPath::from("a::b as b")]);
+        assert_eq!(combiner.diagnostics(), vec![]);
+    }
+    #[test]
+    fn redundant_renames() {
+        let vps = [&ViewPath::from("a::b as b"),
+                   &ViewPath::from("a::{b as b, c, d as e}"),
+                   &ViewPath::from("a::c::{self as c}")];
+        assert_eq!(combine_imports(&vps), vec![ViewPath::from("a::{b, c, d as e}")]);
+        let config = Config { strip_redundant_renames: false, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("a::{b as b, c, c as c, d as e}")]);
+    }
+    #[test]
+    fn self_prefixes() {
+        let vps = [&ViewPath::from("self::a::b"),
+                   &ViewPath::from("a::{c, d}"),
+                   &ViewPath::from("{self::a::e, x::y}"),
+                   &ViewPath::from("self::*")];
+        let config = Config { strip_self_prefix: true, ..Config::default() };
+        assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                   vec![ViewPath::from("a::{b, c, d, e}"),
+                        ViewPath::from("self::*"),
+                        ViewPath::from("x::y")]);
+        let config = Config { edition: Edition::Edition2018, min_list_length: 2, ..config };
+        let mut combiner = ImportCombiner::with_config(config);
+        combiner.set_module(&as_path("m"));
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::{c, d}"),
+                        ViewPath::from("crate::m::*"),
+                        ViewPath::from("crate::m::a::{b, e}"),
+                        ViewPath::from("x::y")]);
+    }
+    #[test]
+    fn migrate_from_2015() {
+        let crates = vec!["serde".to_string()];
+        for &(before, after) in &[("::std::io", "std::io"),
+                                  ("serde::Serialize", "serde::Serialize"),
+                                  ("::foo::{a, b}", "crate::foo::{a, b}"),
+                                  ("foo::*", "crate::foo::*"),
+                                  ("self::foo", "self::foo"),
+                                  ("super::foo::bar as baz", "super::foo::bar as baz"),
+                                  ("::{serde, foo::bar}", "{serde, crate::foo::bar}"),
+                                  ("{std, foo}", "{std, crate::foo}")] {
+            assert_eq!(ViewPath::from(before).migrated_from_2015(&crates).to_string(), after);
+        }
+        let config = Config {
+            edition: Edition::Edition2018,
+            migrate_from_2015: true,
+            extern_crates: crates,
+            ..Config::rustfmt_default()
+        };
+        assert_eq!(combine_imports_with_config(&[&ViewPath::from("::foo::a"),
+                                                 &ViewPath::from("foo::b"),
+                                                 &ViewPath::from("crate::foo::c"),
+                                                 &ViewPath::from("serde::de")],
+                                               config),
+                   vec![ViewPath::from("crate::foo::{a, b, c}"), ViewPath::from("serde::de")]);
+    }
+    #[test]
+    fn crate_names() {
+        let vps = [&ViewPath::from("crate::foo::Bar"),
+                   &ViewPath::from("my_crate::foo::Baz"),
+                   &ViewPath::from("{my_crate::x, y}")];
+        let config = Config {
+            edition: Edition::Edition2018,
+            crate_name: Some("my_crate".to_string()),
+            ..Config::rustfmt_default()
+        };
+        assert_eq!(combine_imports_with_config(&vps, config.clone()),
+                   vec![ViewPath::from("crate::foo::{Bar, Baz}"),
+                        ViewPath::from("crate::x"),
+                        ViewPath::from("y")]);
+        let config = Config { prefer_crate_name: true, ..config };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("my_crate::foo::{Bar, Baz}"),
+                        ViewPath::from("my_crate::x"),
+                        ViewPath::from("y")]);
+    }
+    #[test]
+    fn package_names() {
+        let vps = [&ViewPath::from("serde-json::Value"),
+                   &ViewPath::from("serde_json::Map"),
+                   &ViewPath::from("{serde-json::json, my-crate::x}"),
+                   &ViewPath::from("crate::y"),
+                   &ViewPath::from("other::z")];
+        let mut config = Config {
+            crate_name: Some("my-crate".to_string()),
+            ..Config::rustfmt_default()
+        };
+        config.crate_names.insert("other".to_string(), "renamed".to_string());
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("crate::{x, y}"),
+                        ViewPath::from("renamed::z"),
+                        ViewPath::from("serde_json::{Map, Value, json}")]);
+    }
+    #[test]
+    fn reserved_keywords() {
+        let vps = [&ViewPath::from("fn::x"), &ViewPath::from("a::{b as try, dyn}")];
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&vps);
+        let errors: Vec<_> = combiner.try_get_import_list()
+            .unwrap_err()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(errors, vec!["`fn::x` can't be imported, as `fn` is a reserved keyword"]);
+        let config = Config { edition: Edition::Edition2018, ..Config::default() };
+        let mut combiner = ImportCombiner::with_config(config.clone());
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.diagnostics().len(), 3);
+        let config = Config { keyword_policy: KeywordPolicy::Raw, ..config };
+        let mut combiner = ImportCombiner::with_config(config);
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.try_get_import_list(),
+                   Ok(vec![ViewPath::from("a::b as r#try"),
+                           ViewPath::from("a::r#dyn"),
+                           ViewPath::from("r#fn::x")]));
+    }
+    #[test]
+    fn alias_cycles() {
+        let vps = [&ViewPath::from("m::a as b"),
+                   &ViewPath::from("m::b as a"),
+                   &ViewPath::from("n::x as y"),
+                   &ViewPath::from("n::y as z"),
+                   &ViewPath::from("p::z as x"),
+                   &ViewPath::from("n::u as v")];
+        let (_, diagnostics) = combine_with_diagnostics(&vps, Config::default());
+        let cycles: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+        assert_eq!(cycles,
+                   vec![DiagnosticKind::AliasCycle(vec!["a".to_string(), "b".to_string()]),
+                        DiagnosticKind::AliasCycle(vec!["x".to_string(),
+                                                        "y".to_string(),
+                                                        "z".to_string()])]);
+        assert_eq!(diagnostics[0].message, "`m::a as b`, `m::b as a` swap names around");
+    }
+    #[test]
+    fn prelude_aliases() {
+        let vps = [&ViewPath::from("io::Result as Result"),
+                   &ViewPath::from("a::Option as Maybe"),
+                   &ViewPath::from("a::Thing as Box"),
+                   &ViewPath::from("b::Ok")];
+        assert!(combine_with_diagnostics(&vps, Config::default()).1.is_empty());
+        let config = Config { lint_prelude_aliases: true, ..Config::default() };
+        let (_, diagnostics) = combine_with_diagnostics(&vps, config);
+        let kinds: Vec<_> = diagnostics.into_iter().map(|d| d.kind).collect();
+        // Only renames are flagged, not imports that already have the name.
+        assert_eq!(kinds, vec![DiagnosticKind::PreludeAlias("Box".to_string())]);
+    }
+    #[test]
+    fn limits() {
+        let deep = (0..200).map(|i| format!("m{}", i)).collect::<Vec<_>>().join("::");
+        let vps = [&ViewPath::from("a::b"), &ViewPath::from(&deep[..]), &ViewPath::from("a::c")];
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::b"), ViewPath::from("a::c")]);
+        let errors = combiner.try_get_import_list().unwrap_err();
+        assert_eq!(errors[0].kind, DiagnosticKind::PathTooDeep(200));
+        // A statement nested far too deep for the parser to recurse into is rejected too.
+        let nested = format!("{}x{}", "m::{n, o::".repeat(10_000), "}".repeat(10_000));
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&[&ViewPath::from(&nested[..])]);
+        let errors = combiner.try_get_import_list().unwrap_err();
+        assert_eq!(errors.iter().map(|d| &d.kind).collect::<Vec<_>>(),
+                   [&DiagnosticKind::PathTooDeep(20_001)]);
+        let config = Config { max_nodes: 3, ..Config::default() };
+        let mut combiner = ImportCombiner::with_config(config.clone());
+        combiner.add_imports(&[&ViewPath::from("a::b"),
+                               &ViewPath::from("a::c"),
+                               &ViewPath::from("a::d"),
+                               &ViewPath::from("x::y")]);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::b"), ViewPath::from("a::c")]);
+        let errors = combiner.try_get_import_list().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message,
+                   "rejected 2 imports, as there were more than 3 modules and items");
+        // An import that would take the tree past the limit is rejected, even though the tree
+        // was under it, while a repeat that adds no nodes isn't.
+        let mut combiner = ImportCombiner::with_config(config);
+        combiner.add_imports(&[&ViewPath::from("a::b"),
+                               &ViewPath::from("x::y::z"),
+                               &ViewPath::from("a::c")]);
+        combiner.add_import(&ViewPath::from("a::b"));
+        assert_eq!(combiner.metrics().nodes, 3);
+        let diagnostics = combiner.try_get_import_list().unwrap_err();
+        assert_eq!(diagnostics.iter().map(|d| &d.kind).collect::<Vec<_>>(),
+                   [&DiagnosticKind::TooManyNodes, &DiagnosticKind::DuplicatesRemoved(1)]);
+        assert_eq!(diagnostics[0].paths, vec![ViewPath::from("x::y::z")]);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::b"), ViewPath::from("a::c")]);
+    }
+    #[test]
+    fn relative_paths() {
+        let vps = [&ViewPath::from("super::x"),
+                   &ViewPath::from("super::super::y"),
+                   &ViewPath::from("super::super::z::*"),
+                   &ViewPath::from("self::a"),
+                   &ViewPath::from("x::b"),
+                   &ViewPath::from("w")];
+        let config = Config { granularity: Granularity::One, ..Config::compact() };
+        assert_eq!(combine_imports_with_config(&vps, config),
+                   vec![ViewPath::from("{w, x::b}"),
+                        ViewPath::from("self::a"),
+                        ViewPath::from("super::x"),
+                        ViewPath::from("super::super::{y, z::*}")]);
+        assert_eq!(combine_imports_with_config(&vps, Config::rustfmt_default()),
+                   vec![ViewPath::from("self::a"),
+                        ViewPath::from("super::x"),
+                        ViewPath::from("super::super::y"),
+                        ViewPath::from("super::super::z::*"),
+                        ViewPath::from("w"),
+                        ViewPath::from("x::b")]);
+    }
+    #[test]
+    fn origins() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_import_from(&ViewPath::from("log::debug"), "crate");
+        combiner.add_import_from(&ViewPath::from("log::Level"), "module");
+        combiner.add_import(&ViewPath::from("a::b"));
+        combiner.add_import_from(&ViewPath::from("log::info"), "crate");
+        combiner.add_import_from(&ViewPath::from("log::warn"), "crate");
+        combiner.add_import_from(&ViewPath::from("log::Record"), "module");
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::b"),
+                        ViewPath::from("log::Level"),
+                        ViewPath::from("log::{debug, info, warn}"),
+                        ViewPath::from("log::Record")]);
+        let diagnostics = combiner.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::AmbiguousOrigin("log".to_string()));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].paths.len(), 5);
+    }
+    #[test]
+    fn underscore_imports() {
+        let vps = [&ViewPath::from("foo::Trait as _"),
+                   &ViewPath::from("foo::Other as _"),
+                   &ViewPath::from("foo::Trait as _"),
+                   &ViewPath::from("foo::{Trait as _, Third as _}"),
+                   &ViewPath::from("bar::Trait"),
+                   &ViewPath::from("bar::Trait as _"),
+                   &ViewPath::from("baz::Trait as T"),
+                   &ViewPath::from("baz::Trait as _")];
+        assert_eq!(combine_imports(&vps),
+                   vec![ViewPath::from("bar::Trait"),
+                        ViewPath::from("baz::Trait as T"),
+                        ViewPath::from("foo::{Other as _, Third as _, Trait as _}")]);
+    }
+    #[test]
+    fn strict() {
+        let vps = [&ViewPath::from("a::b"), &ViewPath::from("a::c"), &ViewPath::from("a::b")];
+        assert_eq!(combine_imports_strict(&vps, Config::rustfmt_default()),
+                   Ok(vec![ViewPath::from("a::{b, c}")]));
+        let vps = [&ViewPath::from("a::b as x"), &ViewPath::from("c::d as x")];
+        match combine_imports_strict(&vps, Config::default()) {
+            Err(CombineError::Diagnostics(diagnostics)) => {
+                assert_eq!(diagnostics[0].kind, DiagnosticKind::NameConflict("x".to_string()))
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        let vps = [&ViewPath::from("a::*"), &ViewPath::from("a::b")];
+        match combine_imports_strict(&vps, Config::default()) {
+            Err(CombineError::Diagnostics(diagnostics)) => {
+                assert_eq!(diagnostics[0].kind, DiagnosticKind::AbortedConflict(as_path("a")))
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        for vp in &[ViewPath::from("a::::b"), ViewPath::from("a::b c")] {
+            match combine_imports_strict(&[vp], Config::default()) {
+                Err(CombineError::Malformed { ref import, .. }) => assert_eq!(import, vp),
+                result => panic!("unexpected {:?}", result),
+            }
+        }
+        assert!(combine_imports_strict(&[&ViewPath::from("::a::r#fn")], Config::default()).is_ok());
+    }
+    #[test]
+    fn with_diagnostics() {
+        let vps = [&ViewPath::from("a::b"), &ViewPath::from("a::{b, c, d}")];
+        let (imports, diagnostics) = combine_with_diagnostics(&vps, Config::default());
+        assert_eq!(imports, vec![ViewPath::from("a::{b, c, d}")]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].paths, vec![ViewPath::from("a::b")]);
+        let config = Config { strictness: Strictness::Strict, ..Config::default() };
+        let (imports, diagnostics) =
+            combine_with_diagnostics(&[&ViewPath::from("a as x"), &ViewPath::from("b as x")],
+                                     config);
+        assert_eq!(imports, vec![ViewPath::from("a as x"), ViewPath::from("b as x")]);
+        assert_eq!(diagnostics.iter().map(|d| d.severity).collect::<Vec<_>>(),
+                   vec![Severity::Error]);
+    }
+    #[test]
+    fn symbol_tables() {
+        let symbols = |module: &[String]| -> Vec<(String, Namespace)> {
+            match &module.join("::")[..] {
+                "a" => {
+                    vec![("Read".to_string(), Namespace::Type),
+                         ("x".to_string(), Namespace::Value)]
+                }
+                "b" => {
+                    vec![("Read".to_string(), Namespace::Type), ("x".to_string(), Namespace::Type)]
+                }
+                "c" => vec![("Write".to_string(), Namespace::Type)],
+                "d" => vec![("Write".to_string(), Namespace::Type)],
+                _ => vec![],
+            }
+        };
+        let vps = [&ViewPath::from("a::*"),
+                   &ViewPath::from("a::{Read, x}"),
+                   &ViewPath::from("b::*"),
+                   &ViewPath::from("c::*"),
+                   &ViewPath::from("d::*")];
+        let (imports, diagnostics) = combine_with_symbols(&vps, Config::default(), &symbols);
+        assert_eq!(imports,
+                   vec![ViewPath::from("a::*"),
+                        ViewPath::from("a::Read"),
+                        ViewPath::from("b::*"),
+                        ViewPath::from("c::*"),
+                        ViewPath::from("d::*")]);
+        let messages: Vec<_> = diagnostics.iter()
+            .filter(|d| d.kind == DiagnosticKind::KeptUnderGlob ||
+                        d.kind == DiagnosticKind::AmbiguousGlobs("Write".to_string()))
+            .map(|d| &d.message[..])
+            .collect();
+        assert_eq!(messages,
+                   vec!["kept `a::Read` alongside `a::*`, as `b::*` also imports `Read`",
+                        "`Write` is ambiguous, as it's imported by `c::*`, `d::*`"]);
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&vps);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::*"),
+                        ViewPath::from("b::*"),
+                        ViewPath::from("c::*"),
+                        ViewPath::from("d::*")]);
+    }
+    #[test]
+    fn glob_overlaps() {
+        let mut combiner = ImportCombiner::new();
+        combiner.add_imports(&[&ViewPath::from("a::b::*"),
+                               &ViewPath::from("a::b::{c, d as e, f::g}"),
+                               &ViewPath::from("a::b::h"),
+                               &ViewPath::from("x::*")]);
+        assert_eq!(combiner.diagnostics(),
+                   vec![Diagnostic {
+                            kind: DiagnosticKind::GlobOverlap(as_path("a::b")),
+                            severity: Severity::Warning,
+                            message: "`a::b::*` overlaps with the explicit imports `a::b::c`, \
+                                      `a::b::h`"
+                                .to_string(),
+                            paths: vec![ViewPath::from("a::b::*"),
+                                        ViewPath::from("a::b::c"),
+                                        ViewPath::from("a::b::h")],
+                        }]);
+    }
+    #[test]
+    fn duplicates_removed() {
+        let mut combiner = ImportCombiner::with_config(Config::rustfmt_default());
+        for _ in 0..5 {
+            combiner.add_import(&ViewPath::from("a::b as x"));
+        }
+        combiner.add_imports(&[&ViewPath::from("a::{c, d::*}"), &ViewPath::from("a::{c, d::*}")]);
+        assert_eq!(combiner.get_import_list(),
+                   vec![ViewPath::from("a::{b as x, c}"), ViewPath::from("a::d::*")]);
+        let notes: Vec<_> = combiner.diagnostics()
+            .into_iter()
+            .map(|d| (d.kind, d.severity, d.message))
+            .collect();
+        assert_eq!(notes,
+                   vec![(DiagnosticKind::DuplicatesRemoved(4),
+                         Severity::Note,
+                         "removed 4 duplicates of `a::b as x`".to_string()),
+                        (DiagnosticKind::DuplicatesRemoved(1),
+                         Severity::Note,
+                         "removed a duplicate of `a::c`".to_string()),
+                        (DiagnosticKind::DuplicatesRemoved(1),
+                         Severity::Note,
+                         "removed a duplicate of `a::d::*`".to_string())]);
+    }
 }
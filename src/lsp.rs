@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use lsp_server::{Connection, ErrorCode, ExtractError, Message, Notification, Request, Response};
+use lsp_types::{CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+                CodeActionProviderCapability, DidChangeTextDocumentParams,
+                DidCloseTextDocumentParams, DidOpenTextDocumentParams, Position, Range,
+                ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit,
+                Uri, WorkspaceEdit};
+use lsp_types::notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+                              Notification as NotificationMethod};
+use lsp_types::request::{CodeActionRequest, Request as RequestMethod};
+
+use {Config, source_edits, utf16_position};
+
+/// The title of the code action that combines a document's imports.
+pub const COMBINE_IMPORTS_TITLE: &'static str = "Combine imports";
+
+/// A minimal language server, offering a code action that combines the `use` statements of open
+/// documents. It keeps the text of each open document (the client sends all of it on every
+/// change), and edits them as `combine_source` would.
+pub struct LanguageServer {
+    config: Config,
+    documents: HashMap<Uri, String>,
+}
+
+// The LSP position of the byte `offset` in `source`.
+fn position(source: &str, offset: usize) -> Position {
+    let (line, character) = utf16_position(source, offset);
+    Position::new(line as u32, character as u32)
+}
+
+// The path of a `file:` URI, if it is one.
+#[cfg(feature = "config-file")]
+fn file_path(uri: &Uri) -> Option<::std::path::PathBuf> {
+    let path = match uri.as_str().find("://") {
+        Some(i) if &uri.as_str()[..i] == "file" => &uri.as_str()[i + 3..],
+        _ => return None,
+    };
+    // Undo percent-encoding, as of spaces.
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let decoded = if b == b'%' && tail.len() >= 2 {
+            ::std::str::from_utf8(&tail[..2]).ok().and_then(|h| u8::from_str_radix(h, 16).ok())
+        } else {
+            None
+        };
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(::std::path::PathBuf::from)
+}
+
+impl LanguageServer {
+    pub fn new(config: Config) -> LanguageServer {
+        LanguageServer {
+            config: config,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// What the server can do, to answer the client's `initialize` request with.
+    pub fn capabilities() -> ServerCapabilities {
+        ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+            ..ServerCapabilities::default()
+        }
+    }
+
+    /// Handle messages from the client until it shuts the server down. The connection must
+    /// already be initialized.
+    pub fn run(&mut self, connection: &Connection) -> Result<(), String> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request).map_err(|e| e.to_string())? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(response.into()).map_err(|e| e.to_string())?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification),
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        match request.extract::<CodeActionParams>(CodeActionRequest::METHOD) {
+            Ok((id, params)) => {
+                let result = ::serde_json::to_value(self.code_actions(&params));
+                Response::new_ok(id, result.unwrap_or(::serde_json::Value::Null))
+            }
+            Err(ExtractError::MethodMismatch(request)) => {
+                Response::new_err(id,
+                                  ErrorCode::MethodNotFound as i32,
+                                  format!("unsupported request `{}`", request.method))
+            }
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) {
+        let notification = match notification.extract::<DidOpenTextDocumentParams>(
+            DidOpenTextDocument::METHOD) {
+            Ok(params) => {
+                self.documents.insert(params.text_document.uri, params.text_document.text);
+                return;
+            }
+            Err(ExtractError::MethodMismatch(notification)) => notification,
+            Err(error) => return warn!("{}", error),
+        };
+        let notification = match notification.extract::<DidChangeTextDocumentParams>(
+            DidChangeTextDocument::METHOD) {
+            Ok(mut params) => {
+                // Without ranges, the last change is the whole of the document.
+                if let Some(change) = params.content_changes.pop() {
+                    self.documents.insert(params.text_document.uri, change.text);
+                }
+                return;
+            }
+            Err(ExtractError::MethodMismatch(notification)) => notification,
+            Err(error) => return warn!("{}", error),
+        };
+        match notification.extract::<DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD) {
+            Ok(params) => {
+                self.documents.remove(&params.text_document.uri);
+            }
+            Err(ExtractError::MethodMismatch(_)) => {}
+            Err(error) => warn!("{}", error),
+        }
+    }
+
+    // The config for a document: that of the nearest `combiner.toml` to a file, or else the
+    // server's.
+    #[cfg(feature = "config-file")]
+    fn document_config(&self, uri: &Uri) -> Config {
+        let path = match file_path(uri)
+            .and_then(|path| path.parent().and_then(::find_config_file)) {
+            Some(path) => path,
+            None => return self.config.clone(),
+        };
+        let mut config = match Config::from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("{}", e);
+                return self.config.clone();
+            }
+        };
+        match config.apply_env_overrides().and_then(|_| config.validate()) {
+            Ok(()) => config,
+            Err(e) => {
+                warn!("{}: {}", path.display(), e);
+                self.config.clone()
+            }
+        }
+    }
+
+    #[cfg(not(feature = "config-file"))]
+    fn document_config(&self, _uri: &Uri) -> Config {
+        self.config.clone()
+    }
+
+    /// The code actions for a document: just combining its imports, if any of them can be.
+    pub fn code_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let kind = CodeActionKind::SOURCE_ORGANIZE_IMPORTS;
+        if let Some(ref only) = params.context.only {
+            // The kinds requested include their sub-kinds, as `source` does `source.*`.
+            let wanted = only.iter().any(|k| {
+                kind.as_str() == k.as_str() ||
+                kind.as_str().starts_with(&format!("{}.", k.as_str()))
+            });
+            if !wanted {
+                return vec![];
+            }
+        }
+        let uri = &params.text_document.uri;
+        let source = match self.documents.get(uri) {
+            Some(source) => source,
+            None => return vec![],
+        };
+        let edits: Vec<TextEdit> = source_edits(source, &self.document_config(uri))
+            .into_iter()
+            .map(|edit| {
+                let range = Range::new(position(source, edit.start), position(source, edit.end));
+                TextEdit::new(range, edit.replacement)
+            })
+            .collect();
+        if edits.is_empty() {
+            return vec![];
+        }
+        let mut changes = HashMap::new();
+        changes.insert(uri.clone(), edits);
+        vec![CodeActionOrCommand::CodeAction(CodeAction {
+                 title: COMBINE_IMPORTS_TITLE.to_string(),
+                 kind: Some(kind),
+                 edit: Some(WorkspaceEdit::new(changes)),
+                 ..CodeAction::default()
+             })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use lsp_types::{CodeActionContext, TextDocumentIdentifier, TextDocumentItem};
+    use lsp_types::notification::Exit;
+    use lsp_types::request::Shutdown;
+
+    #[test]
+    fn code_action() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || {
+            LanguageServer::new(Config::default()).run(&server)
+        });
+        let uri: Uri = "file:///nowhere/lib.rs".parse().unwrap();
+        let text = "// é\nuse a::b;\nuse a::c;\nuse a::d;\n\nfn main() {}\n";
+        let open = DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri.clone(), "rust".to_string(), 1,
+                                                 text.to_string()),
+        };
+        client.sender
+            .send(Notification::new(DidOpenTextDocument::METHOD.to_string(), open).into())
+            .unwrap();
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier::new(uri.clone()),
+            range: Range::default(),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let request = Request::new(1.into(), CodeActionRequest::METHOD.to_string(), params);
+        client.sender.send(request.into()).unwrap();
+        let actions: Vec<CodeActionOrCommand> = match client.receiver.recv().unwrap() {
+            Message::Response(response) => {
+                ::serde_json::from_value(response.result.unwrap()).unwrap()
+            }
+            message => panic!("unexpected message {:?}", message),
+        };
+        let edit = match actions[..] {
+            [CodeActionOrCommand::CodeAction(ref action)] => {
+                assert_eq!(action.title, COMBINE_IMPORTS_TITLE);
+                action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri].clone()
+            }
+            _ => panic!("unexpected actions {:?}", actions),
+        };
+        assert_eq!(edit,
+                   vec![TextEdit::new(Range::new(Position::new(1, 0), Position::new(3, 9)),
+                                      "use a::{b, c, d};".to_string())]);
+        assert_eq!(position(text, 5), Position::new(0, 4));
+
+        let request = Request::new(2.into(), Shutdown::METHOD.to_string(), ());
+        client.sender.send(request.into()).unwrap();
+        client.receiver.recv().unwrap();
+        client.sender.send(Notification::new(Exit::METHOD.to_string(), ()).into()).unwrap();
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
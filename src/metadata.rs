@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::env;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use std::process::Command;
+
+use serde_json::{self, Value};
+
+/// A package from `cargo metadata`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CargoPackage {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<CargoTarget>,
+    pub dependencies: Vec<CargoDependency>,
+}
+
+/// A dependency of a package, which may be renamed (with `package = "..."` in `Cargo.toml`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CargoDependency {
+    pub name: String,
+    pub rename: Option<String>,
+    /// The directory of a path dependency.
+    pub path: Option<PathBuf>,
+}
+
+/// Where a crate that imports start with comes from, as far as `cargo metadata` tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CrateKind {
+    /// A package of the same workspace (including the package itself, for its binaries, tests
+    /// and examples).
+    Member,
+    /// A dependency outside the workspace given by its path, such as a vendored crate.
+    PathDependency,
+    /// A dependency from a registry or git repository.
+    External,
+}
+
+/// A build target (lib, bin, example, test, bench or build script) of a package.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CargoTarget {
+    pub name: String,
+    pub kind: Vec<String>,
+    pub src_path: PathBuf,
+}
+
+impl CargoPackage {
+    pub fn directory(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// The names the crates of this package and its dependencies have in code, keyed by package
+    /// name, for use as `Config::crate_names`.
+    pub fn crate_names(&self) -> BTreeMap<String, String> {
+        let mut names: BTreeMap<String, String> = self.dependencies
+            .iter()
+            .map(|d| (d.name.clone(), d.rename.clone().unwrap_or(d.name.replace('-', "_"))))
+            .collect();
+        if let Some(lib) = self.targets.iter().find(|t| t.kind.iter().any(|k| k == "lib")) {
+            names.insert(self.name.clone(), lib.name.replace('-', "_"));
+        }
+        names
+    }
+
+    /// The kinds of the crates of this package and its dependencies, keyed by their names in
+    /// code, for use as `Config::crate_kinds`. `workspace` is the workspace's members, as
+    /// returned by `cargo_metadata`.
+    pub fn crate_kinds(&self, workspace: &[CargoPackage]) -> BTreeMap<String, CrateKind> {
+        let names = self.crate_names();
+        let mut kinds: BTreeMap<String, CrateKind> = self.dependencies
+            .iter()
+            .map(|d| {
+                let kind = if workspace.iter().any(|p| p.name == d.name) {
+                    CrateKind::Member
+                } else if d.path.is_some() {
+                    CrateKind::PathDependency
+                } else {
+                    CrateKind::External
+                };
+                (names[&d.name].clone(), kind)
+            })
+            .collect();
+        if let Some(name) = names.get(&self.name) {
+            kinds.insert(name.clone(), CrateKind::Member);
+        }
+        kinds
+    }
+}
+
+/// The package of `workspace` whose directory most closely contains `path`.
+pub fn containing_package<'a>(workspace: &'a [CargoPackage],
+                              path: &Path)
+                              -> Option<&'a CargoPackage> {
+    workspace.iter()
+        .filter(|p| path.starts_with(p.directory()))
+        .max_by_key(|p| p.directory().components().count())
+}
+
+/// Run `cargo metadata` (without dependencies) for the workspace containing `manifest_path`, or
+/// the current directory, returning the workspace members.
+#[cfg(feature = "fs")]
+pub fn cargo_metadata(manifest_path: Option<&Path>) -> Result<Vec<CargoPackage>, String> {
+    let cargo = env::var("CARGO").unwrap_or("cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.args(&["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+    let output = command.output().map_err(|e| format!("couldn't run cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("cargo metadata failed: {}",
+                           String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    parse_metadata(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the JSON output of `cargo metadata --format-version 1`.
+pub fn parse_metadata(json: &str) -> Result<Vec<CargoPackage>, String> {
+    fn string(value: &Value, key: &str) -> Result<String, String> {
+        value[key].as_str().map(String::from).ok_or(format!("missing `{}` in cargo metadata", key))
+    }
+    let metadata: Value = serde_json::from_str(json)
+        .map_err(|e| format!("invalid cargo metadata: {}", e))?;
+    let mut packages = vec![];
+    for package in metadata["packages"].as_array().map_or(&[][..], |p| &p[..]) {
+        let mut targets = vec![];
+        for target in package["targets"].as_array().map_or(&[][..], |t| &t[..]) {
+            targets.push(CargoTarget {
+                name: string(target, "name")?,
+                kind: target["kind"]
+                    .as_array()
+                    .map_or(vec![], |kinds| {
+                        kinds.iter().filter_map(|k| k.as_str()).map(String::from).collect()
+                    }),
+                src_path: PathBuf::from(string(target, "src_path")?),
+            });
+        }
+        let mut dependencies = vec![];
+        for dependency in package["dependencies"].as_array().map_or(&[][..], |d| &d[..]) {
+            dependencies.push(CargoDependency {
+                name: string(dependency, "name")?,
+                rename: dependency["rename"].as_str().map(String::from),
+                path: dependency["path"].as_str().map(PathBuf::from),
+            });
+        }
+        packages.push(CargoPackage {
+            name: string(package, "name")?,
+            manifest_path: PathBuf::from(string(package, "manifest_path")?),
+            targets: targets,
+            dependencies: dependencies,
+        });
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        let json = r#"{"packages": [{
+            "name": "foo",
+            "manifest_path": "/ws/foo/Cargo.toml",
+            "dependencies": [{"name": "serde-json", "rename": null},
+                             {"name": "other-thing", "rename": "thing"},
+                             {"name": "vendored", "rename": null, "path": "/vendor/vendored"},
+                             {"name": "bar", "rename": null, "path": "/ws/bar"}],
+            "targets": [{"name": "foo", "kind": ["lib"], "src_path": "/ws/foo/src/lib.rs"},
+                        {"name": "build-script-build", "kind": ["custom-build"],
+                         "src_path": "/ws/foo/build.rs"}]
+        }]}"#;
+        let packages = parse_metadata(json).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].directory(), Path::new("/ws/foo"));
+        assert_eq!(packages[0].targets[1],
+                   CargoTarget {
+                       name: "build-script-build".to_string(),
+                       kind: vec!["custom-build".to_string()],
+                       src_path: PathBuf::from("/ws/foo/build.rs"),
+                   });
+        let names: Vec<_> = packages[0].crate_names().into_iter().collect();
+        assert_eq!(names,
+                   vec![("bar".to_string(), "bar".to_string()),
+                        ("foo".to_string(), "foo".to_string()),
+                        ("other-thing".to_string(), "thing".to_string()),
+                        ("serde-json".to_string(), "serde_json".to_string()),
+                        ("vendored".to_string(), "vendored".to_string())]);
+        let workspace = vec![packages[0].clone(),
+                             CargoPackage {
+                                 name: "bar".to_string(),
+                                 manifest_path: PathBuf::from("/ws/bar/Cargo.toml"),
+                                 targets: vec![],
+                                 dependencies: vec![],
+                             }];
+        let kinds: Vec<_> = packages[0].crate_kinds(&workspace).into_iter().collect();
+        assert_eq!(kinds,
+                   vec![("bar".to_string(), CrateKind::Member),
+                        ("foo".to_string(), CrateKind::Member),
+                        ("serde_json".to_string(), CrateKind::External),
+                        ("thing".to_string(), CrateKind::External),
+                        ("vendored".to_string(), CrateKind::PathDependency)]);
+        let package = containing_package(&workspace, Path::new("/ws/bar/src/lib.rs"));
+        assert_eq!(package.map(|p| &p.name[..]), Some("bar"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::default::Default;
+use std::str::FromStr;
+
+/// How sibling names are ordered within the combined output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ItemOrdering {
+    /// Plain byte-wise ordering, so all uppercase names sort before lowercase ones.
+    Lexicographic,
+    /// Ordering that ignores ASCII case, falling back to byte-wise ordering for ties.
+    CaseInsensitive,
+    /// rustfmt-style version sort: case-insensitive, with runs of digits compared numerically
+    /// so that `u8` < `u16` < `u128`.
+    VersionSort,
+    /// Module-like names (starting with a lowercase letter or underscore) come before type-like
+    /// names (starting with an uppercase letter), each group ordered byte-wise, e.g.
+    /// `{fmt, io, Read, Write}`.
+    ModulesFirst,
+}
+
+impl ItemOrdering {
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            ItemOrdering::Lexicographic => a.cmp(b),
+            ItemOrdering::CaseInsensitive => {
+                case_insensitive_compare(a, b).then_with(|| a.cmp(b))
+            }
+            ItemOrdering::VersionSort => version_compare(a, b).then_with(|| a.cmp(b)),
+            ItemOrdering::ModulesFirst => {
+                is_type_like(a).cmp(&is_type_like(b)).then_with(|| a.cmp(b))
+            }
+        }
+    }
+}
+
+impl Default for ItemOrdering {
+    fn default() -> ItemOrdering {
+        ItemOrdering::Lexicographic
+    }
+}
+
+impl FromStr for ItemOrdering {
+    type Err = String;
+    fn from_str(s: &str) -> Result<ItemOrdering, String> {
+        match &s.to_lowercase().replace("_", "")[..] {
+            "lexicographic" => Ok(ItemOrdering::Lexicographic),
+            "caseinsensitive" => Ok(ItemOrdering::CaseInsensitive),
+            "versionsort" => Ok(ItemOrdering::VersionSort),
+            "modulesfirst" => Ok(ItemOrdering::ModulesFirst),
+            _ => Err(format!("unknown item ordering `{}`", s)),
+        }
+    }
+}
+
+/// Where `self` (and `self as x`) entries are placed within a brace list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SelfPosition {
+    /// `a::{self, b, c}`
+    First,
+    /// `a::{b, c, self}`
+    Last,
+    /// `self` is ordered like any other name, e.g. `a::{b, self, t}`
+    Sorted,
+}
+
+impl Default for SelfPosition {
+    fn default() -> SelfPosition {
+        SelfPosition::First
+    }
+}
+
+fn is_type_like(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| c.is_uppercase())
+}
+
+fn case_insensitive_compare(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .map(|c| c.to_ascii_lowercase())
+        .cmp(b.chars().map(|c| c.to_ascii_lowercase()))
+}
+
+fn version_compare(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        let (ca, cb) = match (a_chars.peek().cloned(), b_chars.peek().cloned()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) => (ca, cb),
+        };
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let da = take_digits(&mut a_chars);
+            let db = take_digits(&mut b_chars);
+            // Compare by value first (longer runs without leading zeros are bigger), then put
+            // the run with fewer leading zeros first.
+            let va = da.trim_start_matches('0');
+            let vb = db.trim_start_matches('0');
+            let order = va.len()
+                .cmp(&vb.len())
+                .then_with(|| va.cmp(vb))
+                .then_with(|| db.len().cmp(&da.len()));
+            if order != Ordering::Equal {
+                return order;
+            }
+        } else {
+            let order = ca.to_ascii_lowercase().cmp(&cb.to_ascii_lowercase());
+            if order != Ordering::Equal {
+                return order;
+            }
+            a_chars.next();
+            b_chars.next();
+        }
+    }
+}
+
+fn take_digits<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().cloned() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn sorted(ordering: ItemOrdering, names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_by(|a, b| ordering.compare(a, b));
+        names
+    }
+    #[test]
+    fn orderings() {
+        let names = ["b", "Zed", "a", "u16", "u8", "U128"];
+        assert_eq!(sorted(ItemOrdering::Lexicographic, &names),
+                   vec!["U128", "Zed", "a", "b", "u16", "u8"]);
+        assert_eq!(sorted(ItemOrdering::CaseInsensitive, &names),
+                   vec!["a", "b", "U128", "u16", "u8", "Zed"]);
+        assert_eq!(sorted(ItemOrdering::ModulesFirst, &names),
+                   vec!["a", "b", "u16", "u8", "U128", "Zed"]);
+        assert_eq!(sorted(ItemOrdering::VersionSort, &names),
+                   vec!["a", "b", "u8", "u16", "U128", "Zed"]);
+    }
+}
@@ -0,0 +1,64 @@
+use {UseBlock, ViewPath};
+use source::find_blocks;
+
+/// Parses source files into the `use` statements the combiner works on. The combiner only ever
+/// sees the `ViewPath`s and spans a backend gives it, so one built on a real parser (such as
+/// syn, or tree-sitter) can replace the `StringSplitter` the source functions use by default,
+/// for code the splitter misreads.
+pub trait ParserBackend {
+    /// Parse the body of a `use` statement (without `use` and the `;`), or give `None` if it
+    /// isn't one the backend understands.
+    fn parse_statement(&self, statement: &str) -> Option<ViewPath>;
+
+    /// Find the blocks of `use` statements in `source` that can be combined, with the span of
+    /// each statement, leaving out those in `#[cfg(test)]` modules unless
+    /// `include_test_modules`, and including `extern crate` items (as `use` statements) if
+    /// `extern_crates`. By default, the lines of `source` are scanned for statements, which
+    /// are parsed with `parse_statement`, so a backend only has to parse statements.
+    fn parse_file(&self,
+                  source: &str,
+                  include_test_modules: bool,
+                  extern_crates: bool)
+                  -> Vec<UseBlock> {
+        find_blocks(source, include_test_modules, extern_crates, self)
+    }
+}
+
+/// The combiner's own parser, which splits statements on `::`, commas and braces, and finds
+/// them by scanning lines. It needs no dependencies, and accepts every statement.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StringSplitter;
+
+impl ParserBackend for StringSplitter {
+    fn parse_statement(&self, statement: &str) -> Option<ViewPath> {
+        Some(ViewPath::from(statement))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Config, combine_source, combine_source_with_parser, find_use_blocks};
+    // A parser that doesn't understand macro-generated paths, such as `$crate::a`.
+    struct NoMacros;
+    impl ParserBackend for NoMacros {
+        fn parse_statement(&self, statement: &str) -> Option<ViewPath> {
+            if statement.contains('$') {
+                None
+            } else {
+                StringSplitter.parse_statement(statement)
+            }
+        }
+    }
+    #[test]
+    fn backends() {
+        let source = "use a::b;\nuse a::c;\nuse $crate::d;\nuse a::e;\n";
+        assert_eq!(StringSplitter.parse_file(source, true, false), find_use_blocks(source));
+        let blocks = NoMacros.parse_file(source, true, false);
+        assert_eq!(blocks.iter().map(|b| b.statements.len()).collect::<Vec<_>>(), vec![2, 1]);
+        let config = Config::rustfmt_default();
+        assert_eq!(combine_source(source, &config), "use $crate::d;\nuse a::{b, c, e};\n");
+        assert_eq!(combine_source_with_parser(source, &config, &NoMacros),
+                   "use a::{b, c};\nuse $crate::d;\nuse a::e;\n");
+    }
+}
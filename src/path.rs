@@ -0,0 +1,159 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+use smallvec::SmallVec;
+
+// Most paths have no more than this many segments, so they're kept inline.
+const INLINE_SEGMENTS: usize = 4;
+
+/// The segments of a path, such as `std::collections::BTreeMap`. Short paths (the great majority)
+/// are stored inline rather than in a separate allocation. It derefs to a `SmallVec`, and from
+/// there to a slice, so it's used much like a `Vec<String>`.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(SmallVec<[String; INLINE_SEGMENTS]>);
+
+impl Path {
+    pub fn new() -> Path {
+        Path(SmallVec::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Path {
+        Path(SmallVec::with_capacity(capacity))
+    }
+
+    /// The number of bytes the path has allocated on the heap, for its segments and (if it's too
+    /// long to be kept inline) for the list of them.
+    pub fn heap_bytes(&self) -> usize {
+        let list = if self.0.spilled() {
+            self.0.capacity() * mem::size_of::<String>()
+        } else {
+            0
+        };
+        list + self.0.iter().map(|s| s.capacity()).sum::<usize>()
+    }
+}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+// A path is serialized as the list of its segments.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Path {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Path {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Path, D::Error> {
+        Vec::<String>::deserialize(deserializer).map(Path::from)
+    }
+}
+
+impl Deref for Path {
+    type Target = SmallVec<[String; INLINE_SEGMENTS]>;
+    fn deref(&self) -> &SmallVec<[String; INLINE_SEGMENTS]> {
+        &self.0
+    }
+}
+
+impl DerefMut for Path {
+    fn deref_mut(&mut self) -> &mut SmallVec<[String; INLINE_SEGMENTS]> {
+        &mut self.0
+    }
+}
+
+impl Borrow<[String]> for Path {
+    fn borrow(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<Vec<String>> for Path {
+    fn from(segments: Vec<String>) -> Path {
+        Path(SmallVec::from_vec(segments))
+    }
+}
+
+impl From<String> for Path {
+    fn from(segment: String) -> Path {
+        let mut path = Path::new();
+        path.push(segment);
+        path
+    }
+}
+
+impl<'a> From<&'a [String]> for Path {
+    fn from(segments: &[String]) -> Path {
+        segments.iter().cloned().collect()
+    }
+}
+
+impl FromIterator<String> for Path {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Path {
+        Path(iter.into_iter().collect())
+    }
+}
+
+impl Extend<String> for Path {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for Path {
+    type Item = String;
+    type IntoIter = ::smallvec::IntoIter<[String; INLINE_SEGMENTS]>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Path {
+    type Item = &'a String;
+    type IntoIter = ::std::slice::Iter<'a, String>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl PartialEq<Vec<String>> for Path {
+    fn eq(&self, other: &Vec<String>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl PartialEq<Path> for Vec<String> {
+    fn eq(&self, other: &Path) -> bool {
+        self[..] == other[..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use as_path;
+    #[test]
+    fn paths() {
+        let short = as_path("a::b::c");
+        assert!(!short.spilled());
+        assert_eq!(short, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(format!("{:?}", short), r#"["a", "b", "c"]"#);
+        let mut long = short.clone();
+        long.extend(as_path("d::e"));
+        assert!(long.spilled());
+        assert_eq!(long.join("::"), "a::b::c::d::e");
+        assert_eq!(Path::from(&long[..3]), short);
+        assert_eq!(short.heap_bytes(), 3);
+        assert!(long.heap_bytes() >= 5 + 5 * ::std::mem::size_of::<String>());
+        long.truncate(3);
+        assert_eq!(long, short);
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use {Path, ViewPath, path_segments};
+
+/// A pattern over `::`-separated paths, used to pick out imports that need special treatment.
+///
+/// Each pattern segment either matches a path segment literally, or is a wildcard:
+///
+/// * `*` matches exactly one segment (including the `*` of a glob import), and
+/// * `**` matches any number of segments, including none.
+///
+/// So `*::prelude::*` matches `diesel::prelude::*` and `std::prelude::Vec`, and `a::**` matches
+/// `a`, `a::b` and `a::b::c::*`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPattern {
+    segments: Vec<String>,
+}
+
+impl PathPattern {
+    /// Whether the pattern matches `segments`, where a trailing `*` segment denotes a glob.
+    pub fn matches_segments(&self, segments: &[String]) -> bool {
+        fn matches(pattern: &[String], segments: &[String]) -> bool {
+            match pattern.first().map(|s| s.as_str()) {
+                None => segments.is_empty(),
+                Some("**") => {
+                    (0..segments.len() + 1).any(|n| matches(&pattern[1..], &segments[n..]))
+                }
+                Some(p) => {
+                    !segments.is_empty() && (p == "*" || p == segments[0]) &&
+                    matches(&pattern[1..], &segments[1..])
+                }
+            }
+        }
+        matches(&self.segments, segments)
+    }
+
+    /// Whether the pattern matches the import. List imports match if any of their items does.
+    pub fn matches(&self, vp: &ViewPath) -> bool {
+        fn with_last(p: &Path, last: &str) -> Path {
+            let mut segments = p.clone();
+            segments.push(last.to_string());
+            segments
+        }
+        match vp {
+            &ViewPath::ViewPathSimple(ref p, _) => self.matches_segments(p),
+            &ViewPath::ViewPathGlob(ref p) => self.matches_segments(&with_last(p, "*")),
+            &ViewPath::ViewPathList(ref p, ref items) => {
+                items.iter().any(|i| if i.0 == "self" {
+                    self.matches_segments(p)
+                } else {
+                    self.matches_segments(&with_last(p, &i.0))
+                })
+            }
+            &ViewPath::ViewPathNested(..) => vp.flatten().iter().any(|vp| self.matches(vp)),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for PathPattern {
+    fn from(s: &str) -> PathPattern {
+        PathPattern { segments: path_segments(s.trim()).map(|s| s.trim().to_string()).collect() }
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.segments.join("::"))
+    }
+}
+
+// Patterns are (de)serialized as their `::`-separated text.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for PathPattern {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for PathPattern {
+    fn deserialize<D>(deserializer: D) -> Result<PathPattern, D::Error>
+        where D: ::serde::Deserializer<'de>
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(PathPattern::from(&s[..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ViewPath;
+    #[test]
+    fn wildcards() {
+        let prelude = PathPattern::from("*::prelude::*");
+        assert!(prelude.matches(&ViewPath::from("diesel::prelude::*")));
+        assert!(prelude.matches(&ViewPath::from("std::prelude::Vec")));
+        assert!(!prelude.matches(&ViewPath::from("a::diesel::prelude::*")));
+        assert!(!prelude.matches(&ViewPath::from("diesel::prelude")));
+
+        let deep = PathPattern::from("a::**");
+        assert!(deep.matches(&ViewPath::from("a")));
+        assert!(deep.matches(&ViewPath::from("a::b::c::*")));
+        assert!(!deep.matches(&ViewPath::from("b::a")));
+
+        let item = PathPattern::from("a::b::c");
+        assert!(item.matches(&ViewPath::from("a::b::{d, c as x}")));
+        assert!(!item.matches(&ViewPath::from("a::b::{d, e}")));
+    }
+}
@@ -0,0 +1,61 @@
+use syn::{self, File, Item, ItemUse};
+
+use ViewPath;
+
+/// An import as a `syn` item: a `use` statement, preceded by `visibility` (e.g. `pub `, or
+/// nothing).
+pub fn use_item(vp: &ViewPath, visibility: &str) -> syn::Result<ItemUse> {
+    syn::parse_str(&format!("{}use {};", visibility, vp))
+}
+
+/// Render imports as statements formatted by prettyplease, which lays them out as rustfmt does,
+/// wrapping lists too long for one line. Each line starts with `indent`. prettyplease wraps
+/// lines at 100 columns before the indent is added, so nested statements can be a little longer.
+pub fn pretty_print_statements(imports: &[&ViewPath],
+                               indent: &str,
+                               visibility: &str)
+                               -> syn::Result<String> {
+    let mut items = vec![];
+    for vp in imports {
+        items.push(Item::Use(use_item(vp, visibility)?));
+    }
+    let file = File {
+        shebang: None,
+        attrs: vec![],
+        items: items,
+    };
+    Ok(::prettyplease::unparse(&file)
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("{}{}\n", indent, line)
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Config, combine_source};
+    #[test]
+    fn pretty_printing() {
+        let vp = ViewPath::from("a::{b, c::{d, e}}");
+        assert_eq!(pretty_print_statements(&[&vp], "    ", "pub(crate) ").unwrap(),
+                   "    pub(crate) use a::{b, c::{d, e}};\n");
+
+        let names: Vec<String> = (0..16).map(|i| format!("item_number_{}", i)).collect();
+        let source: String = names.iter().map(|name| format!("use a::{};\n", name)).collect();
+        let config = Config { pretty_print: true, ..Config::default() };
+        let combined = combine_source(&source, &config);
+        assert_eq!(combined,
+                   "use a::{\n    item_number_0, item_number_1, item_number_10, item_number_11, \
+                    item_number_12,\n    item_number_13, item_number_14, item_number_15, \
+                    item_number_2, item_number_3,\n    item_number_4, item_number_5, \
+                    item_number_6, item_number_7, item_number_8,\n    item_number_9,\n};\n");
+        // The wrapped statement is left alone once it's been combined.
+        assert_eq!(combine_source(&combined, &config), combined);
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::Value;
+
+use {Config, Diagnostic, ViewPath, combine_with_diagnostics};
+
+/// A config, for Python: `Config(preset=None, **options)`, with the options named as in config
+/// files and given as their JSON values, e.g. `Config("rustfmt", min_list_length=2)`.
+#[pyclass(name = "Config", module = "rustfmt_combiner", frozen)]
+pub struct PyConfig {
+    config: Config,
+}
+
+// A config error, raised as a `ValueError`.
+fn value_error<E: ToString>(error: E) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn checked(config: Config) -> PyResult<PyConfig> {
+    config.validate().map_err(value_error)?;
+    Ok(PyConfig { config: config })
+}
+
+#[pymethods]
+impl PyConfig {
+    #[new]
+    #[pyo3(signature = (preset = None, **options))]
+    fn new(py: Python,
+           preset: Option<&str>,
+           options: Option<&Bound<PyDict>>)
+           -> PyResult<PyConfig> {
+        let config = match preset {
+            Some(name) => {
+                Config::preset(name)
+                    .ok_or_else(|| value_error(format!("unknown preset `{}`", name)))?
+            }
+            None => Config::default(),
+        };
+        let options = match options {
+            Some(options) => options,
+            None => return checked(config),
+        };
+        // The options replace the preset's in its JSON form, so that they're checked by the same
+        // deserializer as the configs the `wasm` feature takes.
+        let json: String = py.import("json")?.call_method1("dumps", (options,))?.extract()?;
+        let options: Value = ::serde_json::from_str(&json).map_err(value_error)?;
+        let mut value = ::serde_json::to_value(&config).map_err(value_error)?;
+        if let (Some(fields), Some(options)) = (value.as_object_mut(), options.as_object()) {
+            for (name, option) in options {
+                if !fields.contains_key(name) {
+                    return Err(value_error(format!("unknown option `{}`", name)));
+                }
+                fields.insert(name.clone(), option.clone());
+            }
+        }
+        checked(::serde_json::from_value(value).map_err(value_error)?)
+    }
+
+    /// Parse a config from TOML, as in a `combiner.toml` file.
+    #[staticmethod]
+    fn from_toml(toml: &str) -> PyResult<PyConfig> {
+        checked(Config::from_toml(toml).map_err(value_error)?)
+    }
+
+    /// Load a config file, such as `combiner.toml`.
+    #[staticmethod]
+    fn from_file(path: PathBuf) -> PyResult<PyConfig> {
+        checked(Config::from_file(&path).map_err(value_error)?)
+    }
+
+    /// The config as JSON, with every option.
+    fn to_json(&self) -> PyResult<String> {
+        ::serde_json::to_string(&self.config).map_err(value_error)
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Config(**{})", self.to_json()?))
+    }
+}
+
+/// A diagnostic, for Python. `start` and `end` are the byte range of the statement involved,
+/// for diagnostics about a source file, and are `None` for ones about a list of imports.
+#[pyclass(name = "Diagnostic", module = "rustfmt_combiner", frozen, get_all)]
+pub struct PyDiagnostic {
+    kind: &'static str,
+    severity: String,
+    message: String,
+    help: Option<String>,
+    paths: Vec<String>,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+impl PyDiagnostic {
+    fn new(diagnostic: &Diagnostic, span: Option<(usize, usize)>) -> PyDiagnostic {
+        PyDiagnostic {
+            kind: diagnostic.kind.name(),
+            severity: diagnostic.severity.to_string(),
+            message: diagnostic.message.clone(),
+            help: diagnostic.help(),
+            paths: diagnostic.paths.iter().map(|vp| vp.to_string()).collect(),
+            start: span.map(|(start, _)| start),
+            end: span.map(|(_, end)| end),
+        }
+    }
+}
+
+#[pymethods]
+impl PyDiagnostic {
+    fn __repr__(&self) -> String {
+        format!("<Diagnostic {} {}: {}>", self.kind, self.severity, self.message)
+    }
+
+    fn __str__(&self) -> String {
+        format!("{}: {}", self.severity, self.message)
+    }
+}
+
+fn config_or_default(config: Option<PyRef<PyConfig>>) -> Config {
+    config.map_or_else(Config::default, |config| config.config.clone())
+}
+
+/// Combine imports, given as paths such as `a::b::{c, d}` (without `use` or `;`), into as few
+/// as they can be. Returns the combined imports and the diagnostics about them.
+#[pyfunction]
+#[pyo3(signature = (imports, config = None))]
+fn combine_imports(imports: Vec<String>,
+                   config: Option<PyRef<PyConfig>>)
+                   -> (Vec<String>, Vec<PyDiagnostic>) {
+    let vps: Vec<ViewPath> = imports.iter().map(|import| ViewPath::from(&import[..])).collect();
+    let vps: Vec<&ViewPath> = vps.iter().collect();
+    let (combined, diagnostics) = combine_with_diagnostics(&vps, config_or_default(config));
+    (combined.iter().map(|vp| vp.to_string()).collect(),
+     diagnostics.iter().map(|d| PyDiagnostic::new(d, None)).collect())
+}
+
+/// Combine the `use` statements in the source of a file.
+#[pyfunction]
+#[pyo3(signature = (source, config = None))]
+fn combine_source(source: &str, config: Option<PyRef<PyConfig>>) -> String {
+    ::combine_source(source, &config_or_default(config))
+}
+
+/// The diagnostics about the `use` statements in the source of a file.
+#[pyfunction]
+#[pyo3(signature = (source, config = None))]
+fn source_diagnostics(source: &str, config: Option<PyRef<PyConfig>>) -> Vec<PyDiagnostic> {
+    ::source_diagnostics(source, &config_or_default(config))
+        .iter()
+        .map(|d| PyDiagnostic::new(&d.diagnostic, Some((d.start, d.end))))
+        .collect()
+}
+
+/// Combine the `use` statements of a file in place. Returns whether it was changed.
+#[pyfunction]
+#[pyo3(signature = (path, config = None))]
+fn combine_file(path: PathBuf, config: Option<PyRef<PyConfig>>) -> PyResult<bool> {
+    ::combine_file(&path, &config_or_default(config))
+        .map_err(|e| PyOSError::new_err(format!("{}: {}", path.display(), e)))
+}
+
+/// The `rustfmt_combiner` Python module.
+#[pymodule]
+fn rustfmt_combiner(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_class::<PyConfig>()?;
+    module.add_class::<PyDiagnostic>()?;
+    module.add_function(wrap_pyfunction!(self::combine_imports, module)?)?;
+    module.add_function(wrap_pyfunction!(self::combine_source, module)?)?;
+    module.add_function(wrap_pyfunction!(self::source_diagnostics, module)?)?;
+    module.add_function(wrap_pyfunction!(self::combine_file, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use pyo3::types::PyModule;
+    #[test]
+    fn python_module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "rustfmt_combiner").unwrap();
+            rustfmt_combiner(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("rc", module).unwrap();
+            let code = CString::new(r#"
+config = rc.Config("rustfmt", min_list_length=2)
+imports, diagnostics = rc.combine_imports(["a::b", "a::c", "d::e as x", "f::g as x"], config)
+assert imports == ["a::{b, c}", "d::e as x", "f::g as x"], imports
+assert [(d.kind, d.severity) for d in diagnostics] == [("name_conflict", "warning")], diagnostics
+assert diagnostics[0].start is None
+
+source = "use a::b;\nuse a::c;\nuse a::d;\n"
+assert rc.combine_source(source) == "use a::{b, c, d};\n"
+[d] = rc.source_diagnostics("use a::b as x;\nuse c::d as x;\n")
+assert (d.start, d.end, d.paths) == (0, 14, ["a::b as x", "c::d as x"]), d
+
+for bad in [lambda: rc.Config(min_list_length=0), lambda: rc.Config(nonsense=1),
+            lambda: rc.Config("nonsense")]:
+    try:
+        bad()
+        assert False
+    except ValueError:
+        pass
+"#)
+                .unwrap();
+            if let Err(e) = py.run(&code, Some(&globals), None) {
+                panic!("{}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,99 @@
+use ra_ap_syntax::AstNode;
+use ra_ap_syntax::ast::{self, HasName};
+
+use {Item, Path, ViewPath};
+
+/// The import of a `use` item in a rust-analyzer syntax tree, or `None` if the tree is
+/// incomplete (as it is while the item's being typed).
+pub fn view_path_from_use(item: &ast::Use) -> Option<ViewPath> {
+    item.use_tree().and_then(|tree| view_path_from_use_tree(&tree))
+}
+
+/// The import of a `UseTree` node (the body of a `use` item, or one of the trees in a brace
+/// list), shaped as the combiner's own parser would give it for the same text.
+pub fn view_path_from_use_tree(tree: &ast::UseTree) -> Option<ViewPath> {
+    let mut path = match tree.path() {
+        Some(path) => ast_path(&path)?,
+        None => Path::new(),
+    };
+    // `::*` and `::{a, b}` have a leading `::` without a path.
+    if path.is_empty() && tree.coloncolon_token().is_some() {
+        path.push(String::new());
+    }
+    if tree.star_token().is_some() {
+        return Some(ViewPath::ViewPathGlob(path));
+    }
+    let list = match tree.use_tree_list() {
+        Some(list) => list,
+        None if path.is_empty() => return None,
+        None => return Some(ViewPath::ViewPathSimple(path, rename(tree))),
+    };
+    let trees: Vec<ast::UseTree> = list.use_trees().collect();
+    // Lists of more than names are nested.
+    let items: Option<Vec<Item>> = trees.iter()
+        .map(|tree| match view_path_from_use_tree(tree) {
+            Some(ViewPath::ViewPathSimple(ref p, ref rename)) if p.len() == 1 => {
+                Some(Item(p[0].clone(), rename.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    match items {
+        Some(ref items) if items.len() == 1 && items[0].0 == "self" => {
+            Some(ViewPath::ViewPathSimple(path, items[0].1.clone()))
+        }
+        Some(items) => Some(ViewPath::ViewPathList(path, items)),
+        None => {
+            let nested = trees.iter().map(view_path_from_use_tree).collect::<Option<_>>()?;
+            Some(ViewPath::ViewPathNested(path, nested))
+        }
+    }
+}
+
+// The segments of a path, with an empty first segment for a leading `::`.
+fn ast_path(path: &ast::Path) -> Option<Path> {
+    let mut segments = vec![];
+    let mut next = Some(path.clone());
+    while let Some(path) = next {
+        let segment = path.segment()?;
+        segments.push(segment.name_ref()?.syntax().text().to_string());
+        next = path.qualifier();
+        if next.is_none() && segment.coloncolon_token().is_some() {
+            segments.push(String::new());
+        }
+    }
+    segments.reverse();
+    Some(Path::from(segments))
+}
+
+// The name a use tree is renamed to (`_` included), if it is.
+fn rename(tree: &ast::UseTree) -> Option<String> {
+    let rename = tree.rename()?;
+    match rename.underscore_token() {
+        Some(_) => Some("_".to_string()),
+        None => rename.name().map(|name| name.syntax().text().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ra_ap_syntax::{Edition, SourceFile};
+    #[test]
+    fn use_trees() {
+        let source = "use a::{self, b::{c, d as e}, f::*};\nuse ::g::h as _;\nuse i::{j, k};\n\
+                      use l::{self as m};\nuse super::super::n;\nuse crate::{o, p::*};\nuse q::";
+        let parse = SourceFile::parse(source, Edition::CURRENT);
+        let imports: Vec<_> = parse.tree()
+            .syntax()
+            .descendants()
+            .filter_map(ast::Use::cast)
+            .map(|item| view_path_from_use(&item))
+            .collect();
+        let expected: Vec<_> = source.lines()
+            .map(|line| line.trim_start_matches("use ").trim_end_matches(';'))
+            .map(|body| if body.ends_with("::") { None } else { Some(ViewPath::from(body)) })
+            .collect();
+        assert_eq!(imports, expected);
+    }
+}
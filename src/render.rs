@@ -0,0 +1,290 @@
+use std::fmt;
+
+use {Config, CrateKind, Edition, GroupImports, Item, ViewPath};
+use pattern::PathPattern;
+
+/// A named group of imports, emitted as its own blank-line separated block.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImportGroup {
+    pub name: String,
+    /// An import belongs to the first group with a pattern matching it.
+    pub patterns: Vec<PathPattern>,
+}
+
+impl ImportGroup {
+    pub fn new(name: &str, patterns: &[&str]) -> ImportGroup {
+        ImportGroup {
+            name: name.to_string(),
+            patterns: patterns.iter().map(|p| PathPattern::from(*p)).collect(),
+        }
+    }
+
+    pub fn matches(&self, vp: &ViewPath) -> bool {
+        self.patterns.iter().any(|p| p.matches(vp))
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Some(ref rename) => write!(f, "{} as {}", self.0, rename),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl fmt::Display for ViewPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_prefix(f: &mut fmt::Formatter, p: &[String]) -> fmt::Result {
+            for segment in p {
+                write!(f, "{}::", segment)?;
+            }
+            Ok(())
+        }
+        match self {
+            &ViewPath::ViewPathSimple(ref p, ref rename) => {
+                write!(f, "{}", p.join("::"))?;
+                match rename {
+                    &Some(ref rename) => write!(f, " as {}", rename),
+                    &None => Ok(()),
+                }
+            }
+            &ViewPath::ViewPathGlob(ref p) => {
+                write_prefix(f, p)?;
+                write!(f, "*")
+            }
+            &ViewPath::ViewPathList(ref p, ref items) => {
+                write_prefix(f, p)?;
+                let items: Vec<_> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "{{{}}}", items.join(", "))
+            }
+            &ViewPath::ViewPathNested(ref p, ref nested) => {
+                write_prefix(f, p)?;
+                let nested: Vec<_> = nested.iter().map(|vp| vp.to_string()).collect();
+                write!(f, "{{{}}}", nested.join(", "))
+            }
+        }
+    }
+}
+/// An import that can't be written as it is in the configured edition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditionError {
+    pub import: ViewPath,
+    pub edition: Edition,
+    pub message: String,
+}
+
+impl fmt::Display for EditionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`use {};` {}", self.import, self.message)
+    }
+}
+
+// Why `vp` can't be written in `edition`, if it can't.
+fn edition_problem(vp: &ViewPath, edition: Edition) -> Option<&'static str> {
+    if edition.supports_nested_groups() {
+        return None;
+    }
+    match vp {
+        &ViewPath::ViewPathNested(..) => {
+            Some("uses nested groups, which aren't available in the 2015 edition")
+        }
+        &ViewPath::ViewPathList(_, ref items) if items.iter().any(|i| i.0 == "*") => {
+            Some("has a glob in a list, which isn't available in the 2015 edition")
+        }
+        _ => None,
+    }
+}
+
+/// Check that each of `imports` can be written as it is in `edition`, which for the 2015
+/// edition rules out nested groups (`a::{b::c, d}`) and globs in lists (`a::{self, *}`).
+pub fn validate_imports(imports: &[ViewPath], edition: Edition) -> Result<(), EditionError> {
+    for vp in imports {
+        if let Some(message) = edition_problem(vp, edition) {
+            return Err(EditionError {
+                import: vp.clone(),
+                edition: edition,
+                message: message.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `vp` as statements that can be written in `edition`: imports that can't be are split into
+/// flat lists, simple imports and globs.
+pub fn downgrade_for_edition(vp: &ViewPath, edition: Edition) -> Vec<ViewPath> {
+    if edition_problem(vp, edition).is_none() {
+        return vec![vp.clone()];
+    }
+    let mut statements = vec![];
+    for vp in vp.flatten() {
+        match vp {
+            ViewPath::ViewPathList(p, items) => {
+                let (globs, items): (Vec<_>, Vec<_>) = items.into_iter().partition(|i| i.0 == "*");
+                if items.len() == 1 {
+                    statements.extend(ViewPath::ViewPathList(p.clone(), items).simple_paths());
+                } else if !items.is_empty() {
+                    statements.push(ViewPath::ViewPathList(p.clone(), items));
+                }
+                if !globs.is_empty() {
+                    statements.push(ViewPath::ViewPathGlob(p));
+                }
+            }
+            vp => statements.push(vp),
+        }
+    }
+    statements
+}
+
+/// The group rustfmt's `group_imports = "StdExternalCrate"` puts `vp` in: 0 for imports from
+/// `std`, `core` and `alloc`, 2 for those from the current crate (starting with `crate`, `self`
+/// or `super`), and 1 for everything else. As with rustfmt, that includes absolute paths like
+/// `::std::io`, and statements without a prefix such as `use {a, b::c};`.
+pub fn std_external_crate_group(vp: &ViewPath) -> usize {
+    match vp.path().first().map(|s| &s[..]) {
+        Some("std") | Some("core") | Some("alloc") => 0,
+        Some("crate") | Some("self") | Some("super") => 2,
+        _ => 1,
+    }
+}
+
+/// Render imports as `use` statements, one per line. If the config defines import groups, each
+/// group is emitted in the configured order (followed by any ungrouped imports), with groups
+/// separated by a blank line.
+pub fn render_imports(imports: &[ViewPath], config: &Config) -> String {
+    render_statements(imports, config, "", "")
+}
+
+// Render imports as statements with each line starting with `indent`, and `use` preceded by
+// `visibility` (e.g. `pub `).
+pub fn render_statements(imports: &[ViewPath],
+                         config: &Config,
+                         indent: &str,
+                         visibility: &str)
+                         -> String {
+    let _span = debug_span!("render", statements = imports.len()).entered();
+    let imports: Vec<ViewPath> = imports.iter()
+        .flat_map(|vp| downgrade_for_edition(vp, config.edition))
+        .collect();
+    let count = match config.group_imports {
+        GroupImports::Preserve => config.groups.len() + 1,
+        GroupImports::StdExternalCrate => 3,
+        GroupImports::StdExternalWorkspaceCrate => 4,
+        GroupImports::One => 1,
+    };
+    let mut groups: Vec<Vec<&ViewPath>> = vec![vec![]; count];
+    for vp in &imports {
+        let index = match config.group_imports {
+            GroupImports::Preserve => {
+                config.groups
+                    .iter()
+                    .position(|g| g.matches(vp))
+                    .unwrap_or(config.groups.len())
+            }
+            GroupImports::StdExternalCrate => std_external_crate_group(vp),
+            GroupImports::StdExternalWorkspaceCrate => {
+                let local = vp.path()
+                    .first()
+                    .and_then(|name| config.crate_kinds.get(name))
+                    .map_or(false, |&kind| kind != CrateKind::External);
+                match std_external_crate_group(vp) {
+                    1 if local => 2,
+                    2 => 3,
+                    group => group,
+                }
+            }
+            GroupImports::One => 0,
+        };
+        groups[index].push(vp);
+    }
+    let blocks: Vec<String> = groups.iter()
+        .filter(|g| !g.is_empty())
+        .map(|g| render_group(g, config, indent, visibility))
+        .collect();
+    blocks.join("\n")
+}
+
+// Render a group of imports as statements, formatted with prettyplease if the config says so.
+fn render_group(imports: &[&ViewPath], config: &Config, indent: &str, visibility: &str) -> String {
+    #[cfg(feature = "pretty")]
+    {
+        if config.pretty_print {
+            match ::pretty::pretty_print_statements(imports, indent, visibility) {
+                Ok(rendered) => return rendered,
+                Err(e) => warn!("not pretty printing the imports, as syn can't parse them: {}", e),
+            }
+        }
+    }
+    #[cfg(not(feature = "pretty"))]
+    let _ = config;
+    imports.iter().map(|vp| format!("{}{}use {};\n", indent, visibility, vp)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Config, ViewPath};
+    #[test]
+    fn render_view_paths() {
+        for s in &["a::b::c",
+                   "a::b as c",
+                   "::a::*",
+                   "a::{self, b as c, d}",
+                   "{a, b}",
+                   "a::{self, b::{c, d}, e::*}"] {
+            assert_eq!(ViewPath::from(*s).to_string(), *s);
+        }
+    }
+    #[test]
+    fn edition_2015() {
+        let nested = ViewPath::from("a::{self, b::{c, d}, e::*, f}");
+        let glob_list = ViewPath::ViewPathList(vec!["a".to_string()].into(),
+                                               vec![Item::from("self"), Item::from("*")]);
+        let imports = vec![nested.clone(), glob_list.clone(), ViewPath::from("x::{y, z}")];
+        assert_eq!(validate_imports(&imports, Edition::Edition2018), Ok(()));
+        let error = validate_imports(&imports, Edition::Edition2015).unwrap_err();
+        assert_eq!(error.import, nested);
+        assert_eq!(downgrade_for_edition(&glob_list, Edition::Edition2015),
+                   vec![ViewPath::from("a"), ViewPath::from("a::*")]);
+        assert_eq!(render_imports(&imports, &Config::default()),
+                   "use a;\nuse a::b::{c, d};\nuse a::e::*;\nuse a::f;\nuse a;\nuse a::*;\n\
+                    use x::{y, z};\n");
+    }
+    #[test]
+    fn render_groups() {
+        let imports = vec![ViewPath::from("foo::Bar"),
+                           ViewPath::from("std::io"),
+                           ViewPath::from("crate::x::{a, b, c}"),
+                           ViewPath::from("core::fmt")];
+        let config = Config {
+            groups: vec![ImportGroup::new("std", &["std::**", "core::**"]),
+                         ImportGroup::new("local", &["crate::**"])],
+            ..Config::default()
+        };
+        assert_eq!(render_imports(&imports, &config),
+                   "use std::io;\nuse core::fmt;\n\nuse crate::x::{a, b, c};\n\nuse foo::Bar;\n");
+        let imports = vec![ViewPath::from("alloc::vec::Vec"),
+                           ViewPath::from("super::y"),
+                           ViewPath::from("::std::fmt"),
+                           ViewPath::from("foo::Bar"),
+                           ViewPath::from("self::z::*"),
+                           ViewPath::from("std::io")];
+        let config = Config { group_imports: GroupImports::StdExternalCrate, ..Config::default() };
+        assert_eq!(render_imports(&imports, &config),
+                   "use alloc::vec::Vec;\nuse std::io;\n\nuse ::std::fmt;\nuse foo::Bar;\n\n\
+                    use super::y;\nuse self::z::*;\n");
+        let mut config = Config {
+            group_imports: GroupImports::StdExternalWorkspaceCrate,
+            ..Config::default()
+        };
+        config.crate_kinds.insert("foo".to_string(), CrateKind::PathDependency);
+        config.crate_kinds.insert("std".to_string(), CrateKind::External);
+        assert_eq!(render_imports(&imports, &config),
+                   "use alloc::vec::Vec;\nuse std::io;\n\nuse ::std::fmt;\n\nuse foo::Bar;\n\n\
+                    use super::y;\nuse self::z::*;\n");
+        let config = Config { group_imports: GroupImports::One, ..config };
+        assert_eq!(render_imports(&imports[..2], &config), "use alloc::vec::Vec;\nuse super::y;\n");
+    }
+}
@@ -0,0 +1,49 @@
+use {Path, Strictness};
+
+/// A merge that would be lossy or ambiguous: a glob of a module combined with explicit imports
+/// of names in it. Absorbing the explicit imports is only neutral if the names really are in
+/// the module, rather than being imported to override a glob's conflicting name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    /// The module the glob imports.
+    pub module: Path,
+    /// The names in the module that are imported explicitly as well.
+    pub names: Vec<String>,
+}
+
+/// What to do about a `Conflict`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep both the glob and the explicit imports.
+    KeepBoth,
+    /// Let the glob absorb the explicit imports (if it's emitted).
+    PreferGlob,
+    /// Drop the glob, keeping the explicit imports.
+    PreferExplicit,
+    /// Keep both, and report an error so that the imports aren't rewritten.
+    Abort,
+}
+
+/// Decides what happens when combining imports would be lossy or ambiguous. Closures taking a
+/// conflict and returning a resolution implement this, as does `Strictness`, which is what the
+/// combiner uses unless it's given another resolver.
+pub trait ConflictResolver {
+    fn resolve(&self, conflict: &Conflict) -> Resolution;
+}
+
+impl<F> ConflictResolver for F
+    where F: Fn(&Conflict) -> Resolution
+{
+    fn resolve(&self, conflict: &Conflict) -> Resolution {
+        self(conflict)
+    }
+}
+
+impl ConflictResolver for Strictness {
+    fn resolve(&self, _: &Conflict) -> Resolution {
+        match self {
+            &Strictness::Lossless => Resolution::KeepBoth,
+            _ => Resolution::PreferGlob,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::cmp;
+
+use {SourceDiagnostic, line_column};
+
+// The width of `s` in columns as rustc shows it, with tabs expanded to four spaces.
+fn columns(s: &str) -> usize {
+    s.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Render a message about the byte range `span` of `source` (the contents of `file_name`) as
+/// rustc renders its diagnostics: the level and message, the file, line and column, the lines
+/// of the span with it marked, and any help.
+pub fn render_message(level: &str,
+                      message: &str,
+                      file_name: &str,
+                      source: &str,
+                      (start, end): (usize, usize),
+                      help: Option<&str>)
+                      -> String {
+    let (line_start, column_start) = line_column(source, start);
+    let line_end = line_column(source, end).0;
+    let width = line_end.to_string().len();
+    let pad = " ".repeat(width);
+    let mut rendered = format!("{}: {}\n{}--> {}:{}:{}\n{} |\n",
+                               level,
+                               message,
+                               pad,
+                               file_name,
+                               line_start,
+                               column_start,
+                               pad);
+    let first = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let lines: Vec<String> = source[first..]
+        .lines()
+        .take(line_end - line_start + 1)
+        .map(|line| line.replace('\t', "    "))
+        .collect();
+    if lines.len() == 1 {
+        let marks = cmp::max(columns(&source[start..end]), 1);
+        rendered.push_str(&format!("{:>width$} | {}\n", line_start, lines[0], width = width));
+        rendered.push_str(&format!("{} | {}{}\n",
+                                   pad,
+                                   " ".repeat(columns(&source[first..start])),
+                                   "^".repeat(marks)));
+    } else {
+        // Spans of several lines are marked down the left, and along to the end of the last.
+        for (i, line) in lines.iter().enumerate() {
+            let marker = if i == 0 { '/' } else { '|' };
+            rendered.push_str(&format!("{:>width$} | {} {}\n",
+                                       line_start + i,
+                                       marker,
+                                       line,
+                                       width = width));
+        }
+        let last = source[..end].rfind('\n').map_or(0, |i| i + 1);
+        rendered.push_str(&format!("{} | |{}^\n", pad, "_".repeat(columns(&source[last..end]))));
+    }
+    if let Some(help) = help {
+        rendered.push_str(&format!("{} |\n{} = help: {}\n", pad, pad, help));
+    }
+    rendered
+}
+
+/// A diagnostic about `source` (the contents of `file_name`), rendered as rustc renders its
+/// warnings and errors, so that it reads the same as the compiler's output alongside it and
+/// editors can jump to the statement involved.
+pub fn render_diagnostic(diagnostic: &SourceDiagnostic, file_name: &str, source: &str) -> String {
+    render_message(&diagnostic.diagnostic.severity.to_string(),
+                   &diagnostic.diagnostic.message,
+                   file_name,
+                   source,
+                   (diagnostic.start, diagnostic.end),
+                   diagnostic.diagnostic.help().as_ref().map(|h| &h[..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Config, source_diagnostics};
+    #[test]
+    fn rendering() {
+        let source = "fn f() {}\n\nuse a::b as x;\nuse c::d as x;\n";
+        let diagnostics = source_diagnostics(source, &Config::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].start, diagnostics[0].end), (11, 25));
+        assert_eq!(render_diagnostic(&diagnostics[0], "src/lib.rs", source),
+                   "warning: `x` is imported more than once: `a::b as x`, `c::d as x`\n \
+                    --> src/lib.rs:3:1\n  |\n3 | use a::b as x;\n  | ^^^^^^^^^^^^^^\n  |\n  \
+                    = help: rename the imports with `as`, so that each name is only bound once\n");
+
+        let source = "mod m {\n\tuse a::b;\n\tuse a::c;\n}\n";
+        let end = source.find('}').unwrap() - 1;
+        assert_eq!(render_message("note", "here", "m.rs", source, (9, end), None),
+                   "note: here\n --> m.rs:2:2\n  |\n2 | /     use a::b;\n3 | |     use a::c;\n  \
+                    | |_____________^\n");
+    }
+}
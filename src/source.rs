@@ -0,0 +1,750 @@
+use std::cmp;
+
+use {Config, Diagnostic, GlobPolicy, Granularity, ImportCombiner, ViewPath};
+use parser::{ParserBackend, StringSplitter};
+use render::render_statements;
+
+const DIRECTIVE_OFF: &'static str = "// combiner: off";
+const DIRECTIVE_ON: &'static str = "// combiner: on";
+const DIRECTIVE_KEEP: &'static str = "// combiner: keep";
+
+/// A run of consecutive `use` statements in a source file, with the same indentation and
+/// visibility, that can be combined as a unit.
+///
+/// Blank lines don't end a block, but anything else does, including comments (which would
+/// otherwise be lost when the block is rewritten). Statements are left out of blocks entirely
+/// when they:
+///
+/// * have attributes or a trailing comment,
+/// * are marked with a `// combiner: keep` comment, either trailing or on the line before, or
+/// * are between `// combiner: off` and `// combiner: on` comments, or
+/// * can't be parsed.
+///
+/// With `Config::convert_extern_crates`, `extern crate` items are taken as `use` statements of
+/// the same visibility, as long as the edition allows them to be converted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UseBlock {
+    /// The byte range of the block, from the start of the first statement's line to the end of
+    /// the last statement's `;`.
+    pub start: usize,
+    pub end: usize,
+    pub indent: String,
+    /// The visibility of the statements, including a trailing space (e.g. `pub `), or empty.
+    pub visibility: String,
+    pub statements: Vec<ViewPath>,
+    /// The byte range of each statement, from the start of its visibility (or `use`) to its `;`.
+    pub spans: Vec<(usize, usize)>,
+}
+
+impl UseBlock {
+    fn combiner(&self, config: &Config) -> ImportCombiner {
+        let mut combiner = ImportCombiner::with_config(config.clone());
+        // Blocks that have already been combined are in order, which is the common case when
+        // checking that files are unchanged.
+        combiner.add_imports_sorted(&self.statements.iter().collect::<Vec<_>>());
+        combiner
+    }
+
+    /// The combined statements for the block.
+    pub fn combined_statements(&self, config: &Config) -> Vec<ViewPath> {
+        self.combiner(config).get_import_list()
+    }
+
+    /// The problems with combining the block's statements.
+    pub fn diagnostics(&self, config: &Config) -> Vec<Diagnostic> {
+        self.combiner(config).diagnostics()
+    }
+
+    /// The combined replacement text for the block.
+    pub fn combine(&self, config: &Config) -> String {
+        let rendered = render_statements(&self.combined_statements(config),
+                                         config,
+                                         &self.indent,
+                                         &self.visibility);
+        rendered.trim_end().to_string()
+    }
+
+    // The byte range of the first statement importing one of the diagnostic's paths, or if
+    // there isn't one (as the combiner may have respelt the paths), of the whole block.
+    fn diagnostic_span(&self, diagnostic: &Diagnostic) -> (usize, usize) {
+        self.statements
+            .iter()
+            .zip(&self.spans)
+            .find(|&(vp, _)| vp.simple_paths().iter().any(|p| diagnostic.paths.contains(p)))
+            .map_or((self.start + self.indent.len(), self.end), |(_, &span)| span)
+    }
+
+    // A statement of the block, as written by `render_statements`.
+    fn render(&self, vp: &ViewPath) -> String {
+        format!("{}use {};", self.visibility, vp)
+    }
+}
+
+/// A change to a source file from combining one of its blocks of `use` statements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceEdit {
+    /// The byte range replaced in the original source.
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+    /// The statements (e.g. `pub use a::b;`) that were removed from the block, and those that
+    /// replaced them. Statements left as they were aren't included in either.
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+    /// What the combiner noticed while combining the block, such as duplicates it removed.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// The visibility (with trailing space) at the start of a line, which may be empty, or `None`
+// if it's malformed.
+fn visibility(line: &str) -> Option<&str> {
+    let mut rest = line;
+    if rest.starts_with("pub") {
+        rest = &rest[3..];
+        if rest.trim_start().starts_with('(') {
+            let close = match rest.find(')') {
+                Some(close) => close,
+                None => return None,
+            };
+            rest = &rest[close + 1..];
+        }
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+    Some(&line[..line.len() - rest.len()])
+}
+
+// The visibility (with trailing space) of a line that starts a `use` statement, if it does.
+fn use_visibility(line: &str) -> Option<&str> {
+    let visibility = match visibility(line) {
+        Some(visibility) => visibility,
+        None => return None,
+    };
+    let rest = &line[visibility.len()..];
+    if !rest.starts_with("use") || !rest[3..].starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(visibility)
+}
+
+// The visibility (with trailing space) of a line that starts a `use` statement, or with
+// `extern_crates`, an `extern crate` item to convert into one, along with the length of the line
+// up to the statement's body (e.g. `pub use` or `extern crate`).
+fn statement_visibility(line: &str, extern_crates: bool) -> Option<(&str, usize)> {
+    if let Some(visibility) = use_visibility(line) {
+        return Some((visibility, visibility.len() + "use".len()));
+    }
+    let visibility = match visibility(line) {
+        Some(visibility) if extern_crates => visibility,
+        _ => return None,
+    };
+    let mut rest = &line[visibility.len()..];
+    for keyword in &["extern", "crate"] {
+        if !rest.starts_with(*keyword) || !rest[keyword.len()..].starts_with(char::is_whitespace) {
+            return None;
+        }
+        rest = rest[keyword.len()..].trim_start();
+    }
+    // `extern crate self as name;` has no `use` statement to become.
+    let name_end = rest.find(|c: char| c != '_' && !c.is_alphanumeric()).unwrap_or(rest.len());
+    if &rest[..name_end] == "self" {
+        return None;
+    }
+    Some((visibility, line.len() - rest.len()))
+}
+
+// Whether `extern crate` items in a source file are converted with `config`.
+fn converts_extern_crates(config: &Config) -> bool {
+    config.convert_extern_crates && config.edition.allows_extern_crate_conversion()
+}
+
+// Whether a line starts an inline module (`mod name {`).
+fn starts_inline_module(line: &str) -> bool {
+    let rest = &line[visibility(line).map_or(0, |v| v.len())..];
+    rest.starts_with("mod ") && rest.contains('{')
+}
+
+// What the end of a line is inside of, which the next line continues.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Lexical {
+    Code,
+    // A block comment, nested this deep.
+    BlockComment(usize),
+    StringLiteral,
+    // A raw string literal, closed by a quote and this many `#`s.
+    RawString(usize),
+}
+
+// If the text `before` a quote ends with a raw string's prefix (`r`, `br` or `cr`, followed by
+// any `#`s), the number of `#`s.
+fn raw_string_hashes(before: &[u8]) -> Option<usize> {
+    let hashes = before.iter().rev().take_while(|&&b| b == b'#').count();
+    let mut prefix = &before[..before.len() - hashes];
+    if !prefix.ends_with(b"r") {
+        return None;
+    }
+    prefix = &prefix[..prefix.len() - 1];
+    if prefix.ends_with(b"b") || prefix.ends_with(b"c") {
+        prefix = &prefix[..prefix.len() - 1];
+    }
+    match prefix.last() {
+        // The end of an identifier, like `bar"`.
+        Some(&b) if b == b'_' || b.is_ascii_alphanumeric() => None,
+        _ => Some(hashes),
+    }
+}
+
+// What the end of `text` is inside of, if it starts inside `state`.
+fn scan(text: &str, mut state: Lexical) -> Lexical {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        match state {
+            Lexical::Code => {
+                if rest.starts_with(b"//") {
+                    // The rest of the line is a comment.
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                    continue;
+                } else if rest.starts_with(b"/*") {
+                    state = Lexical::BlockComment(1);
+                    i += 1;
+                } else if rest[0] == b'"' {
+                    state = raw_string_hashes(&bytes[..i])
+                        .map_or(Lexical::StringLiteral, Lexical::RawString);
+                } else if rest[0] == b'\'' {
+                    // A character literal (which may be a quote), rather than a lifetime.
+                    if rest.starts_with(b"'\\") {
+                        i += 2;
+                        while i < bytes.len() && bytes[i] != b'\'' {
+                            i += 1;
+                        }
+                    } else if let Some(c) = text[i + 1..].chars().next() {
+                        if text[i + 1 + c.len_utf8()..].starts_with('\'') {
+                            i += c.len_utf8() + 1;
+                        }
+                    }
+                }
+            }
+            Lexical::BlockComment(depth) => {
+                if rest.starts_with(b"/*") {
+                    state = Lexical::BlockComment(depth + 1);
+                    i += 1;
+                } else if rest.starts_with(b"*/") {
+                    state = if depth == 1 {
+                        Lexical::Code
+                    } else {
+                        Lexical::BlockComment(depth - 1)
+                    };
+                    i += 1;
+                }
+            }
+            Lexical::StringLiteral => {
+                if rest[0] == b'\\' {
+                    i += 1;
+                } else if rest[0] == b'"' {
+                    state = Lexical::Code;
+                }
+            }
+            Lexical::RawString(hashes) => {
+                let closing = rest[1..].iter().take_while(|&&b| b == b'#').count();
+                if rest[0] == b'"' && closing >= hashes {
+                    state = Lexical::Code;
+                    i += hashes;
+                }
+            }
+        }
+        i += 1;
+    }
+    state
+}
+
+/// Find the blocks of `use` statements in `source` that can be combined.
+pub fn find_use_blocks(source: &str) -> Vec<UseBlock> {
+    StringSplitter.parse_file(source, true, false)
+}
+
+/// Find the blocks of `use` statements in `source` that can be combined with `config`, which
+/// leaves out those in `#[cfg(test)]` modules unless it processes test modules, and includes
+/// `extern crate` items if it converts them.
+pub fn find_use_blocks_with(source: &str, config: &Config) -> Vec<UseBlock> {
+    StringSplitter.parse_file(source, config.process_test_modules, converts_extern_crates(config))
+}
+
+/// Find the blocks of `use` statements in `source` by scanning its lines, parsing each statement
+/// with `parser`. With `extern_crates`, `extern crate` items are parsed as `use` statements too.
+pub fn find_blocks<P: ParserBackend + ?Sized>(source: &str,
+                                              include_test_modules: bool,
+                                              extern_crates: bool,
+                                              parser: &P)
+                                              -> Vec<UseBlock> {
+    let _span = debug_span!("parse").entered();
+    fn flush(block: &mut Option<UseBlock>, blocks: &mut Vec<UseBlock>) {
+        if let Some(block) = block.take() {
+            blocks.push(block);
+        }
+    }
+    let mut blocks = vec![];
+    let mut block: Option<UseBlock> = None;
+    let mut off = false;
+    let mut keep_next = false;
+    // The number of brackets left open by an attribute split over several lines.
+    let mut attribute_depth = 0usize;
+    // Whether the line starts in a comment or string literal, rather than in code.
+    let mut lexical = Lexical::Code;
+    // The brace depth at the start of each line (ignoring braces in `use` statements, which
+    // balance), whether the next item is `#[cfg(test)]`, and the depth outside the test module
+    // being skipped, if there is one.
+    let mut depth = 0usize;
+    let mut cfg_test_next = false;
+    let mut test_module_depth: Option<usize> = None;
+    let mut line_start = 0;
+    while line_start < source.len() {
+        let line_end = source[line_start..].find('\n').map_or(source.len(), |i| line_start + i);
+        let line = &source[line_start..line_end];
+        let trimmed = line.trim();
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let mut next_line = line_end + 1;
+        if lexical != Lexical::Code {
+            // Statements in block comments and string literals aren't real ones.
+            flush(&mut block, &mut blocks);
+        } else if attribute_depth > 0 {
+            // The rest of an attribute, which still applies to the next item.
+            attribute_depth += trimmed.matches('[').count();
+            attribute_depth = attribute_depth.saturating_sub(trimmed.matches(']').count());
+        } else if trimmed == DIRECTIVE_OFF {
+            flush(&mut block, &mut blocks);
+            off = true;
+        } else if trimmed == DIRECTIVE_ON {
+            flush(&mut block, &mut blocks);
+            off = false;
+        } else if trimmed == DIRECTIVE_KEEP {
+            flush(&mut block, &mut blocks);
+            keep_next = true;
+        } else if let Some((visibility, keyword_end)) =
+            statement_visibility(line.trim_start(), extern_crates) {
+            let body_start = line_start + indent.len() + keyword_end;
+            let semi = match source[body_start..].find(';') {
+                Some(i) => body_start + i,
+                None => break,
+            };
+            let statement_line_end =
+                source[semi..].find('\n').map_or(source.len(), |i| semi + i);
+            let trailing = source[semi + 1..statement_line_end].trim();
+            next_line = statement_line_end + 1;
+            let in_test_module = test_module_depth.is_some();
+            let parsed = if off || keep_next || in_test_module || !trailing.is_empty() {
+                None
+            } else {
+                parser.parse_statement(&source[body_start..semi])
+            };
+            if let Some(vp) = parsed {
+                let continues = block.as_ref()
+                    .map_or(false, |b| b.indent == indent && b.visibility == visibility);
+                if !continues {
+                    flush(&mut block, &mut blocks);
+                    block = Some(UseBlock {
+                        start: line_start,
+                        end: semi + 1,
+                        indent: indent.to_string(),
+                        visibility: visibility.to_string(),
+                        statements: vec![],
+                        spans: vec![],
+                    });
+                }
+                let b = block.as_mut().unwrap();
+                b.end = semi + 1;
+                b.statements.push(vp);
+                b.spans.push((line_start + indent.len(), semi + 1));
+            } else {
+                debug!(line = source[..line_start].matches('\n').count() + 1,
+                       "leaving statement as it is: {}",
+                       if off {
+                           "combiner is off"
+                       } else if in_test_module {
+                           "in a test module"
+                       } else if keep_next {
+                           "marked to keep or has attributes"
+                       } else if !trailing.is_empty() {
+                           "has a trailing comment"
+                       } else {
+                           "couldn't be parsed"
+                       });
+                flush(&mut block, &mut blocks);
+                keep_next = false;
+                cfg_test_next = false;
+            }
+        } else if trimmed.starts_with("#[") {
+            // Attributes apply to the next item, so an attributed `use` has to stay as it is.
+            flush(&mut block, &mut blocks);
+            keep_next = true;
+            cfg_test_next |= trimmed.starts_with("#[cfg(test)]");
+            attribute_depth = trimmed.matches('[')
+                .count()
+                .saturating_sub(trimmed.matches(']').count());
+        } else if !trimmed.is_empty() {
+            flush(&mut block, &mut blocks);
+            keep_next = false;
+            if cfg_test_next && !include_test_modules && test_module_depth.is_none() &&
+               starts_inline_module(trimmed) {
+                test_module_depth = Some(depth);
+            }
+            cfg_test_next = false;
+            depth += line.matches('{').count();
+            depth = depth.saturating_sub(line.matches('}').count());
+            if test_module_depth.map_or(false, |d| depth <= d) {
+                test_module_depth = None;
+            }
+        }
+        lexical = scan(&source[line_start..cmp::min(next_line, source.len())], lexical);
+        line_start = next_line;
+    }
+    flush(&mut block, &mut blocks);
+    blocks
+}
+
+/// A statement importing a name that's already been imported earlier in the same block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateImport {
+    /// The byte range of the statement.
+    pub start: usize,
+    pub end: usize,
+    /// The name imported again, as a simple (or glob) path.
+    pub path: ViewPath,
+}
+
+/// A diagnostic about a block of `use` statements in a source file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceDiagnostic {
+    /// The byte range of the first statement involved, or if it can't be told, of the block.
+    pub start: usize,
+    pub end: usize,
+    pub diagnostic: Diagnostic,
+}
+
+/// The duplicate imports in `source`.
+pub fn duplicate_imports(source: &str) -> Vec<DuplicateImport> {
+    let mut duplicates = vec![];
+    for block in find_use_blocks(source) {
+        let mut seen = vec![];
+        for (vp, &(start, end)) in block.statements.iter().zip(&block.spans) {
+            for path in vp.simple_paths() {
+                if seen.contains(&path) {
+                    duplicates.push(DuplicateImport {
+                        start: start,
+                        end: end,
+                        path: path,
+                    });
+                } else {
+                    seen.push(path);
+                }
+            }
+        }
+    }
+    duplicates
+}
+
+/// The one-based line and column (in characters) of the byte `offset` in `source`.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// The zero-based line and column (in UTF-16 code units) of the byte `offset` in `source`, as
+/// positions are given in the language server protocol.
+pub fn utf16_position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count(), before[line_start..].encode_utf16().count())
+}
+
+/// Whether `source` enables clippy's `wildcard_imports` lint (directly or through the
+/// `clippy::pedantic` group) with an inner attribute such as `#![warn(clippy::wildcard_imports)]`.
+pub fn enables_wildcard_imports_lint(source: &str) -> bool {
+    let mut enabled = false;
+    for line in source.lines().map(|l| l.trim()).filter(|l| l.starts_with("#![")) {
+        let (level, lints) = match line[3..].find('(') {
+            Some(open) => (line[3..3 + open].trim(), &line[3 + open..]),
+            None => continue,
+        };
+        let lints: Vec<&str> = lints.trim_matches(|c| "()] ".contains(c))
+            .split(',')
+            .map(|l| l.trim())
+            .collect();
+        if lints.contains(&"clippy::wildcard_imports") ||
+           (lints.contains(&"clippy::pedantic") && level != "allow") {
+            enabled = level == "warn" || level == "deny" || level == "forbid";
+        }
+    }
+    enabled
+}
+
+// The config to combine `source` with, if `config` needs adjusting for it: with
+// `clippy_aware`, keeping explicit imports under globs when `clippy::wildcard_imports` is
+// enabled.
+fn adjusted_config(source: &str, config: &Config) -> Option<Config> {
+    if config.clippy_aware && config.glob_policy == GlobPolicy::Absorb &&
+       enables_wildcard_imports_lint(source) {
+        debug!("keeping explicit imports under globs, for clippy::wildcard_imports");
+        Some(Config { glob_policy: GlobPolicy::Keep, ..config.clone() })
+    } else {
+        None
+    }
+}
+
+/// The problems found with combining each block of `use` statements in `source`, including the
+/// blocks that are left as they are because of errors.
+pub fn source_diagnostics(source: &str, config: &Config) -> Vec<SourceDiagnostic> {
+    let adjusted = adjusted_config(source, config);
+    let config = adjusted.as_ref().unwrap_or(config);
+    let mut diagnostics = vec![];
+    for block in find_use_blocks_with(source, config) {
+        for diagnostic in block.diagnostics(config) {
+            let (start, end) = block.diagnostic_span(&diagnostic);
+            diagnostics.push(SourceDiagnostic {
+                start: start,
+                end: end,
+                diagnostic: diagnostic,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// The edits made by combining every block of `use` statements in `source`, in order. Blocks
+/// that are already combined aren't included.
+pub fn source_edits(source: &str, config: &Config) -> Vec<SourceEdit> {
+    source_edits_with_parser(source, config, &StringSplitter)
+}
+
+/// The same as `source_edits`, but finding the statements with another parser.
+pub fn source_edits_with_parser<P: ParserBackend + ?Sized>(source: &str,
+                                                           config: &Config,
+                                                           parser: &P)
+                                                           -> Vec<SourceEdit> {
+    let adjusted = adjusted_config(source, config);
+    let config = adjusted.as_ref().unwrap_or(config);
+    let mut edits = vec![];
+    for block in parser.parse_file(source,
+                                   config.process_test_modules,
+                                   converts_extern_crates(config)) {
+        let _span = debug_span!("combine",
+                                line = source[..block.start].matches('\n').count() + 1,
+                                statements = block.statements.len())
+            .entered();
+        let diagnostics = block.diagnostics(config);
+        // Users see these through `source_diagnostics`, rendered with the statements involved.
+        for diagnostic in &diagnostics {
+            debug!(severity = %diagnostic.severity, "{}", diagnostic.message);
+        }
+        if diagnostics.iter().any(|d| d.is_error()) {
+            debug!("not combining, as it has errors");
+            continue;
+        }
+        let combined = block.combine(config);
+        if combined == &source[block.start..block.end] {
+            debug!("already combined");
+            continue;
+        }
+        let before: Vec<String> = block.statements.iter().map(|vp| block.render(vp)).collect();
+        let after: Vec<String> = block.combined_statements(config)
+            .iter()
+            .map(|vp| block.render(vp))
+            .collect();
+        debug!(removed = ?before, added = ?after, "combined");
+        edits.push(SourceEdit {
+            start: block.start,
+            end: block.end,
+            replacement: combined,
+            removed: before.iter().filter(|s| !after.contains(s)).cloned().collect(),
+            added: after.iter().filter(|s| !before.contains(s)).cloned().collect(),
+            diagnostics: diagnostics,
+        });
+    }
+    edits
+}
+
+/// Apply `edits` (in order and not overlapping, as from `source_edits`) to `source`.
+pub fn apply_edits(source: &str, edits: &[SourceEdit]) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+    for edit in edits {
+        result.push_str(&source[copied..edit.start]);
+        result.push_str(&edit.replacement);
+        copied = edit.end;
+    }
+    result.push_str(&source[copied..]);
+    result
+}
+
+/// Combine every block of `use` statements in `source`, returning the rewritten source.
+pub fn combine_source(source: &str, config: &Config) -> String {
+    apply_edits(source, &source_edits(source, config))
+}
+
+/// The same as `combine_source`, but finding the statements with another parser.
+pub fn combine_source_with_parser<P: ParserBackend + ?Sized>(source: &str,
+                                                             config: &Config,
+                                                             parser: &P)
+                                                             -> String {
+    apply_edits(source, &source_edits_with_parser(source, config, parser))
+}
+
+/// Split the `use` statements in `source` apart (see `Config::splitting`), returning the
+/// rewritten source.
+pub fn split_source(source: &str, config: &Config, granularity: Granularity) -> String {
+    combine_source(source, &config.splitting(granularity))
+}
+
+/// Normalize the `use` statements in `source` into a canonical form: a single statement for
+/// each name imported, in order, with duplicates removed.
+pub fn normalize_source(source: &str, config: &Config) -> String {
+    split_source(source, config, Granularity::Item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Config, Edition, Strictness};
+    #[test]
+    fn combine_blocks() {
+        let source = "use a::b;\n\
+                      use a::{c,\n        d};\n\
+                      pub use x::y;\n\
+                      \n\
+                      fn f() {\n    use p::q;\n    use p::r;\n\n    use p::s;\n}\n";
+        assert_eq!(combine_source(source, &Config::default()),
+                   "use a::{b, c, d};\n\
+                    pub use x::y;\n\
+                    \n\
+                    fn f() {\n    use p::{q, r, s};\n}\n");
+    }
+    #[test]
+    fn directives() {
+        let source = "use a::b;\n\
+                      use a::c; // combiner: keep\n\
+                      use a::d;\n\
+                      #[cfg(test)]\n\
+                      use a::e;\n\
+                      // combiner: off\n\
+                      use x::y;\n\
+                      use x::z;\n\
+                      use x::w;\n\
+                      // combiner: on\n\
+                      // combiner: keep\n\
+                      use b::a;\n\
+                      use b::b;\n\
+                      use b::c;\n\
+                      use b::d;\n";
+        let blocks = find_use_blocks(source);
+        assert_eq!(blocks.iter().map(|b| b.statements.len()).collect::<Vec<_>>(),
+                   vec![1, 1, 3]);
+        assert_eq!(combine_source(source, &Config::default()),
+                   source.replace("use b::b;\nuse b::c;\nuse b::d;", "use b::{b, c, d};"));
+
+        // An attribute split over lines still applies to the statement after it.
+        let source = "#[cfg(any(\n    unix,\n    windows\n))]\nuse a::b;\nuse a::c;\nuse a::d;\n\
+                      use a::e;\n";
+        assert_eq!(combine_source(source, &Config::default()),
+                   "#[cfg(any(\n    unix,\n    windows\n))]\nuse a::b;\nuse a::{c, d, e};\n");
+
+        // Nor are statements in block comments or string literals.
+        let source = "/*\nuse a::b;\nuse a::c;\n/* */\nuse a::d;\n*/\n\
+                      const S: &str = r#\"\"\nuse a::b;\nuse a::c;\nuse a::d;\n\"#;\n\
+                      const C: char = '\"';\nuse a::e;\nuse a::f;\nuse a::g;\n";
+        assert_eq!(combine_source(source, &Config::default()),
+                   source.replace("use a::e;\nuse a::f;\nuse a::g;", "use a::{e, f, g};"));
+    }
+    #[test]
+    fn edits() {
+        let source = "use a::b;\nuse a::c;\nuse a::d;\nuse x::y;\n\nfn f() {}\n";
+        let edits = source_edits(source, &Config::default());
+        assert_eq!(edits,
+                   vec![SourceEdit {
+                            start: 0,
+                            end: 39,
+                            replacement: "use a::{b, c, d};\nuse x::y;".to_string(),
+                            removed: vec!["use a::b;".to_string(),
+                                          "use a::c;".to_string(),
+                                          "use a::d;".to_string()],
+                            added: vec!["use a::{b, c, d};".to_string()],
+                            diagnostics: vec![],
+                        }]);
+        assert_eq!(apply_edits(source, &edits), combine_source(source, &Config::default()));
+    }
+    #[test]
+    fn test_modules() {
+        let source = "use a::b;\nuse a::c;\nuse a::d;\n\n\
+                      #[cfg(test)]\nmod tests {\n    use super::*;\n    use x::y;\n    use x::z;\n\
+                      \x20   use x::w;\n    fn f() {}\n}\n\
+                      mod other {\n    use p::q;\n    use p::r;\n    use p::s;\n}\n";
+        let config = Config { process_test_modules: false, ..Config::default() };
+        assert_eq!(find_use_blocks(source).len(), 3);
+        assert_eq!(find_use_blocks_with(source, &config).len(), 2);
+        assert_eq!(combine_source(source, &config),
+                   source.replace("use a::b;\nuse a::c;\nuse a::d;", "use a::{b, c, d};")
+                       .replace("use p::q;\n    use p::r;\n    use p::s;", "use p::{q, r, s};"));
+    }
+    #[test]
+    fn strictness() {
+        let source = "use a::b as x;\nuse c::d as x;\nuse c::e;\n";
+        assert_eq!(combine_source(source, &Config::rustfmt_default()),
+                   "use a::b as x;\nuse c::{d as x, e};\n");
+        let config = Config { strictness: Strictness::Strict, ..Config::rustfmt_default() };
+        assert_eq!(combine_source(source, &config), source);
+    }
+    #[test]
+    fn clippy_wildcard_imports() {
+        assert!(enables_wildcard_imports_lint("#![deny(clippy::wildcard_imports)]\n"));
+        assert!(enables_wildcard_imports_lint("#![warn(missing_docs, clippy::pedantic)]\n"));
+        assert!(!enables_wildcard_imports_lint("#![warn(clippy::pedantic)]\n\
+                                                #![allow(clippy::wildcard_imports)]\n"));
+        let source = "#![warn(clippy::pedantic)]\nuse a::*;\nuse a::b;\nuse c::d;\n";
+        let config = Config::rustfmt_default();
+        assert_eq!(combine_source(source, &config),
+                   "#![warn(clippy::pedantic)]\nuse a::*;\nuse c::d;\n");
+        let config = Config { clippy_aware: true, ..config };
+        assert_eq!(combine_source(source, &config),
+                   "#![warn(clippy::pedantic)]\nuse a::*;\nuse a::b;\nuse c::d;\n");
+    }
+    #[test]
+    fn split() {
+        let config = Config::compact();
+        let source = "use a::{b, c::{d, e}};\nuse x::y;\n";
+        assert_eq!(split_source(source, &config, Granularity::Module),
+                   "use a::b;\nuse a::c::{d, e};\nuse x::y;\n");
+        assert_eq!(split_source(source, &config, Granularity::Item),
+                   "use a::b;\nuse a::c::d;\nuse a::c::e;\nuse x::y;\n");
+        assert_eq!(normalize_source("use x::y;\nuse a::{c::e, b};\nuse a::c::{d, e};\n", &config),
+                   "use a::b;\nuse a::c::d;\nuse a::c::e;\nuse x::y;\n");
+    }
+    #[test]
+    fn extern_crates() {
+        let source = "extern crate a;\nuse a::d;\npub extern crate b as c;\n#[macro_use]\n\
+                      extern crate e;\nextern crate self as f;\nextern crate g;\n";
+        let config = Config { convert_extern_crates: true, ..Config::compact() };
+        assert_eq!(combine_source(source, &config),
+                   "use a::{self, d};\npub use b as c;\n#[macro_use]\n\
+                    extern crate e;\nextern crate self as f;\nuse g;\n");
+        // The 2015 edition needs them as they are.
+        let config = Config { edition: Edition::Edition2015, ..config };
+        assert_eq!(combine_source(source, &config), source);
+    }
+    #[test]
+    fn duplicates() {
+        let source = "use a::b;\nfn f() {\n    use a::{b, c};\n    use a::{c, d as e};\n}\n";
+        assert_eq!(duplicate_imports(source),
+                   vec![DuplicateImport {
+                            start: 42,
+                            end: 61,
+                            path: ViewPath::from("a::c"),
+                        }]);
+        assert_eq!(line_column(source, 42), (4, 5));
+        assert_eq!(line_column(source, 0), (1, 1));
+    }
+}
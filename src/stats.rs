@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use {Config, ViewPath};
+use source::{duplicate_imports, find_use_blocks_with};
+
+/// Statistics about the imports in some source files, and how combining them would change them.
+/// Only statements the combiner would consider are counted, so those it's told to keep, or that
+/// have attributes, are left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportStats {
+    pub files: usize,
+    pub statements: usize,
+    /// The number of names imported from each top-level crate (or module).
+    pub crates: BTreeMap<String, usize>,
+    pub duplicates: usize,
+    pub globs: usize,
+    /// The number of brace lists, and the total number of items in them.
+    pub lists: usize,
+    pub list_items: usize,
+    /// The number of statements there would be after combining.
+    pub combined_statements: usize,
+}
+
+/// How much an `ImportCombiner` is storing, as given by `ImportCombiner::metrics`, for keeping an
+/// eye on the memory used by a combiner that a whole workspace's imports are added to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CombinerMetrics {
+    /// The number of modules and items in the combiner's tree.
+    pub nodes: usize,
+    /// The number of path segments stored, in the tree and in the imports kept out of it.
+    pub segments: usize,
+    /// The number of renames (`as` names) in the tree.
+    pub renames: usize,
+    /// Roughly how many bytes the combiner has allocated on the heap. The memory the
+    /// collections use for bookkeeping, beyond the space for their elements, isn't counted.
+    pub heap_bytes: usize,
+}
+
+impl ImportStats {
+    pub fn new() -> ImportStats {
+        ImportStats::default()
+    }
+
+    /// Add the imports in a source file, as they'd be combined with `config`.
+    pub fn add_source(&mut self, source: &str, config: &Config) {
+        self.files += 1;
+        for block in find_use_blocks_with(source, config) {
+            self.statements += block.statements.len();
+            self.combined_statements += block.combined_statements(config).len();
+            for vp in &block.statements {
+                for flat in vp.flatten() {
+                    if let ViewPath::ViewPathList(_, ref items) = flat {
+                        self.lists += 1;
+                        self.list_items += items.len();
+                    }
+                }
+                for path in vp.simple_paths() {
+                    if let ViewPath::ViewPathGlob(_) = path {
+                        self.globs += 1;
+                    }
+                    // `::a::b` imports from `a` as much as `a::b` does.
+                    let root = path.path().iter().find(|s| !s.is_empty()).cloned();
+                    *self.crates.entry(root.unwrap_or("::".to_string())).or_insert(0) += 1;
+                }
+            }
+        }
+        self.duplicates += duplicate_imports(source).len();
+    }
+
+    pub fn average_list_size(&self) -> f64 {
+        if self.lists == 0 {
+            0.0
+        } else {
+            self.list_items as f64 / self.lists as f64
+        }
+    }
+
+    /// The percentage by which combining would reduce the number of statements.
+    pub fn projected_reduction(&self) -> f64 {
+        if self.statements == 0 {
+            0.0
+        } else {
+            100.0 * (self.statements as f64 - self.combined_statements as f64) /
+            self.statements as f64
+        }
+    }
+}
+
+impl fmt::Display for ImportStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "files:              {}", self.files)?;
+        writeln!(f, "use statements:     {}", self.statements)?;
+        writeln!(f, "duplicate imports:  {}", self.duplicates)?;
+        writeln!(f, "glob imports:       {}", self.globs)?;
+        writeln!(f,
+                 "brace lists:        {} (average {:.1} items)",
+                 self.lists,
+                 self.average_list_size())?;
+        let reduction = self.projected_reduction();
+        writeln!(f,
+                 "after combining:    {} statements ({:.0}% {})",
+                 self.combined_statements,
+                 reduction.abs(),
+                 if reduction < 0.0 { "more" } else { "fewer" })?;
+        writeln!(f, "names imported by crate:")?;
+        let mut crates: Vec<_> = self.crates.iter().collect();
+        crates.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (name, count) in crates {
+            writeln!(f, "    {:<24} {}", name, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Config;
+    #[test]
+    fn stats() {
+        let mut stats = ImportStats::new();
+        stats.add_source("use std::io;\nuse std::fmt::{self, Display};\nuse std::io;\n\
+                          use foo::*;\nuse foo::bar::{a, b, c};\n",
+                         &Config::rustfmt_default());
+        assert_eq!(stats.statements, 5);
+        assert_eq!(stats.crates.get("std"), Some(&4));
+        assert_eq!(stats.crates.get("foo"), Some(&4));
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stats.globs, 1);
+        assert_eq!((stats.lists, stats.list_items), (2, 5));
+        assert_eq!(stats.combined_statements, 4);
+        assert_eq!(stats.projected_reduction(), 20.0);
+    }
+}
@@ -0,0 +1,106 @@
+use serde_json::Value;
+
+use {SourceEdit, line_column};
+use rustc_style::render_message;
+
+/// The code of the diagnostics from `rustc_diagnostic`, standing in for a lint name.
+pub const COMBINABLE_IMPORTS_CODE: &'static str = "combinable_imports";
+
+// A span of `source` in rustc's JSON format, with the lines it covers, and the suggested
+// replacement if it's a suggestion's.
+fn span(file_name: &str,
+        source: &str,
+        (start, end): (usize, usize),
+        replacement: Option<&str>)
+        -> Value {
+    let (line_start, column_start) = line_column(source, start);
+    let (line_end, column_end) = line_column(source, end);
+    let first_line = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let text: Vec<Value> = source[first_line..]
+        .lines()
+        .take(line_end - line_start + 1)
+        .enumerate()
+        .map(|(i, line)| {
+            let highlight_start = if i == 0 { column_start } else { 1 };
+            let highlight_end = if i == line_end - line_start {
+                column_end
+            } else {
+                line.chars().count() + 1
+            };
+            json!({
+                "text": line,
+                "highlight_start": highlight_start,
+                "highlight_end": highlight_end,
+            })
+        })
+        .collect();
+    json!({
+        "file_name": file_name,
+        "byte_start": start,
+        "byte_end": end,
+        "line_start": line_start,
+        "line_end": line_end,
+        "column_start": column_start,
+        "column_end": column_end,
+        "is_primary": true,
+        "text": text,
+        "label": null,
+        "suggested_replacement": replacement,
+        "suggestion_applicability": replacement.map(|_| "MachineApplicable"),
+        "expansion": null,
+    })
+}
+
+/// A warning that the statements an edit replaces can be combined, as a diagnostic in the JSON
+/// format rustc emits with `--error-format=json`. It has the combined statements as a
+/// `MachineApplicable` suggestion, so that rustfix (and tools built on it, like `cargo fix`) can
+/// apply the edit to `source`, the contents of `file_name`.
+pub fn rustc_diagnostic(file_name: &str, source: &str, edit: &SourceEdit) -> Value {
+    let message = format!("{} use statements can be combined into {}",
+                          edit.removed.len(),
+                          edit.added.len());
+    let range = (edit.start, edit.end);
+    let help = Some("combine them");
+    let rendered = render_message("warning", &message, file_name, source, range, help);
+    json!({
+        "message": message,
+        "code": { "code": COMBINABLE_IMPORTS_CODE, "explanation": null },
+        "level": "warning",
+        "spans": [span(file_name, source, range, None)],
+        "children": [{
+            "message": "combine them",
+            "code": null,
+            "level": "help",
+            "spans": [span(file_name, source, range, Some(&edit.replacement))],
+            "children": [],
+            "rendered": null,
+        }],
+        "rendered": rendered,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use rustfix::{Filter, apply_suggestions, get_suggestions_from_json};
+    use {Config, combine_source, source_edits};
+    #[test]
+    fn rustfix() {
+        let source = "use x::y;\n\nfn f() {\n    use a::b;\n    use a::c;\n    use a::d;\n}\n\
+                      use é::{a, b};\nuse é::c;\nuse é::d;\n";
+        let config = Config::default();
+        let edits = source_edits(source, &config);
+        assert_eq!(edits.len(), 2);
+        let json: Vec<String> = edits.iter()
+            .map(|edit| rustc_diagnostic("src/lib.rs", source, edit).to_string())
+            .collect();
+        let suggestions = get_suggestions_from_json(&json.join("\n"),
+                                                    &HashSet::new(),
+                                                    Filter::MachineApplicableOnly)
+            .unwrap();
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(apply_suggestions(source, &suggestions).unwrap(),
+                   combine_source(source, &config));
+    }
+}
@@ -0,0 +1,29 @@
+/// The namespaces a name can be defined in. A module can define the same name in more than one
+/// (e.g. a unit struct is both a type and a value).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Namespace {
+    Type,
+    Value,
+    Macro,
+}
+
+/// What the names in modules are, for checking that combining imports doesn't make any of them
+/// ambiguous. Closures taking a module's path and returning its names implement this.
+pub trait SymbolTable {
+    /// The names a module defines or exports (and so would be imported by a glob of it), with
+    /// the namespace each is in. Modules that aren't known have no names.
+    fn names(&self, module: &[String]) -> Vec<(String, Namespace)>;
+
+    /// The namespaces `name` is in within a module, which are empty if it isn't known.
+    fn namespaces(&self, module: &[String], name: &str) -> Vec<Namespace> {
+        self.names(module).into_iter().filter(|n| n.0 == name).map(|n| n.1).collect()
+    }
+}
+
+impl<F> SymbolTable for F
+    where F: Fn(&[String]) -> Vec<(String, Namespace)>
+{
+    fn names(&self, module: &[String]) -> Vec<(String, Namespace)> {
+        self(module)
+    }
+}
@@ -0,0 +1,791 @@
+#[cfg(not(feature = "fx-children"))]
+use std::collections::{BTreeMap, btree_map};
+#[cfg(feature = "fx-children")]
+use std::vec;
+
+#[cfg(feature = "fx-children")]
+use rustc_hash::FxHashMap;
+
+use std::mem;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+use {CombinerMetrics, Config, Conflict, ConflictResolver, ImportNode, Path, Resolution,
+     expand_tree_path, path_segments};
+
+/// The index of a node in an `ImportTree`.
+pub type NodeId = usize;
+
+/// The root of every `ImportTree`.
+pub const ROOT: NodeId = 0;
+
+// The number of nodes in each of the arena's chunks.
+const CHUNK_LEN: usize = 64;
+
+/// The children of a node, by name.
+#[cfg(not(feature = "fx-children"))]
+pub type ChildMap = BTreeMap<String, NodeId>;
+/// The children of a node, by name. They're sorted when they're iterated over (through the
+/// `ImportTree`) instead of as they're inserted.
+#[cfg(feature = "fx-children")]
+pub type ChildMap = FxHashMap<String, NodeId>;
+
+// The children in `map`, in name order.
+#[cfg(not(feature = "fx-children"))]
+fn into_sorted(map: ChildMap) -> btree_map::IntoIter<String, NodeId> {
+    map.into_iter()
+}
+
+#[cfg(feature = "fx-children")]
+fn into_sorted(map: ChildMap) -> vec::IntoIter<(String, NodeId)> {
+    let mut children: Vec<_> = map.into_iter().collect();
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    children.into_iter()
+}
+
+/// The nodes of the combiner's tree, kept in one arena rather than each node owning its children
+/// (which nodes refer to by index instead), so that building a big tree doesn't allocate for
+/// every node. The slots of removed nodes are reused.
+///
+/// A chain of nodes that import nothing and have one child each (as deep generated modules
+/// make) can be compressed into the node at the end of it, which is then named with the whole
+/// chain (e.g. `b::c::d`), like a chain of `super`s is. Its parent's `children` has it under
+/// the chain's first segment, so that it's found by that, and it's expanded again when a path
+/// is added through it.
+///
+/// The arena is split into chunks that a clone of the tree shares with the original, so that
+/// cloning it doesn't copy any nodes. A chunk is copied (along with the list of chunks) when
+/// one of its nodes is first changed, so a clone only ends up with its own copies of the parts
+/// of the tree that have changed since.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImportTree {
+    chunks: Arc<Vec<Arc<Vec<ImportNode>>>>,
+    free: Vec<NodeId>,
+}
+
+/// The shape of a node's imports written on one line as a nested group relative to it (e.g.
+/// `{self, c::{d, e}}` for `a::b` in `use a::b::{self, c::{d, e}};`), leaving out the node's own
+/// `self`, which its parent's glob may absorb.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroupLen {
+    /// The number of items in the group.
+    pub items: usize,
+    /// The total length of the items, joined with `, `.
+    pub len: usize,
+    /// Whether the group is a single `self` or `self as x`, which the parent writes as the
+    /// node's name rather than prefixing it.
+    pub selfish: bool,
+}
+
+impl GroupLen {
+    fn new() -> GroupLen {
+        GroupLen {
+            items: 0,
+            len: 0,
+            selfish: false,
+        }
+    }
+
+    fn push(&mut self, item_len: usize) {
+        if self.items > 0 {
+            self.len += ", ".len();
+        }
+        self.items += 1;
+        self.len += item_len;
+    }
+
+    /// The group with the node's own `self` in it as well, if `has_self`.
+    pub fn with_self(mut self, has_self: bool) -> GroupLen {
+        if has_self {
+            self.selfish = self.items == 0;
+            self.push("self".len());
+        }
+        self
+    }
+
+    /// The length of the group as it's written: its item, if there's only one, or its items in
+    /// braces.
+    pub fn written_len(&self) -> usize {
+        if self.items == 1 {
+            self.len
+        } else {
+            self.len + "{}".len()
+        }
+    }
+
+    // The length of the group as an item of its parent's group, where the node is `name`.
+    fn item_len(&self, name: &str) -> usize {
+        if self.selfish {
+            name.len() + self.len - "self".len()
+        } else {
+            name.len() + "::".len() + self.written_len()
+        }
+    }
+}
+
+/// The last path found with `get_or_add_path_from`, along with the nodes on it, so that the next
+/// path is only looked up from where it diverges from this one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cursor {
+    path: Path,
+    // The node at each prefix of `path`, starting with the root (once a path has been found).
+    nodes: Vec<NodeId>,
+}
+
+/// The children of a node, with their names, in name order.
+pub struct Children<'a> {
+    tree: &'a ImportTree,
+    #[cfg(not(feature = "fx-children"))]
+    iter: btree_map::Iter<'a, String, NodeId>,
+    #[cfg(feature = "fx-children")]
+    iter: vec::IntoIter<(&'a String, &'a NodeId)>,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = (&'a String, &'a ImportNode);
+    fn next(&mut self) -> Option<(&'a String, &'a ImportNode)> {
+        let tree = self.tree;
+        self.iter.next().map(|(key, &id)| tree.named(key, id))
+    }
+}
+
+impl Index<NodeId> for ImportTree {
+    type Output = ImportNode;
+    fn index(&self, id: NodeId) -> &ImportNode {
+        &self.chunks[id / CHUNK_LEN][id % CHUNK_LEN]
+    }
+}
+
+impl IndexMut<NodeId> for ImportTree {
+    fn index_mut(&mut self, id: NodeId) -> &mut ImportNode {
+        let chunk = &mut Arc::make_mut(&mut self.chunks)[id / CHUNK_LEN];
+        &mut Arc::make_mut(chunk)[id % CHUNK_LEN]
+    }
+}
+
+impl ImportTree {
+    pub fn new() -> ImportTree {
+        let mut tree = ImportTree::empty();
+        tree.push(ImportNode::new());
+        tree
+    }
+
+    // A tree without even a root.
+    fn empty() -> ImportTree {
+        ImportTree {
+            chunks: Arc::new(vec![]),
+            free: vec![],
+        }
+    }
+
+    pub fn root(&self) -> &ImportNode {
+        &self[ROOT]
+    }
+
+    pub fn node(&self, id: NodeId) -> &ImportNode {
+        &self[id]
+    }
+
+    // The child `id` stored under `key`, with its name.
+    fn named<'a>(&'a self, key: &'a String, id: NodeId) -> (&'a String, &'a ImportNode) {
+        let node = &self[id];
+        (node.chain.as_ref().unwrap_or(key), node)
+    }
+
+    // The number of slots in the arena, including free ones.
+    fn len(&self) -> usize {
+        self.chunks.last().map_or(0, |last| (self.chunks.len() - 1) * CHUNK_LEN + last.len())
+    }
+
+    // Add a node in a new slot.
+    fn push(&mut self, node: ImportNode) -> NodeId {
+        let id = self.len();
+        let chunks = Arc::make_mut(&mut self.chunks);
+        if id % CHUNK_LEN == 0 {
+            chunks.push(Arc::new(Vec::with_capacity(CHUNK_LEN)));
+        }
+        if let Some(last) = chunks.last_mut() {
+            Arc::make_mut(last).push(node);
+        }
+        id
+    }
+
+    /// Add a node (whose children must already be in the tree) without a parent.
+    pub fn add(&mut self, node: ImportNode) -> NodeId {
+        match self.free.pop() {
+            Some(id) => {
+                self[id] = node;
+                id
+            }
+            None => self.push(node),
+        }
+    }
+
+    /// Remove a node (but not its children) from the tree, returning it.
+    pub fn take(&mut self, id: NodeId) -> ImportNode {
+        self.free.push(id);
+        mem::replace(&mut self[id], ImportNode::new())
+    }
+
+    #[cfg(not(feature = "fx-children"))]
+    pub fn children<'a>(&'a self, node: &'a ImportNode) -> Children<'a> {
+        Children {
+            tree: self,
+            iter: node.children.iter(),
+        }
+    }
+
+    #[cfg(feature = "fx-children")]
+    pub fn children<'a>(&'a self, node: &'a ImportNode) -> Children<'a> {
+        let mut children: Vec<_> = node.children.iter().collect();
+        children.sort_by(|a, b| a.0.cmp(b.0));
+        Children {
+            tree: self,
+            iter: children.into_iter(),
+        }
+    }
+
+    /// The children of a node, in whatever order they're stored in, for when the order doesn't
+    /// matter (or they're sorted another way).
+    pub fn stored_children<'a>(&'a self,
+                               node: &'a ImportNode)
+                               -> impl Iterator<Item = (&'a String, &'a ImportNode)> + 'a {
+        node.children.iter().map(move |(key, &id)| self.named(key, id))
+    }
+
+    pub fn child<'a>(&'a self, node: &ImportNode, name: &str) -> Option<&'a ImportNode> {
+        if let Some(&id) = node.children.get(name) {
+            if self[id].chain.is_none() {
+                return Some(&self[id]);
+            }
+        }
+        // A compressed chain is stored under its first segment.
+        let key = path_segments(name).next().unwrap_or(name);
+        node.children
+            .get(key)
+            .map(|&id| &self[id])
+            .filter(|child| child.chain.as_ref().map_or(false, |chain| chain == name))
+    }
+
+    /// The node at `path`, or if `path` ends partway along a compressed chain, the node at the
+    /// end of the chain, along with the rest of the chain's name (e.g. `c::d` for `a::b` when
+    /// `a` has `b::c::d` as a child).
+    pub fn find_path<'a>(&'a self, path: &[String]) -> Option<(&'a ImportNode, &'a str)> {
+        let mut node = self.root();
+        let mut i = 0;
+        while i < path.len() {
+            node = &self[*node.children.get(&path[i])?];
+            i += 1;
+            if let Some(ref chain) = node.chain {
+                let mut offset = path[i - 1].len();
+                for segment in path_segments(chain).skip(1) {
+                    if i == path.len() {
+                        return Some((node, &chain[offset + "::".len()..]));
+                    }
+                    if segment != path[i] {
+                        return None;
+                    }
+                    offset += "::".len() + segment.len();
+                    i += 1;
+                }
+            }
+        }
+        Some((node, ""))
+    }
+
+    /// The children of a node, in the order given by the configured item ordering.
+    pub fn ordered_children<'a>(&'a self,
+                                node: &'a ImportNode,
+                                config: &Config)
+                                -> Vec<(&'a String, &'a ImportNode)> {
+        // Compressed chains are ordered by their first segment, as the nodes they replace were.
+        fn first(name: &str) -> &str {
+            path_segments(name).next().unwrap_or("")
+        }
+        let mut children: Vec<_> = self.stored_children(node).collect();
+        children.sort_by(|a, b| {
+            config.ordering
+                .compare(first(a.0), first(b.0))
+                .then_with(|| config.ordering.compare(a.0, b.0))
+        });
+        children
+    }
+
+    /// Remove the nodes that import nothing, at them or under them, and move the rest into a new
+    /// arena without free slots or excess capacity. The nodes' indices change. The number of
+    /// nodes removed (not counting free slots) is returned.
+    pub fn compact(&mut self) -> usize {
+        // The nodes under the root, parents before children.
+        let mut order = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self[id].children.values());
+        }
+        let mut keep = vec![false; self.len()];
+        keep[ROOT] = true;
+        for &id in order.iter().rev() {
+            let node = &self[id];
+            keep[id] |= node.has_self || node.has_glob || !node.renames.is_empty() ||
+                        node.children.values().any(|&child| keep[child]);
+        }
+        let mut old = mem::replace(self, ImportTree::empty());
+        let mut new_ids = vec![ROOT; old.len()];
+        for id in order.into_iter().filter(|&id| keep[id]) {
+            new_ids[id] = self.push(mem::replace(&mut old[id], ImportNode::new()));
+        }
+        for id in 0..self.len() {
+            let node = &mut self[id];
+            let children = mem::replace(&mut node.children, ChildMap::default());
+            node.children = children.into_iter()
+                .filter(|&(_, id)| keep[id])
+                .map(|(mut name, id)| {
+                    name.shrink_to_fit();
+                    (name, new_ids[id])
+                })
+                .collect();
+        }
+        let chunks = Arc::make_mut(&mut self.chunks);
+        chunks.shrink_to_fit();
+        if let Some(last) = chunks.last_mut() {
+            Arc::make_mut(last).shrink_to_fit();
+        }
+        old.len() - old.free.len() - self.len()
+    }
+
+    /// Compress each chain of nodes below the top-level names that import nothing and have one
+    /// child each (along with the node the chain leads to, if that imports nothing itself) into
+    /// the node at the end of it, freeing the slots of the others. The number of nodes removed
+    /// is returned. Any cursors are no longer valid afterwards.
+    pub fn compress_chains(&mut self) -> usize {
+        let imports_nothing = |node: &ImportNode| {
+            !node.has_self && !node.has_glob && node.renames.is_empty() && !node.keeps_explicit
+        };
+        let mut removed = 0;
+        let mut stack: Vec<NodeId> = self[ROOT].children.values().cloned().collect();
+        while let Some(parent) = stack.pop() {
+            // The children that start chains, with the rest left as they are.
+            let mut chains = vec![];
+            for (key, &id) in self[parent].children.iter() {
+                let node = &self[id];
+                match self.only_child(node) {
+                    Some(child) if imports_nothing(node) && imports_nothing(child) => {
+                        chains.push((key.clone(), id))
+                    }
+                    _ => stack.push(id),
+                }
+            }
+            for (key, first) in chains {
+                let mut chain = self.named(&key, first).0.clone();
+                let mut end = first;
+                loop {
+                    let next = match self[end].children.iter().next() {
+                        Some((key, &next)) if self[end].children.len() == 1 &&
+                                              imports_nothing(&self[next]) => {
+                            chain.push_str("::");
+                            chain.push_str(self.named(key, next).0);
+                            next
+                        }
+                        _ => break,
+                    };
+                    self.take(end);
+                    removed += 1;
+                    end = next;
+                }
+                self[end].chain = Some(chain);
+                if let Some(id) = self[parent].children.get_mut(&key) {
+                    *id = end;
+                }
+                stack.push(end);
+            }
+        }
+        removed
+    }
+
+    /// The number of nodes at and under `node` (counting those a chain was compressed from) and
+    /// the number of statements they were imported by, counting a statement once for each node.
+    pub fn count_under(&self, node: &ImportNode) -> (usize, usize) {
+        let (mut nodes, mut statements) = (0, 0);
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            nodes += node.chain.as_ref().map_or(1, |chain| path_segments(chain).count());
+            statements += node.statements.len();
+            stack.extend(node.children.values().map(|&id| &self[id]));
+        }
+        (nodes, statements)
+    }
+
+    /// Remove the child `name` of `parent`, and everything under it, from the tree.
+    pub fn remove_child(&mut self, parent: NodeId, name: &str) {
+        self.clear_group_len(parent);
+        let key = path_segments(name).next().unwrap_or(name);
+        let key = if self[parent].children.contains_key(name) { name } else { key };
+        let mut stack: Vec<NodeId> = self[parent].children.remove(key).into_iter().collect();
+        while let Some(id) = stack.pop() {
+            stack.extend(self.take(id).children.values());
+        }
+    }
+
+    // The child of `node`, if it has only one.
+    fn only_child<'a>(&'a self, node: &ImportNode) -> Option<&'a ImportNode> {
+        match node.children.len() {
+            1 => node.children.values().next().map(|&id| &self[id]),
+            _ => None,
+        }
+    }
+
+    // Expand the compressed chain stored under `key` in `parent` back into a node for each of
+    // its segments, returning the first of them.
+    fn expand_chain(&mut self, parent: NodeId, key: &str) -> NodeId {
+        let end = self[parent].children[key];
+        let chain = match self[end].chain.take() {
+            Some(chain) => chain,
+            None => return end,
+        };
+        let segments: Vec<&str> = path_segments(&chain).collect();
+        let mut next = end;
+        for segment in segments[1..].iter().rev() {
+            let mut node = ImportNode::new();
+            node.children.insert(segment.to_string(), next);
+            next = self.add(node);
+        }
+        if let Some(id) = self[parent].children.get_mut(key) {
+            *id = next;
+        }
+        next
+    }
+
+    /// The number of nodes (other than the root), segments and renames in the tree, and roughly
+    /// how much memory it's using (including the chunks it shares with its clones).
+    pub fn metrics(&self) -> CombinerMetrics {
+        let mut metrics = CombinerMetrics {
+            nodes: self.len() - self.free.len() - 1,
+            heap_bytes: self.chunks.capacity() * mem::size_of::<Arc<Vec<ImportNode>>>() +
+                        self.free.capacity() * mem::size_of::<NodeId>(),
+            ..CombinerMetrics::default()
+        };
+        // The nodes in free slots are empty, so they can be counted along with the rest.
+        for chunk in self.chunks.iter() {
+            // A chunk's `Arc` has its two counts along with the `Vec`.
+            metrics.heap_bytes += 2 * mem::size_of::<usize>() + mem::size_of::<Vec<ImportNode>>() +
+                                  chunk.capacity() * mem::size_of::<ImportNode>();
+        }
+        for node in self.chunks.iter().flat_map(|chunk| chunk.iter()) {
+            metrics.segments += node.children.len();
+            if let Some(ref chain) = node.chain {
+                metrics.segments += path_segments(chain).count() - 1;
+                metrics.heap_bytes += chain.capacity();
+            }
+            metrics.renames += node.renames.len();
+            metrics.heap_bytes += node.children
+                .keys()
+                .map(|name| name.capacity() + mem::size_of::<(String, NodeId)>())
+                .sum::<usize>();
+            metrics.heap_bytes += node.renames
+                .iter()
+                .map(|rename| rename.capacity() + mem::size_of::<String>())
+                .sum::<usize>();
+            metrics.heap_bytes += node.statements.len() * mem::size_of::<usize>();
+        }
+        metrics
+    }
+
+    /// The node at `path`, adding the nodes that aren't in the tree yet (the number of which is
+    /// returned as well).
+    pub fn get_or_add_path(&mut self, path: &[String]) -> (NodeId, usize) {
+        let mut id = ROOT;
+        let mut added = 0;
+        for segment in path {
+            let (child, new) = self.get_or_add_child(id, segment);
+            id = child;
+            added += new as usize;
+        }
+        self.clear_group_len(id);
+        (id, added)
+    }
+
+    /// `get_or_add_path`, but starting from the end of the longest prefix `path` shares with the
+    /// cursor's path, which is then moved to `path`. Finding a run of paths in order this way
+    /// only descends from the root once. The nodes that aren't descended through again don't
+    /// have their cached group lengths cleared again either, so nothing should look at them in
+    /// between.
+    pub fn get_or_add_path_from(&mut self,
+                                path: &[String],
+                                cursor: &mut Cursor)
+                                -> (NodeId, usize) {
+        let common = cursor.path.iter().zip(path).take_while(|&(a, b)| a == b).count();
+        cursor.path.truncate(common);
+        cursor.nodes.truncate(common + 1);
+        if cursor.nodes.is_empty() {
+            cursor.nodes.push(ROOT);
+        }
+        let mut id = cursor.nodes[common];
+        let mut added = 0;
+        for segment in &path[common..] {
+            let (child, new) = self.get_or_add_child(id, segment);
+            id = child;
+            added += new as usize;
+            cursor.path.push(segment.clone());
+            cursor.nodes.push(id);
+        }
+        self.clear_group_len(id);
+        (id, added)
+    }
+
+    // Clear the cached group length of `id`, without copying its chunk if there isn't one.
+    fn clear_group_len(&mut self, id: NodeId) {
+        if self[id].group_len.get().is_some() {
+            self[id].group_len.take();
+        }
+    }
+
+    // The child of `id` called `name` (clearing the cached group length of `id`), adding it if
+    // it isn't in the tree yet, along with whether it was added.
+    fn get_or_add_child(&mut self, id: NodeId, name: &String) -> (NodeId, bool) {
+        self.clear_group_len(id);
+        if let Some(&child) = self[id].children.get(name) {
+            if self[child].chain.is_some() {
+                return (self.expand_chain(id, name), false);
+            }
+            return (child, false);
+        }
+        let child = self.add(ImportNode::new());
+        self[id].children.insert(name.clone(), child);
+        (child, true)
+    }
+
+    /// The group of `node` (at `path`) with `config`, as it's cached on the node.
+    pub fn group_len(&self, node: &ImportNode, config: &Config, path: &mut Path) -> GroupLen {
+        if let Some(&group) = node.group_len.get() {
+            return group;
+        }
+        let emit_glob = node.has_glob && config.emits_glob(path);
+        let absorb = emit_glob && config.absorbs_under_globs() && !node.keeps_explicit;
+        let mut group = GroupLen::new();
+        for rename in &node.renames {
+            group.push("self as ".len() + rename.len());
+        }
+        group.selfish = group.items == 1;
+        if emit_glob {
+            group.push("*".len());
+        }
+        for (name, child) in self.stored_children(node) {
+            let len = path.len();
+            path.extend(path_segments(name).map(String::from));
+            let child_group =
+                self.group_len(child, config, path).with_self(child.has_self && !absorb);
+            path.truncate(len);
+            if child_group.items > 0 {
+                group.push(child_group.item_len(name));
+            }
+        }
+        group.selfish &= group.items == 1;
+        // Another thread may have got here first, with the same group.
+        let _ = node.group_len.set(group);
+        group
+    }
+
+    // The names of the children explicitly imported by one of `a` and `b`, when the other
+    // imports a glob of the module (and didn't already import them).
+    fn glob_conflicts(&self, a: &ImportNode, b: &ImportNode) -> Vec<String> {
+        let explicit = |node: &ImportNode, other: &ImportNode| -> Vec<String> {
+            self.children(node)
+                .filter(|&(name, child)| {
+                    child.has_self && self.child(other, name).map_or(true, |c| !c.has_self)
+                })
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        if a.has_glob && !b.has_glob {
+            explicit(b, a)
+        } else if b.has_glob && !a.has_glob {
+            explicit(a, b)
+        } else {
+            vec![]
+        }
+    }
+
+    // Ask `resolver` what to do about `a` (at `path`) importing a glob along with explicit
+    // imports of `names`, returning the conflict if it's aborted.
+    fn resolve<R: ConflictResolver>(&mut self,
+                                    a: NodeId,
+                                    names: Vec<String>,
+                                    resolver: &R,
+                                    path: &Path)
+                                    -> Option<Conflict> {
+        if names.is_empty() {
+            return None;
+        }
+        let conflict = Conflict {
+            module: expand_tree_path(path),
+            names: names,
+        };
+        let node = &mut self[a];
+        match resolver.resolve(&conflict) {
+            Resolution::PreferGlob => {}
+            Resolution::KeepBoth => node.keeps_explicit = true,
+            Resolution::PreferExplicit => node.has_glob = false,
+            Resolution::Abort => {
+                node.keeps_explicit = true;
+                return Some(conflict);
+            }
+        }
+        None
+    }
+
+    /// Combine `b` (at `path`) into `a`, asking `resolver` what to do about any globs combined
+    /// with explicit imports. `b` isn't in the tree, but its children are (without a parent).
+    /// Its renames and statements, and the children `a` doesn't have, are moved into `a` rather
+    /// than copied, and the slots of its other children are freed. The conflicts the resolver
+    /// aborted are returned.
+    pub fn combine_with_owned<R: ConflictResolver>(&mut self,
+                                                   a: NodeId,
+                                                   b: ImportNode,
+                                                   resolver: &R,
+                                                   path: &mut Path)
+                                                   -> Vec<Conflict> {
+        let mut aborted = vec![];
+        let names = self.glob_conflicts(&self[a], &b);
+        {
+            let node = &mut self[a];
+            node.group_len.take();
+            node.combine_flags(&b);
+            node.renames.extend(b.renames);
+            node.statements.extend(b.statements);
+            node.drop_underscore_rename();
+        }
+        for (name, child) in into_sorted(b.children) {
+            match self[a].children.get(&name).cloned() {
+                Some(existing) => {
+                    let existing = match self[existing].chain {
+                        Some(_) => self.expand_chain(a, &name),
+                        None => existing,
+                    };
+                    let child = self.take(child);
+                    path.push(name);
+                    aborted.extend(self.combine_with_owned(existing, child, resolver, path));
+                    path.pop();
+                }
+                None => {
+                    self[a].children.insert(name, child);
+                }
+            }
+        }
+        aborted.extend(self.resolve(a, names, resolver, path));
+        aborted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Strictness, as_path};
+    #[test]
+    fn owned_combining() {
+        let mut tree = ImportTree::new();
+        let (b, added) = tree.get_or_add_path(&as_path("a::b"));
+        assert_eq!(added, 2);
+        assert_eq!(tree.get_or_add_path(&as_path("a::b")), (b, 0));
+        // `a::c` and `a::d`, with the children in the tree but not yet under the root.
+        let mut a = ImportNode::new();
+        for name in &["c", "d"] {
+            let mut child = ImportNode::new();
+            child.has_self = true;
+            a.children.insert(name.to_string(), tree.add(child));
+        }
+        let mut root = ImportNode::new();
+        root.children.insert("a".to_string(), tree.add(a));
+        assert_eq!(tree.len(), 6);
+        let aborted = tree.combine_with_owned(ROOT, root, &Strictness::Strict, &mut Path::new());
+        assert_eq!(aborted, vec![]);
+        let a = tree.child(tree.root(), "a").unwrap();
+        assert_eq!(tree.children(a).map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+                   vec!["b", "c", "d"]);
+        // The other `a` was freed, and its slot is reused.
+        assert_eq!(tree.free.len(), 1);
+        assert_eq!(tree.get_or_add_path(&as_path("x")).1, 1);
+        assert_eq!(tree.len(), 6);
+    }
+    #[test]
+    fn compacting() {
+        let mut tree = ImportTree::new();
+        // `a::b` is imported, but `a::c` and `d` are just paths to nothing.
+        let (b, _) = tree.get_or_add_path(&as_path("a::b"));
+        tree[b].has_self = true;
+        tree.get_or_add_path(&as_path("a::c"));
+        tree.get_or_add_path(&as_path("d"));
+        let free = tree.add(ImportNode::new());
+        tree.take(free);
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.compact(), 2);
+        assert_eq!((tree.len(), tree.free.len()), (3, 0));
+        assert_eq!(tree.children(tree.root()).map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+                   vec!["a"]);
+        let a = tree.child(tree.root(), "a").unwrap();
+        assert!(tree.child(a, "b").unwrap().has_self);
+        assert!(tree.child(a, "c").is_none());
+    }
+    #[test]
+    fn chains() {
+        let mut tree = ImportTree::new();
+        let (e, _) = tree.get_or_add_path(&as_path("a::b::c::d::e"));
+        tree[e].has_self = true;
+        let (f, _) = tree.get_or_add_path(&as_path("a::b::c::d::f"));
+        tree[f].has_self = true;
+        // `b` and `c` go, and `d` is named for them.
+        assert_eq!(tree.compress_chains(), 2);
+        assert_eq!(tree.compress_chains(), 0);
+        let a = tree.child(tree.root(), "a").unwrap();
+        assert_eq!(tree.children(a).map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+                   vec!["b::c::d"]);
+        assert!(tree.child(a, "b").is_none());
+        let d = tree.child(a, "b::c::d").unwrap();
+        assert!(tree.child(d, "e").unwrap().has_self);
+        assert_eq!(tree.find_path(&as_path("a::b")).map(|(_, rest)| rest), Some("c::d"));
+        assert_eq!(tree.find_path(&as_path("a::b::c::d::f")).map(|(_, rest)| rest), Some(""));
+        assert!(tree.find_path(&as_path("a::b::x")).is_none());
+        // Adding a path through the chain expands it, with its nodes in the freed slots.
+        let len = tree.len();
+        let (g, added) = tree.get_or_add_path(&as_path("a::b::g"));
+        assert_eq!(added, 1);
+        assert_eq!((tree.len(), tree.free.len()), (len + 1, 0));
+        assert_eq!(tree.find_path(&as_path("a::b::c::d::e")).map(|(node, _)| node.has_self),
+                   Some(true));
+        tree[g].has_self = true;
+        assert_eq!(tree.compress_chains(), 1);
+        let a = tree.child(tree.root(), "a").unwrap();
+        let b = tree.child(a, "b").unwrap();
+        assert_eq!(tree.children(b).map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+                   vec!["c::d", "g"]);
+    }
+    #[test]
+    fn cloning() {
+        let mut tree = ImportTree::new();
+        for i in 0..100 {
+            tree.get_or_add_path(&as_path(&format!("m{}", i)));
+        }
+        let mut clone = tree.clone();
+        assert!(Arc::ptr_eq(&tree.chunks, &clone.chunks));
+        // Only the chunk with the changed node is copied.
+        let (id, _) = clone.get_or_add_path(&as_path("m99"));
+        clone[id].has_self = true;
+        assert!(!tree[id].has_self);
+        assert!(Arc::ptr_eq(&tree.chunks[0], &clone.chunks[0]));
+        assert!(!Arc::ptr_eq(&tree.chunks[1], &clone.chunks[1]));
+        assert!(clone != tree);
+    }
+    #[test]
+    fn cursors() {
+        let mut tree = ImportTree::new();
+        let mut cursor = Cursor::default();
+        let (b, added) = tree.get_or_add_path_from(&as_path("a::b"), &mut cursor);
+        assert_eq!(added, 2);
+        let (c, added) = tree.get_or_add_path_from(&as_path("a::c"), &mut cursor);
+        assert_eq!(added, 1);
+        assert_eq!(cursor.path, as_path("a::c"));
+        assert_eq!(tree.get_or_add_path_from(&as_path("a::b"), &mut cursor), (b, 0));
+        assert_eq!(tree.get_or_add_path_from(&[], &mut cursor), (ROOT, 0));
+        assert_eq!(tree.get_or_add_path(&as_path("a::c")), (c, 0));
+    }
+}
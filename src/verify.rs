@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use {Config, Namespace, Path, SymbolTable, ViewPath, combine_imports_with_config};
+
+/// A name that the combined imports bring into scope differently from the original imports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// The name, or `*` for globs of modules whose names aren't known (the paths being the
+    /// modules).
+    pub name: String,
+    /// The namespace the name is in, if it's known.
+    pub namespace: Option<Namespace>,
+    /// What the name refers to with the original imports (more than one thing if it's
+    /// ambiguous, and nothing if it isn't in scope).
+    pub original: Vec<Path>,
+    /// What the name refers to with the combined imports.
+    pub combined: Vec<Path>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn targets(paths: &[Path]) -> String {
+            if paths.is_empty() {
+                return "nothing".to_string();
+            }
+            let paths: Vec<_> = paths.iter().map(|p| format!("`{}`", p.join("::"))).collect();
+            paths.join(" and ")
+        }
+        write!(f, "`{}`", self.name)?;
+        match self.namespace {
+            Some(Namespace::Type) => write!(f, " (a type)")?,
+            Some(Namespace::Value) => write!(f, " (a value)")?,
+            Some(Namespace::Macro) => write!(f, " (a macro)")?,
+            None => {}
+        }
+        write!(f,
+               " refers to {} before combining, but {} after",
+               targets(&self.original),
+               targets(&self.combined))
+    }
+}
+
+/// A way in which rendering, parsing and combining imports aren't consistent with each other.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    /// Rendering an import and parsing it back gave a different import.
+    Roundtrip {
+        original: ViewPath,
+        rendered: String,
+        parsed: ViewPath,
+    },
+    /// Combining the combined imports again changed them.
+    NotIdempotent {
+        combined: Vec<ViewPath>,
+        recombined: Vec<ViewPath>,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn render(vps: &[ViewPath]) -> String {
+            let vps: Vec<_> = vps.iter().map(|vp| format!("`{}`", vp)).collect();
+            vps.join(", ")
+        }
+        match self {
+            &Mismatch::Roundtrip { ref original, ref rendered, ref parsed } => {
+                write!(f,
+                       "{:?} is rendered as `{}`, which parses as {:?}",
+                       original,
+                       rendered,
+                       parsed)
+            }
+            &Mismatch::NotIdempotent { ref combined, ref recombined } => {
+                write!(f,
+                       "combining {} again gives {}",
+                       render(combined),
+                       render(recombined))
+            }
+        }
+    }
+}
+
+impl ViewPath {
+    /// Check that rendering this view path and parsing the result gives it back.
+    pub fn verify_roundtrip(&self) -> Result<(), Mismatch> {
+        let rendered = self.to_string();
+        let parsed = ViewPath::from(&rendered[..]);
+        if parsed == *self {
+            Ok(())
+        } else {
+            Err(Mismatch::Roundtrip {
+                original: self.clone(),
+                rendered: rendered,
+                parsed: parsed,
+            })
+        }
+    }
+}
+
+/// Check that each of `inputs` and each of the imports they combine into (with the default
+/// config) round-trip through rendering and parsing, and that combining the combined imports
+/// again leaves them as they are. Meant for running over a project's imports in its own tests.
+pub fn verify(inputs: &[&ViewPath]) -> Result<(), Vec<Mismatch>> {
+    verify_with(inputs, &Config::default())
+}
+
+/// As `verify`, combining with `config`.
+pub fn verify_with(inputs: &[&ViewPath], config: &Config) -> Result<(), Vec<Mismatch>> {
+    let combined = combine_imports_with_config(inputs, config.clone());
+    let mut mismatches: Vec<Mismatch> = inputs.iter()
+        .cloned()
+        .chain(&combined)
+        .filter_map(|vp| vp.verify_roundtrip().err())
+        .collect();
+    let recombined = {
+        let combined: Vec<&ViewPath> = combined.iter().collect();
+        combine_imports_with_config(&combined, config.clone())
+    };
+    if recombined != combined {
+        mismatches.push(Mismatch::NotIdempotent {
+            combined: combined,
+            recombined: recombined,
+        });
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+// The things each name in each namespace refers to. Names whose namespaces aren't known are in
+// the `None` namespace.
+type Scope = BTreeMap<(String, Option<Namespace>), BTreeSet<Path>>;
+
+// What the names brought into scope by `vps` refer to, given the names in the modules they
+// import from. Explicit imports shadow the names globs would import in the same namespace.
+fn scope<S: SymbolTable>(vps: &[&ViewPath], symbols: &S) -> Scope {
+    let mut explicit = Scope::new();
+    let mut globs = vec![];
+    for simple in vps.iter().flat_map(|vp| vp.simple_paths()) {
+        match simple {
+            ViewPath::ViewPathSimple(path, rename) => {
+                let item = match path.last() {
+                    Some(item) => item.clone(),
+                    None => continue,
+                };
+                let name = rename.unwrap_or(item.clone());
+                let mut namespaces: Vec<_> = symbols.namespaces(&path[..path.len() - 1], &item)
+                    .into_iter()
+                    .map(Some)
+                    .collect();
+                if namespaces.is_empty() {
+                    namespaces.push(None);
+                }
+                for namespace in namespaces {
+                    explicit.entry((name.clone(), namespace))
+                        .or_insert_with(BTreeSet::new)
+                        .insert(path.clone());
+                }
+            }
+            ViewPath::ViewPathGlob(module) => globs.push(module),
+            _ => {}
+        }
+    }
+    let mut scope = explicit.clone();
+    for module in globs {
+        let names = symbols.names(&module);
+        if names.is_empty() {
+            scope.entry(("*".to_string(), None)).or_insert_with(BTreeSet::new).insert(module);
+            continue;
+        }
+        for (name, namespace) in names {
+            if explicit.contains_key(&(name.clone(), Some(namespace))) ||
+               explicit.contains_key(&(name.clone(), None)) {
+                continue;
+            }
+            let mut path = module.clone();
+            path.push(name.clone());
+            scope.entry((name, Some(namespace))).or_insert_with(BTreeSet::new).insert(path);
+        }
+    }
+    scope
+}
+
+/// Check that `combined` brings the same names into scope as `original`, each referring to the
+/// same things, with `symbols` giving the names in the modules globs import. Paths are compared
+/// as they're written, so imports respelt by the config (e.g. with `crate_name`) diverge. The
+/// error is the first name (in sorted order) that diverges.
+pub fn verify_equivalent<S: SymbolTable>(original: &[&ViewPath],
+                                         combined: &[ViewPath],
+                                         symbols: &S)
+                                         -> Result<(), Divergence> {
+    let combined: Vec<&ViewPath> = combined.iter().collect();
+    let before = scope(original, symbols);
+    let after = scope(&combined, symbols);
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    for key in keys {
+        let original = before.get(key).cloned().unwrap_or_default();
+        let combined = after.get(key).cloned().unwrap_or_default();
+        if original != combined {
+            return Err(Divergence {
+                name: key.0.clone(),
+                namespace: key.1,
+                original: original.into_iter().collect(),
+                combined: combined.into_iter().collect(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Item, as_path};
+
+    fn symbols(module: &[String]) -> Vec<(String, Namespace)> {
+        match &module.join("::")[..] {
+            "a" => vec![("b".to_string(), Namespace::Type), ("c".to_string(), Namespace::Value)],
+            "x" => vec![("b".to_string(), Namespace::Type)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn roundtrips() {
+        let vps = [&ViewPath::from("a::b::c"),
+                   &ViewPath::from("a::b::{d, e as f}"),
+                   &ViewPath::from("a::b::*"),
+                   &ViewPath::from("x::{self, y::{z, w}}")];
+        assert_eq!(verify(&vps), Ok(()));
+        assert_eq!(verify_with(&vps, &Config::compact()), Ok(()));
+        let list = ViewPath::ViewPathList(as_path("a"), vec![Item::from("self"), Item::from("*")]);
+        match list.verify_roundtrip() {
+            Err(Mismatch::Roundtrip { rendered, .. }) => assert_eq!(rendered, "a::{self, *}"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+    #[test]
+    fn equivalence() {
+        let vps = [&ViewPath::from("a::*"),
+                   &ViewPath::from("a::c"),
+                   &ViewPath::from("x::b"),
+                   &ViewPath::from("p::q"),
+                   &ViewPath::from("p::r as s")];
+        // Absorbing `a::c` into the glob is neutral, but absorbing `x::b` wouldn't be.
+        let combined = combine_imports_with_config(&vps, Config::compact());
+        assert_eq!(verify_equivalent(&vps, &combined, &symbols), Ok(()));
+        let combined = vec![ViewPath::from("a::*"), ViewPath::from("p::{q, r as s}")];
+        let divergence = verify_equivalent(&vps, &combined, &symbols).unwrap_err();
+        assert_eq!(divergence.name, "b");
+        assert_eq!(divergence.namespace, Some(Namespace::Type));
+        assert_eq!(divergence.combined, vec![as_path("a::b")]);
+        assert_eq!(divergence.to_string(),
+                   "`b` (a type) refers to `x::b` before combining, but `a::b` after");
+        let combined = vec![ViewPath::from("a::*"),
+                            ViewPath::from("x::b"),
+                            ViewPath::from("p::{q, r}")];
+        let divergence = verify_equivalent(&vps, &combined, &symbols).unwrap_err();
+        assert_eq!((divergence.name.as_str(), divergence.combined.len()), ("r", 1));
+    }
+}
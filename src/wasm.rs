@@ -0,0 +1,83 @@
+use js_sys::JSON;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use {Config, combine_source, source_edits, utf16_position};
+
+// The config in `config_json`, or the default config if it's blank.
+fn config_from_json(config_json: &str) -> Result<Config, String> {
+    let config = if config_json.trim().is_empty() {
+        Config::default()
+    } else {
+        ::serde_json::from_str(config_json).map_err(|e| format!("invalid config: {}", e))?
+    };
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+// Combine `source` with the config in `config_json`, or the default config if it's blank.
+fn combine_with_json(source: &str, config_json: &str) -> Result<String, String> {
+    Ok(combine_source(source, &config_from_json(config_json)?))
+}
+
+// The edits combining `source`, as LSP `TextEdit`s.
+fn edits_with_json(source: &str, config_json: &str) -> Result<Value, String> {
+    let position = |offset| {
+        let (line, character) = utf16_position(source, offset);
+        json!({ "line": line, "character": character })
+    };
+    let edits: Vec<Value> = source_edits(source, &config_from_json(config_json)?)
+        .iter()
+        .map(|edit| {
+            json!({
+                "range": { "start": position(edit.start), "end": position(edit.end) },
+                "newText": edit.replacement,
+            })
+        })
+        .collect();
+    Ok(Value::Array(edits))
+}
+
+/// Combine the `use` statements in `source`, for a playground in the browser. `config_json` is a
+/// config as JSON (as `Config` is serialized, with missing options taking their defaults), or
+/// is empty for the default config. An invalid config is thrown as an error.
+#[wasm_bindgen]
+pub fn combine(source: &str, config_json: &str) -> Result<String, JsValue> {
+    combine_with_json(source, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// The edits that combine the `use` statements in `source`, as an array of LSP `TextEdit`s
+/// (`{ range: { start, end }, newText }`, with zero-based lines and UTF-16 columns), for an
+/// editor extension to apply, e.g. with `vscode.TextEdit.replace`. `config_json` is as for
+/// `combine`. Built as a `cdylib` and run through `wasm-bindgen --target nodejs`, this runs in
+/// the extension's own process.
+#[wasm_bindgen(js_name = computeEdits)]
+pub fn compute_edits(source: &str, config_json: &str) -> Result<JsValue, JsValue> {
+    let edits = edits_with_json(source, config_json).map_err(|e| JsValue::from_str(&e))?;
+    JSON::parse(&edits.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn json_configs() {
+        let source = "use a::b;\nuse a::c;\n";
+        assert_eq!(combine_with_json(source, ""), Ok(source.to_string()));
+        assert_eq!(combine_with_json(source, r#"{ "min_list_length": 2 }"#),
+                   Ok("use a::{b, c};\n".to_string()));
+        assert!(combine_with_json(source, r#"{ "min_list_length": 0 }"#).is_err());
+        assert!(combine_with_json(source, "{").unwrap_err().starts_with("invalid config"));
+
+        let source = "// é\nuse a::b;\nuse a::c;\nuse a::d;\n";
+        assert_eq!(edits_with_json(source, ""),
+                   Ok(json!([{
+                       "range": {
+                           "start": { "line": 1, "character": 0 },
+                           "end": { "line": 3, "character": 9 },
+                       },
+                       "newText": "use a::{b, c, d};",
+                   }])));
+        assert_eq!(utf16_position(source, 5), (0, 4));
+    }
+}